@@ -1,6 +1,9 @@
 // cargo run --example hello_world
 
-use http_server::{Router, Server, responses::{BadRequestError, OkResponse}};
+use http_server::{
+    Router, Server,
+    responses::{BadRequestError, OkResponse},
+};
 
 use std::sync::Arc;
 
@@ -11,10 +14,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     router.get(
         "/",
         Arc::new(|req, _| {
-            if let Some(value) = req.query().get("error") {
-                if value == "true" {
-                    return BadRequestError::with_message("Bad request example").into();
-                }
+            if let Some(value) = req.query().get("error")
+                && value == "true"
+            {
+                return BadRequestError::with_message("Bad request example").into();
             }
 
             OkResponse::from("Hello, World!").into()
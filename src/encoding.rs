@@ -0,0 +1,109 @@
+use std::pin::Pin;
+
+use tokio::io::{AsyncBufRead, AsyncRead};
+
+/// A content coding the server can apply to, or remove from, message bodies.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum Encoding {
+    #[cfg(feature = "zstd")]
+    Zstd,
+    #[cfg(feature = "br")]
+    Brotli,
+    #[cfg(feature = "gzip")]
+    Gzip,
+    #[cfg(feature = "deflate")]
+    Deflate,
+}
+
+impl Encoding {
+    /// Encodings enabled in this build, in order of preference.
+    pub(crate) const SUPPORTED: &[Encoding] = &[
+        #[cfg(feature = "zstd")]
+        Encoding::Zstd,
+        #[cfg(feature = "br")]
+        Encoding::Brotli,
+        #[cfg(feature = "gzip")]
+        Encoding::Gzip,
+        #[cfg(feature = "deflate")]
+        Encoding::Deflate,
+    ];
+
+    /// Returns the supported encoding named by a content coding token.
+    pub(crate) fn from_token(token: &str) -> Option<Encoding> {
+        Encoding::SUPPORTED
+            .iter()
+            .find(|encoding| encoding.as_str().eq_ignore_ascii_case(token.trim()))
+            .copied()
+    }
+
+    pub(crate) fn as_str(&self) -> &'static str {
+        match *self {
+            #[cfg(feature = "zstd")]
+            Encoding::Zstd => "zstd",
+            #[cfg(feature = "br")]
+            Encoding::Brotli => "br",
+            #[cfg(feature = "gzip")]
+            Encoding::Gzip => "gzip",
+            #[cfg(feature = "deflate")]
+            Encoding::Deflate => "deflate",
+        }
+    }
+
+    #[cfg_attr(
+        not(any(
+            feature = "zstd",
+            feature = "br",
+            feature = "gzip",
+            feature = "deflate"
+        )),
+        allow(unused_variables)
+    )]
+    pub(crate) fn encode<'a, R: AsyncBufRead + Send + 'a>(
+        &self,
+        reader: R,
+    ) -> Pin<Box<dyn AsyncRead + Send + 'a>> {
+        match *self {
+            #[cfg(feature = "zstd")]
+            Encoding::Zstd => Box::pin(async_compression::tokio::bufread::ZstdEncoder::new(reader)),
+            #[cfg(feature = "br")]
+            Encoding::Brotli => Box::pin(async_compression::tokio::bufread::BrotliEncoder::new(
+                reader,
+            )),
+            #[cfg(feature = "gzip")]
+            Encoding::Gzip => Box::pin(async_compression::tokio::bufread::GzipEncoder::new(reader)),
+            #[cfg(feature = "deflate")]
+            Encoding::Deflate => {
+                Box::pin(async_compression::tokio::bufread::ZlibEncoder::new(reader))
+            }
+        }
+    }
+
+    #[cfg_attr(
+        not(any(
+            feature = "zstd",
+            feature = "br",
+            feature = "gzip",
+            feature = "deflate"
+        )),
+        allow(unused_variables)
+    )]
+    pub(crate) fn decode<'a, R: AsyncBufRead + Send + 'a>(
+        &self,
+        reader: R,
+    ) -> Pin<Box<dyn AsyncRead + Send + 'a>> {
+        match *self {
+            #[cfg(feature = "zstd")]
+            Encoding::Zstd => Box::pin(async_compression::tokio::bufread::ZstdDecoder::new(reader)),
+            #[cfg(feature = "br")]
+            Encoding::Brotli => Box::pin(async_compression::tokio::bufread::BrotliDecoder::new(
+                reader,
+            )),
+            #[cfg(feature = "gzip")]
+            Encoding::Gzip => Box::pin(async_compression::tokio::bufread::GzipDecoder::new(reader)),
+            #[cfg(feature = "deflate")]
+            Encoding::Deflate => {
+                Box::pin(async_compression::tokio::bufread::ZlibDecoder::new(reader))
+            }
+        }
+    }
+}
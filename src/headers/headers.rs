@@ -45,10 +45,10 @@ impl Headers {
     pub fn get<T: std::str::FromStr>(&self, key: &str) -> Option<T> {
         let local_key = key.to_lowercase();
 
-        if let Some((_, value)) = self.data.get(&local_key) {
-            if let Ok(parsed) = value.trim().parse::<T>() {
-                return Some(parsed);
-            }
+        if let Some((_, value)) = self.data.get(&local_key)
+            && let Ok(parsed) = value.trim().parse::<T>()
+        {
+            return Some(parsed);
         }
 
         None
@@ -75,6 +75,13 @@ impl Headers {
             .or_insert((key.to_string(), value.to_string()));
     }
 
+    /// Removes a header, returning its value if it was present.
+    pub fn remove(&mut self, key: &str) -> Option<String> {
+        let local_key = key.to_lowercase();
+
+        self.data.remove(&local_key).map(|(_, value)| value)
+    }
+
     /// Checks if a header exists.
     pub fn contains(&self, key: &str) -> bool {
         let local_key = key.to_lowercase();
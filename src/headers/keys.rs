@@ -1,3 +1,25 @@
 pub const CONTENT_LENGTH_HEADER: &str = "Content-Length";
 pub const CONTENT_TYPE_KEY: &str = "Content-Type";
 pub const CONNECTION_HEADER: &str = "Connection";
+pub const ETAG_HEADER: &str = "ETag";
+pub const LAST_MODIFIED_HEADER: &str = "Last-Modified";
+pub const IF_NONE_MATCH_HEADER: &str = "If-None-Match";
+pub const IF_MODIFIED_SINCE_HEADER: &str = "If-Modified-Since";
+pub const RANGE_HEADER: &str = "Range";
+pub const IF_RANGE_HEADER: &str = "If-Range";
+pub const ACCEPT_RANGES_HEADER: &str = "Accept-Ranges";
+pub const CONTENT_RANGE_HEADER: &str = "Content-Range";
+pub const IF_MATCH_HEADER: &str = "If-Match";
+pub const IF_UNMODIFIED_SINCE_HEADER: &str = "If-Unmodified-Since";
+pub const CONTENT_ENCODING_HEADER: &str = "Content-Encoding";
+pub const ACCEPT_ENCODING_HEADER: &str = "Accept-Encoding";
+pub const VARY_HEADER: &str = "Vary";
+pub const TRANSFER_ENCODING_HEADER: &str = "Transfer-Encoding";
+pub const ACCEPT_HEADER: &str = "Accept";
+pub const AUTHORIZATION_HEADER: &str = "Authorization";
+pub const USER_AGENT_HEADER: &str = "User-Agent";
+pub const UPGRADE_HEADER: &str = "Upgrade";
+pub const LAST_EVENT_ID_HEADER: &str = "Last-Event-ID";
+pub const CACHE_CONTROL_HEADER: &str = "Cache-Control";
+pub const REFERER_HEADER: &str = "Referer";
+pub const RETRY_AFTER_HEADER: &str = "Retry-After";
@@ -1,4 +1,8 @@
+#[allow(clippy::module_inception)]
 mod headers;
 pub mod keys;
+pub(crate) mod quality;
+mod typed;
 
 pub use headers::Headers;
+pub use typed::{Authorization, ContentType, LastEventId, TypedHeader, UserAgent};
@@ -0,0 +1,86 @@
+/// An element of a header list weighted with q-values, such as `Accept` or `Accept-Encoding`.
+#[derive(Debug, PartialEq)]
+pub(crate) struct QualityItem {
+    /// The lowercased token or media range.
+    pub(crate) value: String,
+    /// Parameters preceding the weight, with lowercased names.
+    pub(crate) params: Vec<(String, String)>,
+    pub(crate) quality: f32,
+}
+
+const QUALITY_PARAM: &str = "q";
+
+/// Parses a comma separated list of values with optional `;q=` weights.
+///
+/// Elements with an invalid weight are skipped, and elements without one default to 1.
+/// https://datatracker.ietf.org/doc/html/rfc9110#name-quality-values
+pub(crate) fn parse(header: &str) -> Vec<QualityItem> {
+    let mut items = Vec::new();
+
+    'items: for element in header.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let mut parts = element.split(';').map(str::trim);
+        let value = parts.next().unwrap_or("").to_ascii_lowercase();
+
+        let mut params = Vec::new();
+        let mut quality = 1.0;
+
+        for param in parts.filter(|p| !p.is_empty()) {
+            let (name, param_value) = param.split_once('=').unwrap_or((param, ""));
+            let name = name.trim().to_ascii_lowercase();
+            let param_value = param_value.trim().trim_matches('"');
+
+            if name == QUALITY_PARAM {
+                match param_value.parse::<f32>() {
+                    Ok(q) if (0.0..=1.0).contains(&q) => quality = q,
+                    _ => continue 'items,
+                }
+
+                // Anything after the weight is an extension parameter
+                break;
+            }
+
+            params.push((name, param_value.to_string()));
+        }
+
+        items.push(QualityItem {
+            value,
+            params,
+            quality,
+        });
+    }
+
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_quality_list() {
+        let items = parse("text/html;level=1;q=0.5, Application/JSON, gzip;q=0");
+
+        assert_eq!(items.len(), 3);
+
+        assert_eq!(items[0].value, "text/html");
+        assert_eq!(
+            items[0].params,
+            vec![("level".to_string(), "1".to_string())]
+        );
+        assert_eq!(items[0].quality, 0.5);
+
+        assert_eq!(items[1].value, "application/json");
+        assert_eq!(items[1].quality, 1.0);
+
+        assert_eq!(items[2].value, "gzip");
+        assert_eq!(items[2].quality, 0.0);
+    }
+
+    #[test]
+    fn test_parse_skips_invalid_weights() {
+        let items = parse("br;q=2, gzip;q=abc, deflate");
+
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].value, "deflate");
+    }
+}
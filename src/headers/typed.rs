@@ -0,0 +1,137 @@
+use super::keys;
+
+/// A header that can be parsed from its raw value, for use with the
+/// [`Header`](crate::extract::Header) extractor.
+pub trait TypedHeader: Sized {
+    /// Returns the name of the header.
+    fn name() -> &'static str;
+
+    /// Parses the value of the header, returning `None` if it is malformed.
+    fn decode(value: &str) -> Option<Self>;
+}
+
+/// The `Authorization` header, split into its scheme and credentials.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Authorization {
+    scheme: String,
+    credentials: String,
+}
+
+impl Authorization {
+    /// Returns the authentication scheme, such as `Bearer` or `Basic`.
+    pub fn scheme(&self) -> &str {
+        &self.scheme
+    }
+
+    /// Returns the credentials following the scheme.
+    pub fn credentials(&self) -> &str {
+        &self.credentials
+    }
+
+    /// Returns the token if the header uses the `Bearer` scheme.
+    pub fn bearer(&self) -> Option<&str> {
+        if self.scheme.eq_ignore_ascii_case("Bearer") {
+            Some(&self.credentials)
+        } else {
+            None
+        }
+    }
+}
+
+impl TypedHeader for Authorization {
+    fn name() -> &'static str {
+        keys::AUTHORIZATION_HEADER
+    }
+
+    fn decode(value: &str) -> Option<Self> {
+        let (scheme, credentials) = value.trim().split_once(' ')?;
+        let credentials = credentials.trim();
+
+        if scheme.is_empty() || credentials.is_empty() {
+            return None;
+        }
+
+        Some(Authorization {
+            scheme: scheme.to_string(),
+            credentials: credentials.to_string(),
+        })
+    }
+}
+
+/// The `Content-Type` header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentType(pub String);
+
+impl ContentType {
+    /// Returns the media type without parameters, such as `application/json`.
+    pub fn media_type(&self) -> &str {
+        self.0.split(';').next().unwrap_or(&self.0).trim()
+    }
+}
+
+impl TypedHeader for ContentType {
+    fn name() -> &'static str {
+        keys::CONTENT_TYPE_KEY
+    }
+
+    fn decode(value: &str) -> Option<Self> {
+        let value = value.trim();
+        if value.is_empty() {
+            return None;
+        }
+
+        Some(ContentType(value.to_string()))
+    }
+}
+
+/// The `User-Agent` header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserAgent(pub String);
+
+impl TypedHeader for UserAgent {
+    fn name() -> &'static str {
+        keys::USER_AGENT_HEADER
+    }
+
+    fn decode(value: &str) -> Option<Self> {
+        Some(UserAgent(value.trim().to_string()))
+    }
+}
+
+/// The `Last-Event-ID` header, sent by clients reconnecting to an event stream with the
+/// id of the last event they received.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LastEventId(pub String);
+
+impl TypedHeader for LastEventId {
+    fn name() -> &'static str {
+        keys::LAST_EVENT_ID_HEADER
+    }
+
+    fn decode(value: &str) -> Option<Self> {
+        Some(LastEventId(value.trim().to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_authorization() {
+        let auth = Authorization::decode("Bearer abc.def").unwrap();
+        assert_eq!(auth.scheme(), "Bearer");
+        assert_eq!(auth.bearer(), Some("abc.def"));
+
+        let auth = Authorization::decode("Basic dXNlcjpwYXNz").unwrap();
+        assert_eq!(auth.bearer(), None);
+
+        assert!(Authorization::decode("Bearer").is_none());
+    }
+
+    #[test]
+    fn test_content_type_media_type() {
+        let content_type = ContentType::decode("application/json; charset=utf-8").unwrap();
+        assert_eq!(content_type.media_type(), "application/json");
+    }
+}
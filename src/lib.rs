@@ -1,10 +1,24 @@
+mod encoding;
 pub mod headers;
+mod percent;
 mod request;
 mod response;
 mod server;
 
-pub use request::Request;
+pub use request::{Extensions, Request};
 pub use response::*;
 pub use server::*;
 
+pub use server::access_log;
+pub use server::extract;
+pub use server::fs;
+pub use server::limits;
+pub use server::metrics;
+pub use server::request_id;
 pub use server::responses;
+#[cfg(feature = "tls")]
+pub use server::tls;
+pub use server::trace;
+pub use server::upgrade;
+#[cfg(feature = "websocket")]
+pub use server::websocket;
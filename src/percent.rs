@@ -0,0 +1,61 @@
+//! Decoding of percent-encoded URL components.
+
+fn hex_value(byte: u8) -> Option<u8> {
+    match byte {
+        b'0'..=b'9' => Some(byte - b'0'),
+        b'a'..=b'f' => Some(byte - b'a' + 10),
+        b'A'..=b'F' => Some(byte - b'A' + 10),
+        _ => None,
+    }
+}
+
+fn decode_bytes(value: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = Vec::with_capacity(value.len());
+    let mut i = 0;
+
+    while i < value.len() {
+        if value[i] == b'%' {
+            let high = hex_value(*value.get(i + 1)?)?;
+            let low = hex_value(*value.get(i + 2)?)?;
+            decoded.push(high << 4 | low);
+            i += 3;
+        } else {
+            decoded.push(value[i]);
+            i += 1;
+        }
+    }
+
+    Some(decoded)
+}
+
+/// Decodes a path segment, returning `None` for malformed escapes or invalid UTF-8.
+pub(crate) fn percent_decode(value: &str) -> Option<String> {
+    String::from_utf8(decode_bytes(value.as_bytes())?).ok()
+}
+
+/// Decodes a name or value of an `application/x-www-form-urlencoded` query string, where
+/// `+` stands for a space. Malformed escapes are kept as they are and invalid UTF-8 is
+/// replaced, like browsers do.
+pub(crate) fn form_decode(value: &str) -> String {
+    let value = value.replace('+', " ");
+
+    match decode_bytes(value.as_bytes()) {
+        Some(decoded) => String::from_utf8_lossy(&decoded).into_owned(),
+        None => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(percent_decode("J%C3%B6rg").unwrap(), "Jörg");
+        assert_eq!(percent_decode("a+b").unwrap(), "a+b");
+        assert!(percent_decode("100%").is_none());
+
+        assert_eq!(form_decode("a%20b+c%2Bd"), "a b c+d");
+        assert_eq!(form_decode("100%"), "100%");
+    }
+}
@@ -0,0 +1,154 @@
+use tokio::io::AsyncReadExt;
+
+use crate::{
+    Request,
+    encoding::Encoding,
+    headers::keys,
+    responses::{BadRequestError, ContentTooLargeError, HttpResponse, UnsupportedMediaTypeError},
+};
+
+const IDENTITY: &str = "identity";
+
+fn accepted_encodings() -> String {
+    match Encoding::SUPPORTED {
+        [] => IDENTITY.to_string(),
+        supported => supported
+            .iter()
+            .map(Encoding::as_str)
+            .collect::<Vec<&str>>()
+            .join(", "),
+    }
+}
+
+impl Request {
+    /// Removes the `Content-Encoding` of the body, so handlers always receive the
+    /// original bytes.
+    ///
+    /// Unknown codings are rejected with 415 Unsupported Media Type, and bodies that
+    /// decode to more than `max_size` bytes with 413 Content Too Large.
+    pub(crate) async fn decompress_body(
+        &mut self,
+        max_size: u64,
+    ) -> Result<(), Box<dyn HttpResponse>> {
+        let header = match self.headers.get::<String>(keys::CONTENT_ENCODING_HEADER) {
+            Some(header) => header,
+            None => return Ok(()),
+        };
+
+        let mut encodings = Vec::new();
+        for coding in header.split(',').map(str::trim) {
+            if coding.is_empty() || coding.eq_ignore_ascii_case(IDENTITY) {
+                continue;
+            }
+
+            match Encoding::from_token(coding) {
+                Some(encoding) => encodings.push(encoding),
+                None => {
+                    return Err(UnsupportedMediaTypeError::with_message(format!(
+                        "Unsupported content encoding: {}",
+                        coding
+                    ))
+                    .with_accept_encoding(accepted_encodings())
+                    .into());
+                }
+            }
+        }
+
+        let mut body = std::mem::take(&mut self.body);
+
+        // Codings are listed in the order they were applied, so they are removed in reverse
+        for encoding in encodings.iter().rev() {
+            let mut decoded = Vec::new();
+
+            // Reading one byte past the limit is enough to tell that it was exceeded
+            encoding
+                .decode(body.as_slice())
+                .take(max_size.saturating_add(1))
+                .read_to_end(&mut decoded)
+                .await
+                .map_err(|_| {
+                    BadRequestError::with_message(format!(
+                        "Request body is not valid {} data",
+                        encoding.as_str()
+                    ))
+                })?;
+
+            if decoded.len() as u64 > max_size {
+                return Err(ContentTooLargeError::with_message(format!(
+                    "Decompressed request body exceeds {} bytes",
+                    max_size
+                ))
+                .into());
+            }
+
+            body = decoded;
+        }
+
+        self.headers.remove(keys::CONTENT_ENCODING_HEADER);
+        self.headers
+            .set(keys::CONTENT_LENGTH_HEADER, &body.len().to_string());
+        self.body = body;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::StatusCode;
+
+    use super::*;
+
+    async fn request(headers: &str, body: &[u8]) -> Request {
+        let mut raw = format!(
+            "POST / HTTP/1.1\r\n{}Content-Length: {}\r\n\r\n",
+            headers,
+            body.len()
+        )
+        .into_bytes();
+        raw.extend_from_slice(body);
+
+        Request::from_reader(raw.as_slice()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_identity_body_is_untouched() {
+        let mut req = request("Content-Encoding: identity\r\n", b"hello").await;
+
+        assert!(req.decompress_body(1024).await.is_ok());
+        assert_eq!(req.body(), b"hello");
+        assert!(!req.headers().contains(keys::CONTENT_ENCODING_HEADER));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_encoding_is_rejected() {
+        let mut req = request("Content-Encoding: compress\r\n", b"hello").await;
+
+        let error = req.decompress_body(1024).await.unwrap_err();
+        assert_eq!(error.status_code(), StatusCode::UnsupportedMediaType);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[tokio::test]
+    async fn test_gzip_body_is_decoded() {
+        let original = "hello ".repeat(100);
+        let mut compressed = Vec::new();
+        Encoding::Gzip
+            .encode(original.as_bytes())
+            .read_to_end(&mut compressed)
+            .await
+            .unwrap();
+
+        let mut req = request("Content-Encoding: gzip\r\n", &compressed).await;
+        assert!(req.decompress_body(1024).await.is_ok());
+        assert_eq!(req.body(), original.as_bytes());
+        assert_eq!(
+            req.headers().get::<usize>(keys::CONTENT_LENGTH_HEADER),
+            Some(original.len())
+        );
+
+        let mut req = request("Content-Encoding: gzip\r\n", &compressed).await;
+        let error = req.decompress_body(100).await.unwrap_err();
+        assert_eq!(error.status_code(), StatusCode::ContentTooLarge);
+    }
+}
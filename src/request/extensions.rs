@@ -0,0 +1,76 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+};
+
+/// A map holding at most one value of each type, used to attach data to a request.
+#[derive(Default)]
+pub struct Extensions {
+    map: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl Extensions {
+    /// Creates an empty map.
+    pub fn new() -> Self {
+        Extensions {
+            map: HashMap::new(),
+        }
+    }
+
+    /// Inserts a value, returning the previous value of the same type.
+    pub fn insert<T: Send + Sync + 'static>(&mut self, value: T) -> Option<T> {
+        self.map
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .and_then(|previous| previous.downcast().ok())
+            .map(|previous| *previous)
+    }
+
+    /// Returns a reference to the value of type `T`.
+    pub fn get<T: Send + Sync + 'static>(&self) -> Option<&T> {
+        self.map
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref())
+    }
+
+    /// Returns a mutable reference to the value of type `T`.
+    pub fn get_mut<T: Send + Sync + 'static>(&mut self) -> Option<&mut T> {
+        self.map
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_mut())
+    }
+
+    /// Removes and returns the value of type `T`.
+    pub fn remove<T: Send + Sync + 'static>(&mut self) -> Option<T> {
+        self.map
+            .remove(&TypeId::of::<T>())
+            .and_then(|value| value.downcast().ok())
+            .map(|value| *value)
+    }
+
+    /// Returns true if the map holds a value of type `T`.
+    pub fn contains<T: Send + Sync + 'static>(&self) -> bool {
+        self.map.contains_key(&TypeId::of::<T>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct User(&'static str);
+
+    #[test]
+    fn test_extensions() {
+        let mut extensions = Extensions::new();
+
+        assert_eq!(extensions.insert(User("ada")), None);
+        assert_eq!(extensions.insert(User("grace")), Some(User("ada")));
+        assert_eq!(extensions.insert(7u32), None);
+
+        assert_eq!(extensions.get::<User>(), Some(&User("grace")));
+        *extensions.get_mut::<u32>().unwrap() += 1;
+        assert_eq!(extensions.remove::<u32>(), Some(8));
+        assert!(!extensions.contains::<u32>());
+    }
+}
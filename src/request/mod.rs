@@ -1,6 +1,11 @@
 mod body;
+mod decompression;
+mod extensions;
+mod negotiation;
+#[allow(clippy::module_inception)]
 mod request;
 mod request_line;
 mod request_state;
 
+pub use extensions::Extensions;
 pub use request::Request;
@@ -0,0 +1,173 @@
+use crate::{
+    Request,
+    headers::{keys, quality},
+    responses::NotAcceptableError,
+};
+
+const WILDCARD: &str = "*";
+
+struct MediaType<'a> {
+    kind: String,
+    subtype: String,
+    params: Vec<(String, String)>,
+    original: &'a str,
+}
+
+fn parse_media_type(offer: &str) -> Option<MediaType<'_>> {
+    let item = quality::parse(offer).into_iter().next()?;
+    let (kind, subtype) = item.value.split_once('/')?;
+
+    Some(MediaType {
+        kind: kind.trim().to_string(),
+        subtype: subtype.trim().to_string(),
+        params: item.params,
+        original: offer,
+    })
+}
+
+/// Returns how specifically a media range from `Accept` matches an offered type, or
+/// `None` if it does not match. Exact types beat `type/*`, which beats `*/*`, and ranges
+/// with parameters beat those without.
+fn specificity(range: &quality::QualityItem, offer: &MediaType) -> Option<usize> {
+    let (kind, subtype) = range.value.split_once('/')?;
+
+    let score = match (kind, subtype) {
+        (WILDCARD, WILDCARD) => 0,
+        (kind, WILDCARD) if kind == offer.kind => 1,
+        (kind, subtype) if kind == offer.kind && subtype == offer.subtype => 2,
+        _ => return None,
+    };
+
+    let params_match = range.params.iter().all(|(name, value)| {
+        offer.params.iter().any(|(offer_name, offer_value)| {
+            offer_name == name && offer_value.eq_ignore_ascii_case(value)
+        })
+    });
+
+    if !params_match {
+        return None;
+    }
+
+    Some(score * 100 + range.params.len())
+}
+
+/// Picks the offer with the highest weight in an `Accept` header, preferring earlier
+/// offers on ties.
+fn negotiate<'a>(header: &str, offers: &[&'a str]) -> Option<&'a str> {
+    let ranges = quality::parse(header);
+    let mut best: Option<(&'a str, f32)> = None;
+
+    for offer in offers.iter().filter_map(|offer| parse_media_type(offer)) {
+        let quality = ranges
+            .iter()
+            .filter_map(|range| specificity(range, &offer).map(|score| (score, range.quality)))
+            .max_by_key(|(score, _)| *score)
+            .map(|(_, quality)| quality)
+            .unwrap_or(0.0);
+
+        if quality > 0.0 && best.is_none_or(|(_, best_quality)| quality > best_quality) {
+            best = Some((offer.original, quality));
+        }
+    }
+
+    best.map(|(offer, _)| offer)
+}
+
+impl Request {
+    /// Picks the best representation for the request among the media types a handler
+    /// can produce, based on the `Accept` header.
+    ///
+    /// Offers are given in order of preference, which breaks ties between equally
+    /// weighted types. Without an `Accept` header the first offer is chosen. Responses
+    /// that depend on the result should carry a `Vary: Accept` header.
+    pub fn negotiate<'a>(&self, offers: &[&'a str]) -> Result<&'a str, NotAcceptableError> {
+        let not_acceptable = || {
+            NotAcceptableError::with_message(format!(
+                "None of the available representations are acceptable: {}",
+                offers.join(", ")
+            ))
+        };
+
+        let header = match self.headers.get::<String>(keys::ACCEPT_HEADER) {
+            Some(header) if !header.trim().is_empty() => header,
+            _ => return offers.first().copied().ok_or_else(not_acceptable),
+        };
+
+        negotiate(&header, offers).ok_or_else(not_acceptable)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OFFERS: [&str; 3] = ["application/json", "application/xml", "text/csv"];
+
+    #[test]
+    fn test_negotiate_exact_type() {
+        assert_eq!(negotiate("text/csv", &OFFERS), Some("text/csv"));
+        assert_eq!(
+            negotiate("application/XML", &OFFERS),
+            Some("application/xml")
+        );
+    }
+
+    #[test]
+    fn test_negotiate_with_quality_values() {
+        assert_eq!(
+            negotiate("application/json;q=0.5, text/csv;q=0.9", &OFFERS),
+            Some("text/csv")
+        );
+        assert_eq!(
+            negotiate("*/*;q=0.1, application/json;q=0", &OFFERS),
+            Some("application/xml")
+        );
+    }
+
+    #[test]
+    fn test_negotiate_with_wildcards() {
+        assert_eq!(negotiate("*/*", &OFFERS), Some("application/json"));
+        assert_eq!(negotiate("text/*", &OFFERS), Some("text/csv"));
+
+        // The most specific range decides the weight of an offer
+        assert_eq!(
+            negotiate("application/*;q=0.2, application/xml, */*;q=0.5", &OFFERS),
+            Some("application/xml")
+        );
+    }
+
+    #[test]
+    fn test_negotiate_with_params() {
+        let offers = ["text/html; level=1", "text/html"];
+
+        assert_eq!(
+            negotiate("text/html;level=1", &offers),
+            Some("text/html; level=1")
+        );
+        assert_eq!(
+            negotiate("text/html;level=1;q=0.1, text/html", &offers),
+            Some("text/html")
+        );
+    }
+
+    #[test]
+    fn test_negotiate_without_match() {
+        assert_eq!(negotiate("image/png", &OFFERS), None);
+        assert_eq!(negotiate("text/csv;q=0", &OFFERS), None);
+        assert_eq!(negotiate("*/*", &[]), None);
+    }
+
+    #[tokio::test]
+    async fn test_request_negotiate() {
+        let req = Request::from_reader(&b"GET / HTTP/1.1\r\nAccept: text/*\r\n\r\n"[..])
+            .await
+            .unwrap();
+        assert_eq!(req.negotiate(&OFFERS).ok(), Some("text/csv"));
+        assert!(req.negotiate(&["application/json"]).is_err());
+
+        let req = Request::from_reader(&b"GET / HTTP/1.1\r\n\r\n"[..])
+            .await
+            .unwrap();
+        assert_eq!(req.negotiate(&OFFERS).ok(), Some("application/json"));
+    }
+}
@@ -1,9 +1,10 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, net::SocketAddr};
 
 use tokio::io::AsyncReadExt;
 
 use crate::headers::{self, Headers};
 
+use super::Extensions;
 use super::body;
 use super::request_line::RequestLine;
 use super::request_state::RequestState;
@@ -11,11 +12,18 @@ use super::request_state::RequestState;
 /// Represents an HTTP request.
 pub struct Request {
     method: String,
+    target: String,
     path: String,
     version: String,
     query: HashMap<String, String>,
-    headers: Headers,
-    body: Vec<u8>,
+    query_pairs: Vec<(String, String)>,
+    params: Vec<(String, String)>,
+    route: Option<String>,
+    request_id: Option<String>,
+    pub(super) headers: Headers,
+    pub(super) body: Vec<u8>,
+    extensions: Extensions,
+    peer_addr: Option<SocketAddr>,
     state: RequestState,
 }
 
@@ -25,11 +33,18 @@ impl Request {
     fn new() -> Self {
         Request {
             method: String::new(),
+            target: String::new(),
             path: String::new(),
             version: String::new(),
             query: HashMap::new(),
+            query_pairs: Vec::new(),
+            params: Vec::new(),
+            route: None,
+            request_id: None,
             headers: Headers::new(),
             body: Vec::new(),
+            extensions: Extensions::new(),
+            peer_addr: None,
             state: RequestState::StateInit,
         }
     }
@@ -48,6 +63,12 @@ impl Request {
         &self.method
     }
 
+    /// Returns the target of the request as sent by the client, including the query
+    /// string.
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
     /// Returns the path of the request.
     pub fn path(&self) -> &str {
         &self.path
@@ -63,16 +84,107 @@ impl Request {
         &self.body
     }
 
-    /// Returns the query parameters of the request.
+    /// Returns the decoded query parameters of the request. Only the last value of a
+    /// repeated parameter is kept.
     pub fn query(&self) -> &HashMap<String, String> {
         &self.query
     }
 
+    /// Returns the decoded query parameters of the request in the order they were sent,
+    /// including every value of a repeated parameter.
+    pub fn query_pairs(&self) -> &[(String, String)] {
+        &self.query_pairs
+    }
+
+    /// Returns the parameters captured from the matched route path, in the order they
+    /// appear in the route.
+    pub fn params(&self) -> &[(String, String)] {
+        &self.params
+    }
+
+    /// Returns the value of a parameter captured from the matched route path.
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the path of the route that matched the request, such as `/users/:id`.
+    pub fn route(&self) -> Option<&str> {
+        self.route.as_deref()
+    }
+
+    /// Returns the id of the request, when the server assigns request ids.
+    pub fn request_id(&self) -> Option<&str> {
+        self.request_id.as_deref()
+    }
+
+    /// Returns the values attached to the request, such as the authenticated user.
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    /// Returns a mutable reference to the values attached to the request.
+    pub fn extensions_mut(&mut self) -> &mut Extensions {
+        &mut self.extensions
+    }
+
+    /// Returns the address of the client, when the connection has one.
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        self.peer_addr
+    }
+
+    pub(crate) fn set_peer_addr(&mut self, peer_addr: Option<SocketAddr>) {
+        self.peer_addr = peer_addr;
+    }
+
+    pub(crate) fn set_params(&mut self, params: Vec<(String, String)>) {
+        self.params = params;
+    }
+
+    pub(crate) fn set_route(&mut self, route: &str) {
+        self.route = Some(route.to_string());
+    }
+
+    pub(crate) fn set_request_id(&mut self, request_id: String) {
+        self.request_id = Some(request_id);
+    }
+
+    /// Builds a request received through a protocol without a textual request line, such
+    /// as HTTP/2.
+    #[cfg(feature = "http2")]
+    pub(crate) fn from_parts(
+        method: &str,
+        target: &str,
+        version: &str,
+        headers: Headers,
+        body: Vec<u8>,
+    ) -> Self {
+        let (path, query_pairs) = super::request_line::split_target(target);
+
+        let mut request = Request::new();
+        request.set_request_line(RequestLine {
+            method: method.to_string(),
+            target: target.to_string(),
+            path,
+            query: query_pairs.iter().cloned().collect(),
+            query_pairs,
+            version: version.to_string(),
+        });
+        request.headers = headers;
+        request.body = body;
+        request.state = RequestState::StateDone;
+        request
+    }
+
     fn set_request_line(&mut self, rl: RequestLine) {
         self.method = rl.method;
+        self.target = rl.target;
         self.path = rl.path;
         self.version = rl.version;
         self.query = rl.query;
+        self.query_pairs = rl.query_pairs;
     }
 
     fn parse(&mut self, buffer: &[u8]) -> Result<usize, std::io::Error> {
@@ -140,6 +252,8 @@ impl Request {
                     let (done, consumed) =
                         body::parse(&mut self.body, current_slice, content_length.unwrap())?;
 
+                    read += consumed;
+
                     if done {
                         self.state = RequestState::StateDone;
                         continue;
@@ -148,8 +262,6 @@ impl Request {
                     if consumed == 0 {
                         break;
                     }
-
-                    read += consumed;
                 }
                 RequestState::StateDone => {
                     break;
@@ -160,15 +272,31 @@ impl Request {
         Ok(read)
     }
 
+    #[cfg(test)]
     pub(crate) async fn from_reader<R: tokio::io::AsyncRead + Unpin>(
-        mut reader: R,
+        reader: R,
     ) -> Result<Self, std::io::Error> {
+        let (request, _) = Self::from_reader_with_leftover(reader).await?;
+        Ok(request)
+    }
+
+    /// Reads a request, also returning the bytes read past its end, such as data sent
+    /// right after a protocol upgrade.
+    pub(crate) async fn from_reader_with_leftover<R: tokio::io::AsyncRead + Unpin>(
+        mut reader: R,
+    ) -> Result<(Self, Vec<u8>), std::io::Error> {
         let mut request = Request::new();
         let mut buffer: [u8; BUFFER_SIZE] = [0; BUFFER_SIZE];
         let mut len = 0;
 
         while !request.done() {
             let read_len = reader.read(&mut buffer[len..]).await?;
+            if read_len == 0 {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "Connection closed before the request was complete",
+                ));
+            }
 
             len += read_len;
 
@@ -178,7 +306,7 @@ impl Request {
             len -= processed_len;
         }
 
-        return Ok(request);
+        Ok((request, buffer[..len].to_vec()))
     }
 }
 
@@ -289,4 +417,26 @@ mod tests {
         assert!(request.headers().get::<String>("Accept").is_some());
         assert_eq!(request.headers().get::<String>("Accept").unwrap(), "*/*");
     }
+
+    #[tokio::test]
+    async fn test_leftover_bytes_after_request() {
+        let reader = ChunkReader::new(
+            b"POST / HTTP/1.1\r\nContent-Length: 5\r\n\r\nhelloextra",
+            1024,
+        );
+        let (request, leftover) = Request::from_reader_with_leftover(reader).await.unwrap();
+
+        assert_eq!(request.body(), b"hello");
+        assert_eq!(leftover, b"extra");
+    }
+
+    #[tokio::test]
+    async fn test_truncated_request() {
+        let reader = ChunkReader::new(b"POST / HTTP/1.1\r\nContent-Length: 5\r\n\r\nhel", 1024);
+        let Err(err) = Request::from_reader(reader).await else {
+            panic!("A truncated request should fail");
+        };
+
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+    }
 }
@@ -1,9 +1,13 @@
 use std::{collections::HashMap, io};
 
+use crate::percent::form_decode;
+
 pub(super) struct RequestLine {
     pub(crate) method: String,
+    pub(crate) target: String,
     pub(crate) path: String,
     pub(crate) query: HashMap<String, String>,
+    pub(crate) query_pairs: Vec<(String, String)>,
     pub(crate) version: String,
 }
 
@@ -20,7 +24,34 @@ fn is_valid_method(method: &str) -> bool {
 }
 
 fn is_valid_version(version: &str) -> bool {
-    matches!(version, "HTTP/1.0" | "HTTP/1.1" | "HTTP/2.0")
+    matches!(version, "HTTP/1.0" | "HTTP/1.1")
+}
+
+/// Splits a request target into its path and decoded query parameters, in the order they
+/// were sent.
+pub(super) fn split_target(target: &str) -> (String, Vec<(String, String)>) {
+    let (path, query_string) = match target.find(QUERY_SEPARATOR) {
+        Some(pos) => (&target[..pos], &target[pos + 1..]),
+        None => (target, ""),
+    };
+
+    let mut query = Vec::new();
+    for param in query_string.split(PARAMETER_SEPARATOR) {
+        if param.is_empty() {
+            continue;
+        }
+
+        let mut key_value = param.splitn(2, '=');
+        let key = key_value.next().unwrap_or("");
+        if key.is_empty() {
+            continue;
+        }
+
+        let value = key_value.next().unwrap_or("");
+        query.push((form_decode(key), form_decode(value)));
+    }
+
+    (path.to_string(), query)
 }
 
 impl RequestLine {
@@ -59,26 +90,7 @@ impl RequestLine {
         let target = String::from_utf8(parts[1].to_vec())
             .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
 
-        let (path, _query) = match target.find(QUERY_SEPARATOR) {
-            Some(pos) => (&target[..pos], &target[pos + 1..]),
-            None => (target.as_str(), ""),
-        };
-
-        let mut query = HashMap::new();
-        for param in _query.split(PARAMETER_SEPARATOR) {
-            if param.is_empty() {
-                continue;
-            }
-
-            let mut key_value = param.splitn(2, '=');
-            let key = key_value.next().unwrap_or("");
-            if key.is_empty() {
-                continue;
-            }
-
-            let value = key_value.next().unwrap_or("").to_string();
-            query.insert(key.to_string(), value);
-        }
+        let (path, query_pairs) = split_target(&target);
 
         let version = String::from_utf8(parts[2].to_vec())
             .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
@@ -93,8 +105,10 @@ impl RequestLine {
         let bytes_consumed = index.unwrap() + LINE_SEPARATOR.len();
         let request_line = RequestLine {
             method,
-            path: path.to_string(),
-            query,
+            target,
+            path,
+            query: query_pairs.iter().cloned().collect(),
+            query_pairs,
             version,
         };
 
@@ -181,4 +195,11 @@ mod tests {
         assert_eq!(request_line.query.get("query").unwrap(), "coffee");
         assert_eq!(request_line.query.get("sort").unwrap(), "asc");
     }
+
+    #[test]
+    fn test_http2_request_line_is_rejected() {
+        // HTTP/2 is a binary protocol and never sends a textual request line
+        let data = b"GET / HTTP/2.0\r\n\r\n";
+        assert!(RequestLine::parse(data).is_err());
+    }
 }
@@ -1,3 +1,4 @@
+#[allow(clippy::enum_variant_names)]
 #[derive(PartialEq)]
 pub(super) enum RequestState {
     StateInit = 0,
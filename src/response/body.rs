@@ -0,0 +1,172 @@
+use std::{
+    fs::File,
+    io::{Seek, SeekFrom},
+    pin::Pin,
+};
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+/// A boxed source of bytes for streamed bodies.
+pub type BodyReader = Pin<Box<dyn AsyncRead + Send>>;
+
+const CHUNK_SIZE: usize = 8192;
+
+/// Represents the body of an HTTP response.
+pub enum Body {
+    /// A body held entirely in memory.
+    Full(Vec<u8>),
+    /// A section of a file, streamed from disk when the response is written.
+    File {
+        file: File,
+        offset: u64,
+        length: u64,
+    },
+    /// Several bodies written one after the other. Parts must have a known length.
+    Chain(Vec<Body>),
+    /// A body of unknown length, sent with chunked transfer encoding.
+    Stream(BodyReader),
+}
+
+impl Body {
+    /// Creates an empty body.
+    pub fn empty() -> Self {
+        Body::Full(Vec::new())
+    }
+
+    /// Creates a body streamed from the given reader.
+    pub fn stream<R: AsyncRead + Send + 'static>(reader: R) -> Self {
+        Body::Stream(Box::pin(reader))
+    }
+
+    /// Returns the length of the body in bytes, or `None` for streamed bodies.
+    pub fn len(&self) -> Option<u64> {
+        match self {
+            Body::Full(data) => Some(data.len() as u64),
+            Body::File { length, .. } => Some(*length),
+            Body::Chain(parts) => parts.iter().map(Body::len).sum(),
+            Body::Stream(_) => None,
+        }
+    }
+
+    /// Returns true if the body is known to have no content.
+    pub fn is_empty(&self) -> bool {
+        self.len() == Some(0)
+    }
+
+    /// Returns true if sections of the body can be taken with `slice`.
+    pub(crate) fn can_slice(&self) -> bool {
+        matches!(self, Body::Full(_) | Body::File { .. })
+    }
+
+    /// Returns `length` bytes of the body starting at `start`, or `None` if the body
+    /// cannot be sliced or the section is out of bounds.
+    pub(crate) fn slice(&self, start: u64, length: u64) -> Option<Body> {
+        if start.checked_add(length)? > self.len()? {
+            return None;
+        }
+
+        match self {
+            Body::Full(data) => {
+                let start = start as usize;
+                Some(Body::Full(data[start..start + length as usize].to_vec()))
+            }
+            Body::File { file, offset, .. } => Some(Body::File {
+                file: file.try_clone().ok()?,
+                offset: offset + start,
+                length,
+            }),
+            Body::Chain(_) | Body::Stream(_) => None,
+        }
+    }
+
+    /// Converts the body into a reader over its content.
+    pub(crate) fn into_reader(self) -> Result<BodyReader, std::io::Error> {
+        match self {
+            Body::Full(data) => Ok(Box::pin(std::io::Cursor::new(data))),
+            Body::File {
+                mut file,
+                offset,
+                length,
+            } => {
+                file.seek(SeekFrom::Start(offset))?;
+                Ok(Box::pin(tokio::fs::File::from_std(file).take(length)))
+            }
+            Body::Chain(parts) => {
+                let mut reader: BodyReader = Box::pin(tokio::io::empty());
+                for part in parts {
+                    reader = Box::pin(reader.chain(part.into_reader()?));
+                }
+
+                Ok(reader)
+            }
+            Body::Stream(reader) => Ok(reader),
+        }
+    }
+
+    /// Writes the body, returning the number of bytes of content written.
+    pub(crate) async fn write_to<W: tokio::io::AsyncWrite + Unpin>(
+        &mut self,
+        writer: &mut W,
+    ) -> Result<u64, std::io::Error> {
+        match self {
+            Body::Full(data) => {
+                writer.write_all(data).await?;
+                Ok(data.len() as u64)
+            }
+            Body::File {
+                file,
+                offset,
+                length,
+            } => {
+                let mut file = tokio::fs::File::from_std(file.try_clone()?);
+                file.seek(SeekFrom::Start(*offset)).await?;
+
+                let copied = tokio::io::copy(&mut file.take(*length), writer).await?;
+                if copied != *length {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::UnexpectedEof,
+                        "File is shorter than the advertised length",
+                    ));
+                }
+
+                Ok(copied)
+            }
+            Body::Chain(parts) => {
+                let mut written = 0;
+                for part in parts {
+                    written += Box::pin(part.write_to(writer)).await?;
+                }
+
+                Ok(written)
+            }
+            Body::Stream(reader) => {
+                let mut buffer = vec![0; CHUNK_SIZE];
+                let mut written = 0;
+
+                loop {
+                    let read = reader.read(&mut buffer).await?;
+                    if read == 0 {
+                        break;
+                    }
+
+                    writer
+                        .write_all(format!("{:x}\r\n", read).as_bytes())
+                        .await?;
+                    writer.write_all(&buffer[..read]).await?;
+                    writer.write_all(b"\r\n").await?;
+                    writer.flush().await?;
+                    written += read as u64;
+                }
+
+                writer.write_all(b"0\r\n\r\n").await?;
+                Ok(written)
+            }
+        }
+    }
+}
+
+impl From<Vec<u8>> for Body {
+    fn from(data: Vec<u8>) -> Self {
+        Body::Full(data)
+    }
+}
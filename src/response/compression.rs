@@ -0,0 +1,248 @@
+use tokio::io::{AsyncReadExt, BufReader};
+
+use crate::{
+    Body, Request, Response, StatusCode,
+    encoding::Encoding,
+    headers::{keys, quality},
+    response::CONTENT_TYPE_JSON,
+};
+
+const DEFAULT_MIN_SIZE: u64 = 1024;
+
+// Content types that are already compressed, or that must reach the client unbuffered
+const SKIPPED_CONTENT_TYPES: [&str; 14] = [
+    "image/",
+    "audio/",
+    "video/",
+    "font/woff",
+    "application/zip",
+    "application/gzip",
+    "application/x-gzip",
+    "application/zstd",
+    "application/x-bzip2",
+    "application/x-7z-compressed",
+    "application/x-rar-compressed",
+    "application/x-xz",
+    "application/pdf",
+    "text/event-stream",
+];
+
+/// Configures response compression based on the `Accept-Encoding` request header.
+///
+/// The available encodings are selected with the `gzip`, `deflate`, `br` and `zstd`
+/// cargo features.
+#[derive(Debug, Clone)]
+pub struct Compression {
+    min_size: u64,
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Compression {
+    /// Creates a new Compression configuration that compresses bodies of at least 1 KiB.
+    pub fn new() -> Self {
+        Compression {
+            min_size: DEFAULT_MIN_SIZE,
+        }
+    }
+
+    /// Sets the minimum body size, in bytes, for a response to be compressed.
+    ///
+    /// Streamed bodies have no known size and are always compressed.
+    pub fn with_min_size(mut self, min_size: u64) -> Self {
+        self.min_size = min_size;
+        self
+    }
+}
+
+/// Picks the preferred token from `supported` according to an `Accept-Encoding` header.
+///
+/// Tokens not listed in the header are only acceptable through a `*` entry, and ties
+/// are broken by the order of `supported`.
+pub(crate) fn negotiate<'a>(header: &str, supported: &[&'a str]) -> Option<&'a str> {
+    let entries = quality::parse(header);
+
+    let quality_of = |token: &str| {
+        entries
+            .iter()
+            .find(|item| item.value == token)
+            .or_else(|| entries.iter().find(|item| item.value == "*"))
+            .map(|item| item.quality)
+            .unwrap_or(0.0)
+    };
+
+    let mut best: Option<(&str, f32)> = None;
+    for &token in supported {
+        let q = quality_of(token);
+        if q > 0.0 && best.is_none_or(|(_, best_q)| q > best_q) {
+            best = Some((token, q));
+        }
+    }
+
+    best.map(|(token, _)| token)
+}
+
+fn is_compressible(content_type: &str) -> bool {
+    let content_type = content_type.trim().to_ascii_lowercase();
+
+    if content_type.starts_with("image/svg") {
+        return true;
+    }
+
+    !SKIPPED_CONTENT_TYPES
+        .iter()
+        .any(|skipped| content_type.starts_with(skipped))
+}
+
+impl Response {
+    /// Compresses the body with the best encoding accepted by the client.
+    pub(crate) async fn apply_compression(&mut self, req: &Request, config: &Compression) {
+        if matches!(
+            self.status_code,
+            StatusCode::NotModified | StatusCode::PartialContent
+        ) || self.body.is_empty()
+            || self.headers.contains(keys::CONTENT_ENCODING_HEADER)
+            || self.headers.contains(keys::CONTENT_RANGE_HEADER)
+        {
+            return;
+        }
+
+        let content_type = self
+            .headers
+            .get::<String>(keys::CONTENT_TYPE_KEY)
+            .unwrap_or_else(|| CONTENT_TYPE_JSON.to_string());
+
+        if !is_compressible(&content_type) {
+            return;
+        }
+
+        // The representation depends on Accept-Encoding even when it ends up uncompressed
+        self.headers
+            .add(keys::VARY_HEADER, keys::ACCEPT_ENCODING_HEADER);
+
+        if self.body.len().is_some_and(|len| len < config.min_size) {
+            return;
+        }
+
+        let header = match req.headers().get::<String>(keys::ACCEPT_ENCODING_HEADER) {
+            Some(header) => header,
+            None => return,
+        };
+
+        let tokens = Encoding::SUPPORTED
+            .iter()
+            .map(Encoding::as_str)
+            .collect::<Vec<&str>>();
+
+        let encoding = match negotiate(&header, &tokens).and_then(Encoding::from_token) {
+            Some(encoding) => encoding,
+            None => return,
+        };
+
+        match &self.body {
+            Body::Full(data) => {
+                let mut encoded = Vec::new();
+                if encoding
+                    .encode(data.as_slice())
+                    .read_to_end(&mut encoded)
+                    .await
+                    .is_err()
+                {
+                    return;
+                }
+
+                self.body = Body::Full(encoded);
+            }
+            _ => {
+                let body = std::mem::replace(&mut self.body, Body::empty());
+                let reader = match body.into_reader() {
+                    Ok(reader) => reader,
+                    Err(_) => return,
+                };
+
+                self.body = Body::Stream(encoding.encode(BufReader::new(reader)));
+            }
+        }
+
+        self.headers
+            .set(keys::CONTENT_ENCODING_HEADER, encoding.as_str());
+
+        // Ranges are only served over the uncompressed representation
+        self.headers.remove(keys::ACCEPT_RANGES_HEADER);
+
+        // The encoded bytes differ from the original, so only a weak match still holds
+        if let Some(etag) = self.headers.get::<String>(keys::ETAG_HEADER)
+            && !etag.starts_with("W/")
+        {
+            self.headers.set(keys::ETAG_HEADER, &format!("W/{}", etag));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUPPORTED: [&str; 3] = ["br", "gzip", "deflate"];
+
+    #[test]
+    fn test_negotiate_prefers_server_order_on_ties() {
+        assert_eq!(negotiate("gzip, br", &SUPPORTED), Some("br"));
+        assert_eq!(negotiate("deflate, gzip", &SUPPORTED), Some("gzip"));
+    }
+
+    #[test]
+    fn test_negotiate_with_quality_values() {
+        assert_eq!(negotiate("br;q=0.5, gzip;q=0.8", &SUPPORTED), Some("gzip"));
+        assert_eq!(negotiate("br;q=0, gzip;q=0", &SUPPORTED), None);
+        assert_eq!(negotiate("gzip;q=0, *", &SUPPORTED), Some("br"));
+        assert_eq!(negotiate("*;q=0.1, deflate", &SUPPORTED), Some("deflate"));
+    }
+
+    #[test]
+    fn test_negotiate_without_match() {
+        assert_eq!(negotiate("identity", &SUPPORTED), None);
+        assert_eq!(negotiate("compress", &SUPPORTED), None);
+        assert_eq!(negotiate("", &SUPPORTED), None);
+        assert_eq!(negotiate("gzip", &[]), None);
+    }
+
+    #[test]
+    fn test_is_compressible() {
+        assert!(is_compressible("application/json; charset=utf-8"));
+        assert!(is_compressible("text/html"));
+        assert!(is_compressible("image/svg+xml"));
+        assert!(!is_compressible("image/png"));
+        assert!(!is_compressible("application/zip"));
+        assert!(!is_compressible("text/event-stream"));
+    }
+
+    #[cfg(feature = "gzip")]
+    #[tokio::test]
+    async fn test_compressed_response_drops_accept_ranges() {
+        use crate::responses::OkResponse;
+
+        let req =
+            Request::from_reader("GET / HTTP/1.1\r\nAccept-Encoding: gzip\r\n\r\n".as_bytes())
+                .await
+                .unwrap();
+
+        let mut response = Response::new();
+        response.set_result(OkResponse::from("hello ".repeat(1000)).into());
+        response.apply_range(&req);
+        assert!(response.headers().contains(keys::ACCEPT_RANGES_HEADER));
+
+        response.apply_compression(&req, &Compression::new()).await;
+        assert_eq!(
+            response
+                .headers()
+                .get::<String>(keys::CONTENT_ENCODING_HEADER),
+            Some("gzip".to_string())
+        );
+        assert!(!response.headers().contains(keys::ACCEPT_RANGES_HEADER));
+    }
+}
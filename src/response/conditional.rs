@@ -0,0 +1,317 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{
+    Body, Request, Response, StatusCode,
+    headers::{Headers, keys},
+    responses::{HttpResponse, NotModifiedResponse, PreconditionFailedError},
+};
+
+/// Selects how the server generates `ETag` headers for responses that do not set one.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ETagMode {
+    /// Strong tags, for bodies that are byte-for-byte identical.
+    Strong,
+    /// Weak tags (`W/"..."`), for bodies that are only semantically equivalent.
+    Weak,
+}
+
+/// The outcome of evaluating the conditional headers of a request.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Precondition {
+    Passed,
+    NotModified,
+    Failed,
+}
+
+// Headers a 304 may repeat from the response it replaces
+// https://datatracker.ietf.org/doc/html/rfc9110#name-304-not-modified
+const NOT_MODIFIED_HEADERS: [&str; 6] = [
+    keys::ETAG_HEADER,
+    keys::LAST_MODIFIED_HEADER,
+    "Cache-Control",
+    "Content-Location",
+    "Expires",
+    "Vary",
+];
+
+fn is_weak(tag: &str) -> bool {
+    tag.starts_with("W/")
+}
+
+fn opaque(tag: &str) -> &str {
+    tag.trim_start_matches("W/")
+}
+
+fn list_matches(header: &str, etag: Option<&str>, strong: bool) -> bool {
+    header.split(',').map(str::trim).any(|candidate| {
+        if candidate == "*" {
+            return true;
+        }
+
+        match etag {
+            Some(_) if strong && is_weak(candidate) => false,
+            Some(etag) if strong && is_weak(etag) => false,
+            Some(etag) => opaque(candidate) == opaque(etag),
+            None => false,
+        }
+    })
+}
+
+fn seconds(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn header_date(headers: &Headers, key: &str) -> Option<SystemTime> {
+    headers
+        .get::<String>(key)
+        .and_then(|value| httpdate::parse_http_date(&value).ok())
+}
+
+/// Evaluates the preconditions of a request against the current validators of a resource,
+/// in the order given by RFC 9110.
+///
+/// https://datatracker.ietf.org/doc/html/rfc9110#name-evaluation-of-preconditions
+pub(crate) fn evaluate(
+    req: &Request,
+    etag: Option<&str>,
+    last_modified: Option<SystemTime>,
+) -> Precondition {
+    let headers = req.headers();
+    let is_safe = matches!(req.method(), "GET" | "HEAD");
+
+    if let Some(if_match) = headers.get::<String>(keys::IF_MATCH_HEADER) {
+        if !list_matches(&if_match, etag, true) {
+            return Precondition::Failed;
+        }
+    } else if let (Some(since), Some(modified)) = (
+        header_date(headers, keys::IF_UNMODIFIED_SINCE_HEADER),
+        last_modified,
+    ) && seconds(modified) > seconds(since)
+    {
+        return Precondition::Failed;
+    }
+
+    if let Some(if_none_match) = headers.get::<String>(keys::IF_NONE_MATCH_HEADER) {
+        if list_matches(&if_none_match, etag, false) {
+            return match is_safe {
+                true => Precondition::NotModified,
+                false => Precondition::Failed,
+            };
+        }
+    } else if is_safe
+        && let (Some(since), Some(modified)) = (
+            header_date(headers, keys::IF_MODIFIED_SINCE_HEADER),
+            last_modified,
+        )
+        && seconds(modified) <= seconds(since)
+    {
+        return Precondition::NotModified;
+    }
+
+    Precondition::Passed
+}
+
+// 64-bit FNV-1a, which is stable across builds so tags survive restarts
+fn fnv1a(data: &[u8]) -> u64 {
+    data.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Computes an entity tag from the bytes of a body.
+pub(crate) fn etag_for(data: &[u8], mode: ETagMode) -> String {
+    let tag = format!("\"{:x}-{:016x}\"", data.len(), fnv1a(data));
+
+    match mode {
+        ETagMode::Strong => tag,
+        ETagMode::Weak => format!("W/{}", tag),
+    }
+}
+
+impl Request {
+    /// Evaluates the conditional headers of the request against the current validators of
+    /// the requested resource.
+    ///
+    /// The server only checks preconditions after a handler has produced its response, so
+    /// handlers of methods that change state should call this before applying the change
+    /// and return the error response when it fails.
+    pub fn check_preconditions(
+        &self,
+        etag: Option<&str>,
+        last_modified: Option<SystemTime>,
+    ) -> Result<(), Box<dyn HttpResponse>> {
+        match evaluate(self, etag, last_modified) {
+            Precondition::Passed => Ok(()),
+            Precondition::NotModified => {
+                let mut response = NotModifiedResponse::new();
+                if let Some(etag) = etag {
+                    response = response.with_header(keys::ETAG_HEADER, etag);
+                }
+                if let Some(last_modified) = last_modified {
+                    response = response.with_header(
+                        keys::LAST_MODIFIED_HEADER,
+                        &httpdate::fmt_http_date(last_modified),
+                    );
+                }
+
+                Err(response.into())
+            }
+            Precondition::Failed => Err(PreconditionFailedError::new().into()),
+        }
+    }
+}
+
+impl Response {
+    /// Generates an `ETag` for successful in-memory responses that do not already have one.
+    pub(crate) fn apply_etag(&mut self, mode: ETagMode) {
+        if self.status_code != StatusCode::Ok || self.headers.contains(keys::ETAG_HEADER) {
+            return;
+        }
+
+        if let Body::Full(data) = &self.body {
+            let etag = etag_for(data, mode);
+            self.headers.set(keys::ETAG_HEADER, &etag);
+        }
+    }
+
+    /// Evaluates the request preconditions against the `ETag` and `Last-Modified` headers of
+    /// a successful response, replacing it with a 304 Not Modified or 412 Precondition Failed.
+    pub(crate) fn apply_conditional(&mut self, req: &Request) {
+        if self.status_code != StatusCode::Ok {
+            return;
+        }
+
+        let etag = self.headers.get::<String>(keys::ETAG_HEADER);
+        let last_modified = header_date(&self.headers, keys::LAST_MODIFIED_HEADER);
+
+        match evaluate(req, etag.as_deref(), last_modified) {
+            Precondition::Passed => {}
+            Precondition::NotModified => {
+                let mut headers = Headers::new();
+                for key in NOT_MODIFIED_HEADERS {
+                    if let Some(value) = self.headers.get::<String>(key) {
+                        headers.set(key, &value);
+                    }
+                }
+
+                self.headers = headers;
+                self.body = Body::empty();
+                self.status_code = StatusCode::NotModified;
+            }
+            Precondition::Failed => {
+                self.set_result(
+                    PreconditionFailedError::with_message(format!(
+                        "Precondition failed for {} {}",
+                        req.method(),
+                        req.path()
+                    ))
+                    .into(),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn request(raw: &str) -> Request {
+        Request::from_reader(raw.as_bytes()).await.unwrap()
+    }
+
+    fn date(secs: u64) -> SystemTime {
+        UNIX_EPOCH + std::time::Duration::from_secs(secs)
+    }
+
+    #[tokio::test]
+    async fn test_if_none_match() {
+        let req = request("GET / HTTP/1.1\r\nIf-None-Match: \"a\", W/\"b\"\r\n\r\n").await;
+
+        assert_eq!(
+            evaluate(&req, Some("\"b\""), None),
+            Precondition::NotModified
+        );
+        assert_eq!(
+            evaluate(&req, Some("W/\"a\""), None),
+            Precondition::NotModified
+        );
+        assert_eq!(evaluate(&req, Some("\"c\""), None), Precondition::Passed);
+
+        let req = request("DELETE / HTTP/1.1\r\nIf-None-Match: *\r\n\r\n").await;
+        assert_eq!(evaluate(&req, Some("\"a\""), None), Precondition::Failed);
+    }
+
+    #[tokio::test]
+    async fn test_if_match_uses_strong_comparison() {
+        let req = request("PUT / HTTP/1.1\r\nIf-Match: \"a\"\r\n\r\n").await;
+
+        assert_eq!(evaluate(&req, Some("\"a\""), None), Precondition::Passed);
+        assert_eq!(evaluate(&req, Some("W/\"a\""), None), Precondition::Failed);
+        assert_eq!(evaluate(&req, None, None), Precondition::Failed);
+    }
+
+    #[tokio::test]
+    async fn test_if_modified_since() {
+        let header = httpdate::fmt_http_date(date(1_000_000));
+        let req = request(&format!(
+            "GET / HTTP/1.1\r\nIf-Modified-Since: {}\r\n\r\n",
+            header
+        ))
+        .await;
+
+        assert_eq!(
+            evaluate(&req, None, Some(date(1_000_000))),
+            Precondition::NotModified
+        );
+        assert_eq!(
+            evaluate(&req, None, Some(date(1_000_001))),
+            Precondition::Passed
+        );
+
+        // If-None-Match takes precedence over If-Modified-Since
+        let req = request(&format!(
+            "GET / HTTP/1.1\r\nIf-None-Match: \"a\"\r\nIf-Modified-Since: {}\r\n\r\n",
+            header
+        ))
+        .await;
+        assert_eq!(
+            evaluate(&req, Some("\"b\""), Some(date(1_000_000))),
+            Precondition::Passed
+        );
+    }
+
+    #[tokio::test]
+    async fn test_if_unmodified_since() {
+        let header = httpdate::fmt_http_date(date(1_000_000));
+        let req = request(&format!(
+            "PUT / HTTP/1.1\r\nIf-Unmodified-Since: {}\r\n\r\n",
+            header
+        ))
+        .await;
+
+        assert_eq!(
+            evaluate(&req, None, Some(date(1_000_001))),
+            Precondition::Failed
+        );
+        assert_eq!(
+            evaluate(&req, None, Some(date(999_999))),
+            Precondition::Passed
+        );
+    }
+
+    #[test]
+    fn test_etag_for() {
+        assert_eq!(
+            etag_for(b"hello", ETagMode::Strong),
+            etag_for(b"hello", ETagMode::Strong)
+        );
+        assert_ne!(
+            etag_for(b"hello", ETagMode::Strong),
+            etag_for(b"world", ETagMode::Strong)
+        );
+        assert!(etag_for(b"hello", ETagMode::Weak).starts_with("W/\""));
+    }
+}
@@ -1,5 +1,14 @@
+mod body;
+mod compression;
+mod conditional;
+mod range;
+#[allow(clippy::module_inception)]
 mod response;
 mod status_code;
 
+pub use body::{Body, BodyReader};
+pub use compression::Compression;
+pub use conditional::ETagMode;
+pub(crate) use response::CONTENT_TYPE_JSON;
 pub use response::Response;
 pub use status_code::StatusCode;
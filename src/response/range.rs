@@ -0,0 +1,308 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    Body, Request, Response, StatusCode, headers::keys, response::CONTENT_TYPE_JSON,
+    responses::RangeNotSatisfiableError,
+};
+
+const RANGE_UNIT: &str = "bytes";
+// Requests asking for more ranges than this are answered with the full body
+const MAX_RANGES: usize = 16;
+
+/// A byte range resolved against the length of a body. Both ends are inclusive.
+#[derive(Debug, PartialEq)]
+struct ByteRange {
+    start: u64,
+    end: u64,
+}
+
+impl ByteRange {
+    fn len(&self) -> u64 {
+        self.end - self.start + 1
+    }
+
+    fn content_range(&self, total: u64) -> String {
+        format!("{} {}-{}/{}", RANGE_UNIT, self.start, self.end, total)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum RangeSet {
+    Satisfiable(Vec<ByteRange>),
+    Unsatisfiable,
+}
+
+fn parse_position(value: &str) -> Option<u64> {
+    if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    value.parse().ok()
+}
+
+/// Parses a `Range` header against a body of `length` bytes.
+///
+/// Returns `None` when the header is malformed or uses an unknown unit, in which case
+/// it must be ignored and the full body sent.
+fn parse_range(header: &str, length: u64) -> Option<RangeSet> {
+    let (unit, specs) = header.split_once('=')?;
+    if !unit.trim().eq_ignore_ascii_case(RANGE_UNIT) {
+        return None;
+    }
+
+    let mut ranges = Vec::new();
+    let mut count = 0;
+
+    for spec in specs.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        count += 1;
+        if count > MAX_RANGES {
+            return None;
+        }
+
+        let (first, last) = spec.split_once('-')?;
+
+        if first.is_empty() {
+            // A suffix range such as `-500` asks for the last 500 bytes
+            let suffix = parse_position(last)?;
+            if suffix == 0 || length == 0 {
+                continue;
+            }
+
+            ranges.push(ByteRange {
+                start: length.saturating_sub(suffix),
+                end: length - 1,
+            });
+            continue;
+        }
+
+        let start = parse_position(first)?;
+        let end = match last {
+            "" => None,
+            _ => Some(parse_position(last)?),
+        };
+
+        if end.is_some_and(|end| end < start) {
+            return None;
+        }
+
+        if start >= length {
+            continue;
+        }
+
+        ranges.push(ByteRange {
+            start,
+            end: end.map_or(length - 1, |end| end.min(length - 1)),
+        });
+    }
+
+    if count == 0 {
+        return None;
+    }
+
+    if ranges.is_empty() {
+        return Some(RangeSet::Unsatisfiable);
+    }
+
+    Some(RangeSet::Satisfiable(ranges))
+}
+
+fn boundary() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(0);
+
+    format!(
+        "{:016x}{:08x}",
+        nanos,
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+impl Response {
+    /// Checks an `If-Range` precondition against the validators of this response.
+    ///
+    /// Entity tags must match using the strong comparison, and dates must match the
+    /// `Last-Modified` header exactly.
+    fn if_range_matches(&self, value: &str) -> bool {
+        let value = value.trim();
+
+        if value.starts_with("W/") {
+            return false;
+        }
+
+        if value.starts_with('"') {
+            return self
+                .headers
+                .get::<String>(keys::ETAG_HEADER)
+                .is_some_and(|etag| !etag.starts_with("W/") && etag == value);
+        }
+
+        let last_modified = self
+            .headers
+            .get::<String>(keys::LAST_MODIFIED_HEADER)
+            .and_then(|date| httpdate::parse_http_date(&date).ok());
+
+        match (last_modified, httpdate::parse_http_date(value)) {
+            (Some(last_modified), Ok(date)) => last_modified == date,
+            _ => false,
+        }
+    }
+
+    /// Answers a `Range` request with a 206 Partial Content or 416 Range Not Satisfiable
+    /// when the handler produced a full 200 response.
+    pub(crate) fn apply_range(&mut self, req: &Request) {
+        if req.method() != "GET" || self.status_code != StatusCode::Ok || !self.body.can_slice() {
+            return;
+        }
+
+        self.headers.set(keys::ACCEPT_RANGES_HEADER, RANGE_UNIT);
+
+        let header = match req.headers().get::<String>(keys::RANGE_HEADER) {
+            Some(header) => header,
+            None => return,
+        };
+
+        if let Some(if_range) = req.headers().get::<String>(keys::IF_RANGE_HEADER)
+            && !self.if_range_matches(&if_range)
+        {
+            return;
+        }
+
+        let length = match self.body.len() {
+            Some(length) => length,
+            None => return,
+        };
+
+        let ranges = match parse_range(&header, length) {
+            Some(RangeSet::Satisfiable(ranges)) => ranges,
+            Some(RangeSet::Unsatisfiable) => {
+                self.set_result(
+                    RangeNotSatisfiableError::with_message(format!(
+                        "Range {} cannot be satisfied for a body of {} bytes",
+                        header, length
+                    ))
+                    .with_complete_length(length)
+                    .into(),
+                );
+                return;
+            }
+            None => return,
+        };
+
+        if let [range] = ranges.as_slice() {
+            if let Some(body) = self.body.slice(range.start, range.len()) {
+                self.body = body;
+                self.status_code = StatusCode::PartialContent;
+                self.headers
+                    .set(keys::CONTENT_RANGE_HEADER, &range.content_range(length));
+            }
+            return;
+        }
+
+        let content_type = self
+            .headers
+            .get::<String>(keys::CONTENT_TYPE_KEY)
+            .unwrap_or_else(|| CONTENT_TYPE_JSON.to_string());
+        let boundary = boundary();
+
+        let mut parts = Vec::with_capacity(ranges.len() * 2 + 1);
+        for range in &ranges {
+            let part_headers = format!(
+                "\r\n--{}\r\n{}: {}\r\n{}: {}\r\n\r\n",
+                boundary,
+                keys::CONTENT_TYPE_KEY,
+                content_type,
+                keys::CONTENT_RANGE_HEADER,
+                range.content_range(length)
+            );
+
+            let part = match self.body.slice(range.start, range.len()) {
+                Some(part) => part,
+                None => return,
+            };
+
+            parts.push(Body::Full(part_headers.into_bytes()));
+            parts.push(part);
+        }
+        parts.push(Body::Full(format!("\r\n--{}--\r\n", boundary).into_bytes()));
+
+        self.body = Body::Chain(parts);
+        self.status_code = StatusCode::PartialContent;
+        self.headers.set(
+            keys::CONTENT_TYPE_KEY,
+            &format!("multipart/byteranges; boundary={}", boundary),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn satisfiable(ranges: &[(u64, u64)]) -> Option<RangeSet> {
+        Some(RangeSet::Satisfiable(
+            ranges
+                .iter()
+                .map(|&(start, end)| ByteRange { start, end })
+                .collect(),
+        ))
+    }
+
+    #[test]
+    fn test_single_range() {
+        assert_eq!(parse_range("bytes=0-499", 1000), satisfiable(&[(0, 499)]));
+        assert_eq!(parse_range("bytes=500-", 1000), satisfiable(&[(500, 999)]));
+        assert_eq!(parse_range("bytes=-200", 1000), satisfiable(&[(800, 999)]));
+        assert_eq!(
+            parse_range("bytes=900-5000", 1000),
+            satisfiable(&[(900, 999)])
+        );
+        assert_eq!(parse_range("bytes=-5000", 1000), satisfiable(&[(0, 999)]));
+    }
+
+    #[test]
+    fn test_multiple_ranges() {
+        assert_eq!(
+            parse_range("bytes=0-9, 20-29,-5", 100),
+            satisfiable(&[(0, 9), (20, 29), (95, 99)])
+        );
+
+        // Unsatisfiable ranges are dropped when at least one other range is satisfiable
+        assert_eq!(
+            parse_range("bytes=0-9,500-600", 100),
+            satisfiable(&[(0, 9)])
+        );
+    }
+
+    #[test]
+    fn test_unsatisfiable_range() {
+        assert_eq!(
+            parse_range("bytes=1000-", 1000),
+            Some(RangeSet::Unsatisfiable)
+        );
+        assert_eq!(parse_range("bytes=-0", 1000), Some(RangeSet::Unsatisfiable));
+        assert_eq!(parse_range("bytes=0-", 0), Some(RangeSet::Unsatisfiable));
+    }
+
+    #[test]
+    fn test_malformed_range_is_ignored() {
+        assert_eq!(parse_range("items=0-9", 100), None);
+        assert_eq!(parse_range("bytes=9-0", 100), None);
+        assert_eq!(parse_range("bytes=a-b", 100), None);
+        assert_eq!(parse_range("bytes=+1-5", 100), None);
+        assert_eq!(parse_range("bytes=", 100), None);
+        assert_eq!(parse_range("bytes 0-9", 100), None);
+    }
+
+    #[test]
+    fn test_too_many_ranges_is_ignored() {
+        let header = format!("bytes={}", vec!["0-0"; MAX_RANGES + 1].join(","));
+        assert_eq!(parse_range(&header, 100), None);
+    }
+}
@@ -1,20 +1,26 @@
 use tokio::io::AsyncWriteExt;
 
 use crate::{
+    Body,
     headers::{self, Headers},
-    response::StatusCode, responses::HttpResponse,
+    response::StatusCode,
+    responses::{CapturedError, HttpResponse},
+    server::upgrade::OnUpgrade,
 };
 
 /// Represents an HTTP response.
 pub struct Response {
-    body: Vec<u8>,
-    headers: Headers,
-    status_code: StatusCode,
+    pub(super) body: Body,
+    pub(super) headers: Headers,
+    pub(super) status_code: StatusCode,
+    upgrade: Option<OnUpgrade>,
+    request_id: Option<String>,
+    error: Option<CapturedError>,
 }
 
 const HTTP_VERSION: &str = "HTTP/1.1";
 
-const CONTENT_TYPE_JSON: &str = "application/json; charset=utf-8";
+pub(crate) const CONTENT_TYPE_JSON: &str = "application/json; charset=utf-8";
 
 impl Response {
     /// Returns a mutable reference to the headers of the response.
@@ -29,25 +35,56 @@ impl Response {
 
     pub(crate) fn new() -> Self {
         Response {
-            body: Vec::new(),
+            body: Body::empty(),
             headers: Headers::new(),
             status_code: StatusCode::Ok,
+            upgrade: None,
+            request_id: None,
+            error: None,
         }
     }
 
-    pub(crate) fn set_result(&mut self, result: Box<dyn HttpResponse>) {
+    pub(crate) fn set_result(&mut self, mut result: Box<dyn HttpResponse>) {
         self.status_code = result.status_code();
-        self.body = result.into_response();
+        self.upgrade = result.take_upgrade();
+        self.error = result.as_error().map(CapturedError::new);
+        result.set_headers(&mut self.headers);
+        self.body = result.into_body_with_request_id(self.request_id.as_deref());
     }
 
-    pub(crate) fn set_status_code(&mut self, status_code: StatusCode) {
-        self.status_code = status_code;
+    /// Echoes the id of the request in `header`, and in the body of errors.
+    pub(crate) fn set_request_id(&mut self, header: &str, request_id: &str) {
+        self.headers.set(header, request_id);
+        self.request_id = Some(request_id.to_string());
     }
 
+    pub(crate) fn status_code(&self) -> StatusCode {
+        self.status_code
+    }
+
+    #[cfg(feature = "http2")]
+    pub(crate) fn take_body(&mut self) -> Body {
+        std::mem::replace(&mut self.body, Body::empty())
+    }
+
+    /// Takes the error the response reports, if any.
+    pub(crate) fn take_error(&mut self) -> Option<CapturedError> {
+        self.error.take()
+    }
+
+    pub(crate) fn has_upgrade(&self) -> bool {
+        self.upgrade.is_some()
+    }
+
+    pub(crate) fn take_upgrade(&mut self) -> Option<OnUpgrade> {
+        self.upgrade.take()
+    }
+
+    /// Writes the response, returning the number of bytes of body written.
     pub(crate) async fn write_response<W: tokio::io::AsyncWrite + Unpin>(
-        &self,
+        &mut self,
         writer: &mut W,
-    ) -> Result<(), std::io::Error> {
+    ) -> Result<u64, std::io::Error> {
         // Write status line
         let status_line = format!(
             "{} {} {}\r\n",
@@ -65,20 +102,35 @@ impl Response {
         writer.write_all(b"\r\n").await?;
 
         // Write body
-        writer.write_all(&self.body).await?;
-
-        Ok(())
+        self.body.write_to(writer).await
     }
 
     pub(crate) fn set_default_headers(&mut self) {
-        self.headers.set(
-            headers::keys::CONTENT_LENGTH_HEADER,
-            &self.body.len().to_string(),
-        );
+        // The connection is handed over to another protocol or tunnel, so it is neither
+        // closed nor followed by a body
+        if self.upgrade.is_some() {
+            return;
+        }
 
         self.headers.set(headers::keys::CONNECTION_HEADER, "close");
 
-        self.headers
-            .set(headers::keys::CONTENT_TYPE_KEY, CONTENT_TYPE_JSON);
+        // A 304 describes a representation without sending it, so it carries no body headers
+        if self.status_code == StatusCode::NotModified {
+            return;
+        }
+
+        match self.body.len() {
+            Some(len) => self
+                .headers
+                .set(headers::keys::CONTENT_LENGTH_HEADER, &len.to_string()),
+            None => self
+                .headers
+                .set(headers::keys::TRANSFER_ENCODING_HEADER, "chunked"),
+        }
+
+        if !self.headers.contains(headers::keys::CONTENT_TYPE_KEY) {
+            self.headers
+                .set(headers::keys::CONTENT_TYPE_KEY, CONTENT_TYPE_JSON);
+        }
     }
 }
@@ -2,22 +2,42 @@ use serde::Serialize;
 
 #[derive(Debug, Copy, Clone, Serialize, PartialEq, Eq)]
 pub enum StatusCode {
+    SwitchingProtocols = 101,
     Ok = 200,
+    PartialContent = 206,
+    NotModified = 304,
     BadRequest = 400,
     NotFound = 404,
+    NotAcceptable = 406,
+    PreconditionFailed = 412,
+    ContentTooLarge = 413,
+    UnsupportedMediaType = 415,
+    RangeNotSatisfiable = 416,
+    UpgradeRequired = 426,
     InternalServerError = 500,
     NotImplemented = 501,
+    ServiceUnavailable = 503,
 }
 
 impl StatusCode {
     /// Returns the standard reason phrase for the status code.
     pub fn as_str(&self) -> &'static str {
         match self {
+            StatusCode::SwitchingProtocols => "Switching Protocols",
             StatusCode::Ok => "OK",
+            StatusCode::PartialContent => "Partial Content",
+            StatusCode::NotModified => "Not Modified",
             StatusCode::BadRequest => "Bad Request",
             StatusCode::NotFound => "Not Found",
+            StatusCode::NotAcceptable => "Not Acceptable",
+            StatusCode::PreconditionFailed => "Precondition Failed",
+            StatusCode::ContentTooLarge => "Content Too Large",
+            StatusCode::UnsupportedMediaType => "Unsupported Media Type",
+            StatusCode::RangeNotSatisfiable => "Range Not Satisfiable",
+            StatusCode::UpgradeRequired => "Upgrade Required",
             StatusCode::InternalServerError => "Internal Server Error",
             StatusCode::NotImplemented => "Not Implemented",
+            StatusCode::ServiceUnavailable => "Service Unavailable",
         }
     }
 }
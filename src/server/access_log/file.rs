@@ -0,0 +1,102 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// A log file that is renamed to `path.1`, shifting older files, once it grows too large.
+pub(super) struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    /// The largest size of a file and how many rotated files are kept.
+    rotation: Option<(u64, usize)>,
+}
+
+fn open(path: &Path) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+impl RotatingFile {
+    pub(super) fn open(path: PathBuf, rotation: Option<(u64, usize)>) -> io::Result<Self> {
+        let file = open(&path)?;
+        let size = file.metadata()?.len();
+
+        Ok(RotatingFile {
+            path,
+            file,
+            size,
+            rotation,
+        })
+    }
+
+    pub(super) fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let length = line.len() as u64 + 1;
+
+        if let Some((max_size, max_files)) = self.rotation
+            && self.size > 0
+            && self.size + length > max_size
+        {
+            self.rotate(max_files)?;
+        }
+
+        self.file.write_all(format!("{}\n", line).as_bytes())?;
+        self.size += length;
+        Ok(())
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", index));
+        path.into()
+    }
+
+    fn rotate(&mut self, max_files: usize) -> io::Result<()> {
+        if max_files == 0 {
+            self.file.set_len(0)?;
+            self.size = 0;
+            return Ok(());
+        }
+
+        // Rotated files that do not exist yet are skipped
+        let _ = fs::remove_file(self.rotated_path(max_files));
+        for index in (1..max_files).rev() {
+            let _ = fs::rename(self.rotated_path(index), self.rotated_path(index + 1));
+        }
+        fs::rename(&self.path, self.rotated_path(1))?;
+
+        self.file = open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rotation() {
+        let dir = std::env::temp_dir().join(format!("access-log-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("access.log");
+
+        let mut file = RotatingFile::open(path.clone(), Some((10, 2))).unwrap();
+        for line in ["one", "two", "three", "four"] {
+            file.write_line(line).unwrap();
+        }
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "four\n");
+        assert_eq!(
+            fs::read_to_string(dir.join("access.log.1")).unwrap(),
+            "three\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("access.log.2")).unwrap(),
+            "one\ntwo\n"
+        );
+        assert!(!dir.join("access.log.3").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,329 @@
+//! Access logging, enabled with [`Server::with_access_log`](crate::Server::with_access_log).
+//!
+//! One [`AccessLogRecord`] is written per request once its response has been sent, in the
+//! Common or Combined Log Format or as a JSON object. Records go to standard output, to a
+//! file that can be rotated by size, to any writer, or to the `tracing` crate with the
+//! `tracing` feature.
+//!
+//! Writers and files are written by a thread of their own, so that a slow disk or pipe
+//! does not hold up the runtime.
+
+mod file;
+mod record;
+
+use std::{
+    io::{self, Write},
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, SyncSender, TrySendError},
+    },
+    time::Instant,
+};
+
+pub use record::AccessLogRecord;
+
+use crate::{Request, StatusCode};
+
+use file::RotatingFile;
+
+/// The layout of a written record.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LogFormat {
+    /// The Common Log Format: `host - - [time] "request line" status bytes`.
+    Common,
+    /// The Common Log Format followed by the quoted `Referer` and `User-Agent`.
+    Combined,
+    /// One JSON object per line.
+    Json,
+}
+
+/// How many lines can wait for the writer thread before records are dropped.
+const QUEUE_SIZE: usize = 4096;
+
+/// A message to the writer thread.
+enum Message {
+    Line(String),
+    /// Acknowledged once the lines queued before it are written.
+    Flush(SyncSender<()>),
+}
+
+enum Sink {
+    Thread(WriterThread),
+    #[cfg(feature = "tracing")]
+    Tracing,
+}
+
+/// A destination written line by line on the writer thread.
+trait LineWriter: Send {
+    fn write_line(&mut self, line: &str) -> io::Result<()>;
+}
+
+impl LineWriter for Box<dyn Write + Send> {
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        writeln!(self, "{}", line)?;
+        self.flush()
+    }
+}
+
+impl LineWriter for RotatingFile {
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        RotatingFile::write_line(self, line)
+    }
+}
+
+/// The queue of a thread writing lines, which stops once the queue is dropped.
+///
+/// A thread of its own is used rather than the blocking pool of the runtime, as it lives
+/// as long as the log, which can be created before the runtime.
+struct WriterThread {
+    lines: SyncSender<Message>,
+    dropping: AtomicBool,
+}
+
+impl WriterThread {
+    fn spawn<W: LineWriter + 'static>(mut writer: W) -> io::Result<Self> {
+        let (lines, queue) = mpsc::sync_channel::<Message>(QUEUE_SIZE);
+
+        std::thread::Builder::new()
+            .name("access-log".to_string())
+            .spawn(move || {
+                // Only the first failure is reported until writing succeeds again
+                let mut failing = false;
+
+                for message in queue {
+                    let line = match message {
+                        Message::Line(line) => line,
+                        Message::Flush(flushed) => {
+                            let _ = flushed.send(());
+                            continue;
+                        }
+                    };
+
+                    match writer.write_line(&line) {
+                        Ok(()) => failing = false,
+                        Err(err) => {
+                            if !failing {
+                                eprintln!("Failed to write access log: {}", err);
+                            }
+                            failing = true;
+                        }
+                    }
+                }
+            })?;
+
+        Ok(WriterThread {
+            lines,
+            dropping: AtomicBool::new(false),
+        })
+    }
+
+    fn send(&self, line: String) {
+        match self.lines.try_send(Message::Line(line)) {
+            Ok(()) => self.dropping.store(false, Ordering::Relaxed),
+            Err(TrySendError::Full(_)) => {
+                if !self.dropping.swap(true, Ordering::Relaxed) {
+                    eprintln!("Access log is falling behind, dropping records");
+                }
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                if !self.dropping.swap(true, Ordering::Relaxed) {
+                    eprintln!("Access log writer stopped, dropping records");
+                }
+            }
+        }
+    }
+
+    /// Blocks until the lines queued so far are written.
+    fn flush(&self) {
+        let (flushed, written) = mpsc::sync_channel(1);
+        if self.lines.send(Message::Flush(flushed)).is_ok() {
+            let _ = written.recv();
+        }
+    }
+}
+
+/// Where and how access records are written.
+pub struct AccessLog {
+    sink: Sink,
+    format: LogFormat,
+}
+
+impl AccessLog {
+    /// Creates a new AccessLog writing Combined Log Format lines to standard output.
+    pub fn stdout() -> Self {
+        Self::writer(io::stdout())
+    }
+
+    /// Creates a new AccessLog writing Combined Log Format lines to `writer`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the writer thread cannot be spawned.
+    pub fn writer<W: Write + Send + 'static>(writer: W) -> Self {
+        let writer: Box<dyn Write + Send> = Box::new(writer);
+
+        AccessLog {
+            sink: Sink::Thread(
+                WriterThread::spawn(writer).expect("Failed to spawn the access log thread"),
+            ),
+            format: LogFormat::Combined,
+        }
+    }
+
+    /// Creates a new AccessLog appending Combined Log Format lines to the file at `path`.
+    pub fn file<P: Into<PathBuf>>(path: P) -> io::Result<Self> {
+        Ok(AccessLog {
+            sink: Sink::Thread(WriterThread::spawn(RotatingFile::open(path.into(), None)?)?),
+            format: LogFormat::Combined,
+        })
+    }
+
+    /// Creates a new AccessLog appending to the file at `path`, which is rotated once it
+    /// would grow past `max_size` bytes. The previous files are kept as `path.1` (the most
+    /// recent) up to `path.<max_files>`.
+    pub fn rotating_file<P: Into<PathBuf>>(
+        path: P,
+        max_size: u64,
+        max_files: usize,
+    ) -> io::Result<Self> {
+        let rotation = Some((max_size, max_files));
+
+        Ok(AccessLog {
+            sink: Sink::Thread(WriterThread::spawn(RotatingFile::open(
+                path.into(),
+                rotation,
+            )?)?),
+            format: LogFormat::Combined,
+        })
+    }
+
+    /// Creates a new AccessLog emitting each record as a `tracing` event at the INFO
+    /// level, with the target `http_server::access` and the record fields as event fields.
+    #[cfg(feature = "tracing")]
+    pub fn tracing() -> Self {
+        AccessLog {
+            sink: Sink::Tracing,
+            format: LogFormat::Combined,
+        }
+    }
+
+    /// Sets the format of the written records.
+    pub fn with_format(mut self, format: LogFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Writes a record, queueing it for the writer thread of writers and files.
+    ///
+    /// Logging never fails a request: write failures, and records dropped while the
+    /// queue is full, are reported on standard error the first time they happen after
+    /// logging worked.
+    pub fn log(&self, record: &AccessLogRecord) {
+        let line = record.format(self.format);
+
+        match &self.sink {
+            Sink::Thread(thread) => thread.send(line),
+            #[cfg(feature = "tracing")]
+            Sink::Tracing => tracing::info!(
+                target: "http_server::access",
+                method = %record.method,
+                path = %record.path,
+                status = record.status,
+                bytes = record.bytes,
+                duration_ms = record.duration.as_secs_f64() * 1000.0,
+                peer_addr = record.peer_addr.map(|addr| addr.to_string()),
+                user_agent = record.user_agent.as_deref(),
+                request_id = record.request_id.as_deref(),
+                "{}",
+                line
+            ),
+        }
+    }
+
+    /// Blocks until the records logged so far are written, so that they are not lost
+    /// when the process exits.
+    pub(crate) fn flush(&self) {
+        match &self.sink {
+            Sink::Thread(thread) => thread.flush(),
+            #[cfg(feature = "tracing")]
+            Sink::Tracing => {}
+        }
+    }
+}
+
+/// The record of a request being served, written when it is dropped.
+pub(crate) struct LogEntry {
+    log: Arc<AccessLog>,
+    record: AccessLogRecord,
+    start: Instant,
+}
+
+impl LogEntry {
+    pub(crate) fn new(log: Arc<AccessLog>, req: &Request, start: Instant) -> Self {
+        LogEntry {
+            log,
+            record: AccessLogRecord::new(req),
+            start,
+        }
+    }
+
+    pub(crate) fn set_status(&mut self, status: StatusCode) {
+        self.record.status = status as u16;
+    }
+
+    pub(crate) fn add_bytes(&mut self, bytes: u64) {
+        self.record.bytes += bytes;
+    }
+}
+
+impl Drop for LogEntry {
+    fn drop(&mut self) {
+        self.record.duration = self.start.elapsed();
+        self.log.log(&self.record);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A writer whose content can be inspected after it was moved into a log.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_writer_sink() {
+        let buffer = SharedBuffer::default();
+        let log = AccessLog::writer(buffer.clone()).with_format(LogFormat::Json);
+
+        let request = Request::from_reader(&b"GET /a?b=1 HTTP/1.1\r\n\r\n"[..])
+            .await
+            .unwrap();
+        let mut record = AccessLogRecord::new(&request);
+        record.status = 204;
+        log.log(&record);
+        log.log(&record);
+
+        // Records are written by the writer thread, which flushing waits for
+        log.flush();
+        let output = String::from_utf8(buffer.0.lock().unwrap().clone()).unwrap();
+        assert_eq!(output.lines().count(), 2);
+        let lines = output.lines().collect::<Vec<&str>>();
+
+        let value: serde_json::Value = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(value["status"], 204);
+        assert_eq!(value["path"], "/a");
+    }
+}
@@ -0,0 +1,225 @@
+use std::{
+    net::SocketAddr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use crate::{Request, headers::keys};
+
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// What is logged about a request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessLogRecord {
+    /// When the request was received.
+    pub time: SystemTime,
+    pub method: String,
+    pub path: String,
+    /// The request target, including the query string.
+    pub target: String,
+    pub version: String,
+    pub status: u16,
+    /// The number of bytes of response body sent.
+    pub bytes: u64,
+    /// The time taken to read the request and send the response.
+    pub duration: Duration,
+    pub peer_addr: Option<SocketAddr>,
+    pub user_agent: Option<String>,
+    pub referer: Option<String>,
+    pub request_id: Option<String>,
+}
+
+impl AccessLogRecord {
+    pub(crate) fn new(req: &Request) -> Self {
+        AccessLogRecord {
+            time: SystemTime::now(),
+            method: req.method().to_string(),
+            path: req.path().to_string(),
+            target: req.target().to_string(),
+            version: req.http_version().to_string(),
+            status: 0,
+            bytes: 0,
+            duration: Duration::ZERO,
+            peer_addr: req.peer_addr(),
+            user_agent: req.headers().get(keys::USER_AGENT_HEADER),
+            referer: req.headers().get(keys::REFERER_HEADER),
+            request_id: req.request_id().map(str::to_string),
+        }
+    }
+
+    /// Formats the record as a single line, without the line break.
+    pub fn format(&self, format: super::LogFormat) -> String {
+        match format {
+            super::LogFormat::Common => self.common(),
+            super::LogFormat::Combined => format!(
+                "{} \"{}\" \"{}\"",
+                self.common(),
+                quoted(self.referer.as_deref()),
+                quoted(self.user_agent.as_deref())
+            ),
+            super::LogFormat::Json => self.json(),
+        }
+    }
+
+    fn common(&self) -> String {
+        let host = self
+            .peer_addr
+            .map(|addr| addr.ip().to_string())
+            .unwrap_or_else(|| "-".to_string());
+        let bytes = match self.bytes {
+            0 => "-".to_string(),
+            bytes => bytes.to_string(),
+        };
+
+        format!(
+            "{} - - [{}] \"{} {} {}\" {} {}",
+            host,
+            clf_time(self.time),
+            escape(&self.method),
+            escape(&self.target),
+            escape(&self.version),
+            self.status,
+            bytes
+        )
+    }
+
+    fn json(&self) -> String {
+        serde_json::json!({
+            "time": rfc3339_time(self.time),
+            "method": self.method,
+            "path": self.path,
+            "target": self.target,
+            "version": self.version,
+            "status": self.status,
+            "bytes": self.bytes,
+            "duration_ms": self.duration.as_secs_f64() * 1000.0,
+            "peer_addr": self.peer_addr.map(|addr| addr.to_string()),
+            "user_agent": self.user_agent,
+            "referer": self.referer,
+            "request_id": self.request_id,
+        })
+        .to_string()
+    }
+}
+
+/// Escapes quotes, backslashes and control characters so that a value cannot break the
+/// layout of a line.
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\x{:02x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+fn quoted(value: Option<&str>) -> String {
+    value.map(escape).unwrap_or_else(|| "-".to_string())
+}
+
+/// Splits a time into its UTC year, month, day, hour, minute and second.
+fn utc(time: SystemTime) -> (i64, u32, u32, u32, u32, u32) {
+    let seconds = time
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_secs() as i64)
+        .unwrap_or(0);
+    let (days, second_of_day) = (seconds.div_euclid(86_400), seconds.rem_euclid(86_400));
+
+    // Civil date from days since the epoch, from Howard Hinnant's date algorithms
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    (
+        year,
+        month as u32,
+        day as u32,
+        (second_of_day / 3600) as u32,
+        (second_of_day / 60 % 60) as u32,
+        (second_of_day % 60) as u32,
+    )
+}
+
+fn clf_time(time: SystemTime) -> String {
+    let (year, month, day, hour, minute, second) = utc(time);
+    format!(
+        "{:02}/{}/{}:{:02}:{:02}:{:02} +0000",
+        day,
+        MONTHS[month as usize - 1],
+        year,
+        hour,
+        minute,
+        second
+    )
+}
+
+fn rfc3339_time(time: SystemTime) -> String {
+    let (year, month, day, hour, minute, second) = utc(time);
+    format!(
+        "{}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year, month, day, hour, minute, second
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::access_log::LogFormat;
+
+    fn record() -> AccessLogRecord {
+        AccessLogRecord {
+            // 2000-10-10T13:55:36Z
+            time: UNIX_EPOCH + Duration::from_secs(971_186_136),
+            method: "GET".to_string(),
+            path: "/apache_pb.gif".to_string(),
+            target: "/apache_pb.gif?a=1".to_string(),
+            version: "HTTP/1.1".to_string(),
+            status: 200,
+            bytes: 2326,
+            duration: Duration::from_millis(12),
+            peer_addr: Some("127.0.0.1:50000".parse().unwrap()),
+            user_agent: Some("curl/8.0 \"quoted\"".to_string()),
+            referer: None,
+            request_id: Some("abc".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_common_and_combined() {
+        assert_eq!(
+            record().format(LogFormat::Common),
+            "127.0.0.1 - - [10/Oct/2000:13:55:36 +0000] \"GET /apache_pb.gif?a=1 HTTP/1.1\" 200 2326"
+        );
+        assert!(
+            record()
+                .format(LogFormat::Combined)
+                .ends_with("2326 \"-\" \"curl/8.0 \\\"quoted\\\"\"")
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        let value: serde_json::Value =
+            serde_json::from_str(&record().format(LogFormat::Json)).unwrap();
+
+        assert_eq!(value["time"], "2000-10-10T13:55:36Z");
+        assert_eq!(value["duration_ms"], 12.0);
+        assert_eq!(value["peer_addr"], "127.0.0.1:50000");
+        assert_eq!(value["referer"], serde_json::Value::Null);
+        assert_eq!(value["request_id"], "abc");
+    }
+}
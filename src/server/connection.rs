@@ -0,0 +1,57 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpStream,
+};
+
+use crate::{Request, server::upgrade::Rewind};
+
+/// Attaches details about a connection, such as the negotiated TLS parameters, to the
+/// extensions of each request received on it.
+pub(crate) type Describe = Arc<dyn Fn(&mut Request) + Send + Sync>;
+
+/// A stream accepted by the server.
+pub(crate) trait Connection: AsyncRead + AsyncWrite + Unpin + Send {
+    fn describe(&self) -> Describe {
+        Arc::new(|_: &mut Request| {})
+    }
+
+    /// Returns the address of the client, if the transport has one.
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        None
+    }
+
+    /// Returns the protocol negotiated through ALPN, if any.
+    #[cfg(feature = "http2")]
+    fn alpn_protocol(&self) -> Option<&[u8]> {
+        None
+    }
+}
+
+impl Connection for TcpStream {
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        TcpStream::peer_addr(self).ok()
+    }
+}
+
+#[cfg(unix)]
+impl Connection for tokio::net::UnixStream {}
+
+impl<S: Connection> Connection for Rewind<S> {
+    fn describe(&self) -> Describe {
+        self.get_ref().describe()
+    }
+
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        self.get_ref().peer_addr()
+    }
+
+    #[cfg(feature = "http2")]
+    fn alpn_protocol(&self) -> Option<&[u8]> {
+        self.get_ref().alpn_protocol()
+    }
+}
+
+#[cfg(test)]
+impl Connection for tokio::io::DuplexStream {}
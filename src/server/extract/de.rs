@@ -0,0 +1,465 @@
+use serde::{
+    de::{self, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor},
+    forward_to_deserialize_any,
+};
+
+/// An error raised while deserializing route parameters or query strings.
+#[derive(Debug)]
+pub(crate) struct Error(String);
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+/// Deserializes a list of string key-value pairs, such as route parameters or a query string.
+///
+/// Structs and maps are read by key, sequences and tuples by position, and single values
+/// such as `u64` from the only pair in the list. The values of a repeated key are read as
+/// a sequence.
+pub(crate) struct PairsDeserializer<'de> {
+    pairs: &'de [(String, String)],
+}
+
+impl<'de> PairsDeserializer<'de> {
+    pub(crate) fn new(pairs: &'de [(String, String)]) -> Self {
+        PairsDeserializer { pairs }
+    }
+
+    fn single(&self) -> Result<ValueDeserializer<'de>, Error> {
+        match self.pairs {
+            [(_, value)] => Ok(ValueDeserializer(value)),
+            _ => Err(Error(format!(
+                "expected a single value, found {}",
+                self.pairs.len()
+            ))),
+        }
+    }
+}
+
+macro_rules! forward_to_single {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                self.single()?.$method(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for PairsDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        let mut entries: Vec<(&'de str, Vec<&'de str>)> = Vec::new();
+        for (key, value) in self.pairs {
+            match entries.iter_mut().find(|(existing, _)| existing == key) {
+                Some((_, values)) => values.push(value),
+                None => entries.push((key, vec![value])),
+            }
+        }
+
+        visitor.visit_map(PairsAccess {
+            entries: entries.into_iter(),
+            values: None,
+        })
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_seq(ValuesAccess {
+            pairs: self.pairs.iter(),
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        if len != self.pairs.len() {
+            return Err(Error(format!(
+                "expected {} values, found {}",
+                len,
+                self.pairs.len()
+            )));
+        }
+
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.pairs {
+            [] => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_unit()
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.single()?.deserialize_enum(name, variants, visitor)
+    }
+
+    forward_to_single! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_f32
+        deserialize_f64 deserialize_char deserialize_str deserialize_string
+        deserialize_bytes deserialize_byte_buf deserialize_identifier
+    }
+}
+
+struct PairsAccess<'de> {
+    entries: std::vec::IntoIter<(&'de str, Vec<&'de str>)>,
+    values: Option<(&'de str, Vec<&'de str>)>,
+}
+
+impl<'de> MapAccess<'de> for PairsAccess<'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, Error> {
+        match self.entries.next() {
+            Some((key, values)) => {
+                self.values = Some((key, values));
+                seed.deserialize(ValueDeserializer(key)).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Error> {
+        match self.values.take() {
+            Some((key, values)) => seed.deserialize(RepeatedDeserializer { key, values }),
+            None => Err(Error("value requested before key".to_string())),
+        }
+    }
+}
+
+/// Deserializes the values of a key, as a sequence or as the only value.
+struct RepeatedDeserializer<'de> {
+    key: &'de str,
+    values: Vec<&'de str>,
+}
+
+impl<'de> RepeatedDeserializer<'de> {
+    fn single(&self) -> Result<ValueDeserializer<'de>, Error> {
+        match self.values[..] {
+            [value] => Ok(ValueDeserializer(value)),
+            _ => Err(Error(format!(
+                "expected a single value for {}, found {}",
+                self.key,
+                self.values.len()
+            ))),
+        }
+    }
+}
+
+impl<'de> de::Deserializer<'de> for RepeatedDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        match self.values[..] {
+            [value] => ValueDeserializer(value).deserialize_any(visitor),
+            _ => self.deserialize_seq(visitor),
+        }
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_seq(RepeatedAccess(self.values.into_iter()))
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.single()?.deserialize_enum(name, variants, visitor)
+    }
+
+    forward_to_single! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_f32
+        deserialize_f64 deserialize_char deserialize_str deserialize_string
+        deserialize_bytes deserialize_byte_buf deserialize_identifier deserialize_unit
+        deserialize_ignored_any
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.single()?.deserialize_unit(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        self.single()?.deserialize_map(visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.single()?.deserialize_struct(name, fields, visitor)
+    }
+}
+
+struct ValuesAccess<'de> {
+    pairs: std::slice::Iter<'de, (String, String)>,
+}
+
+impl<'de> SeqAccess<'de> for ValuesAccess<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        match self.pairs.next() {
+            Some((_, value)) => seed.deserialize(ValueDeserializer(value)).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+struct RepeatedAccess<'de>(std::vec::IntoIter<&'de str>);
+
+impl<'de> SeqAccess<'de> for RepeatedAccess<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        match self.0.next() {
+            Some(value) => seed.deserialize(ValueDeserializer(value)).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Deserializes a single string, parsing it when a number or boolean is expected.
+struct ValueDeserializer<'de>(&'de str);
+
+macro_rules! parse_value {
+    ($($method:ident => $visit:ident,)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                match self.0.parse() {
+                    Ok(value) => visitor.$visit(value),
+                    Err(_) => Err(Error(format!(
+                        "cannot parse {:?} for {}",
+                        self.0,
+                        stringify!($visit).trim_start_matches("visit_")
+                    ))),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_borrowed_str(self.0)
+    }
+
+    parse_value! {
+        deserialize_bool => visit_bool,
+        deserialize_i8 => visit_i8,
+        deserialize_i16 => visit_i16,
+        deserialize_i32 => visit_i32,
+        deserialize_i64 => visit_i64,
+        deserialize_u8 => visit_u8,
+        deserialize_u16 => visit_u16,
+        deserialize_u32 => visit_u32,
+        deserialize_u64 => visit_u64,
+        deserialize_f32 => visit_f32,
+        deserialize_f64 => visit_f64,
+        deserialize_char => visit_char,
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_enum(IntoDeserializer::<Error>::into_deserializer(self.0))
+    }
+
+    forward_to_deserialize_any! {
+        str string bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    fn pairs(values: &[(&str, &str)]) -> Vec<(String, String)> {
+        values
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn from_pairs<T: serde::de::DeserializeOwned>(values: &[(&str, &str)]) -> Result<T, Error> {
+        T::deserialize(PairsDeserializer::new(&pairs(values)))
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Order {
+        Asc,
+        Desc,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Filters {
+        name: String,
+        limit: Option<u32>,
+        order: Order,
+        active: bool,
+    }
+
+    #[test]
+    fn test_deserialize_struct() {
+        let filters: Filters =
+            from_pairs(&[("name", "coffee"), ("order", "desc"), ("active", "true")]).unwrap();
+
+        assert_eq!(
+            filters,
+            Filters {
+                name: "coffee".to_string(),
+                limit: None,
+                order: Order::Desc,
+                active: true,
+            }
+        );
+
+        assert!(
+            from_pairs::<Filters>(&[("name", "coffee"), ("order", "up"), ("active", "1")]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_deserialize_tuple() {
+        let (id, slug): (u64, String) = from_pairs(&[("id", "42"), ("slug", "hello")]).unwrap();
+        assert_eq!(id, 42);
+        assert_eq!(slug, "hello");
+
+        assert!(from_pairs::<(u64,)>(&[("id", "abc")]).is_err());
+        assert!(from_pairs::<(u64,)>(&[("id", "1"), ("other", "2")]).is_err());
+    }
+
+    #[test]
+    fn test_deserialize_single_value() {
+        assert_eq!(from_pairs::<u64>(&[("id", "7")]).unwrap(), 7);
+        assert!(from_pairs::<u64>(&[]).is_err());
+    }
+}
@@ -0,0 +1,334 @@
+//! Typed extraction of handler arguments from a request.
+//!
+//! Any function whose arguments all implement [`FromRequest`] can be turned into an
+//! endpoint with [`handler`](crate::handler). Each argument is extracted in order, and the
+//! first one that fails rejects the request with its error response.
+
+mod de;
+
+use serde::de::DeserializeOwned;
+
+use crate::{
+    Request,
+    headers::{ContentType, TypedHeader},
+    percent::percent_decode,
+    responses::{BadRequestError, HttpResponse, InternalServerError, UnsupportedMediaTypeError},
+};
+
+/// A type that can be built from an incoming request.
+pub trait FromRequest: Sized {
+    /// Extracts the value, or returns the response sent when the request is rejected.
+    fn from_request(req: &Request) -> Result<Self, Box<dyn HttpResponse>>;
+}
+
+/// Parameters captured from the route path, such as `Path<(u64,)>` for `/users/:id`.
+///
+/// Tuples are filled in the order the parameters appear in the route, while structs and
+/// maps are filled by name. Values are percent-decoded, and values that fail to decode or
+/// parse are rejected with 400 Bad Request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Path<T>(pub T);
+
+impl<T: DeserializeOwned> FromRequest for Path<T> {
+    fn from_request(req: &Request) -> Result<Self, Box<dyn HttpResponse>> {
+        let params = req
+            .params()
+            .iter()
+            .map(|(name, value)| match percent_decode(value) {
+                Some(value) => Ok((name.clone(), value)),
+                None => Err(BadRequestError::with_message(format!(
+                    "Invalid path parameters: malformed percent-encoding in {}",
+                    name
+                ))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        T::deserialize(de::PairsDeserializer::new(&params))
+            .map(Path)
+            .map_err(|error| {
+                BadRequestError::with_message(format!("Invalid path parameters: {}", error)).into()
+            })
+    }
+}
+
+/// The query string, deserialized into `T`. Invalid queries are rejected with
+/// 400 Bad Request.
+///
+/// Names and values are decoded as `application/x-www-form-urlencoded`, so `+` stands for a
+/// space. A parameter repeated as in `?id=1&id=2` fills a sequence such as `Vec<u32>`, and
+/// is rejected when a single value is expected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Query<T>(pub T);
+
+impl<T: DeserializeOwned> FromRequest for Query<T> {
+    fn from_request(req: &Request) -> Result<Self, Box<dyn HttpResponse>> {
+        T::deserialize(de::PairsDeserializer::new(req.query_pairs()))
+            .map(Query)
+            .map_err(|error| {
+                BadRequestError::with_message(format!("Invalid query string: {}", error)).into()
+            })
+    }
+}
+
+/// A JSON request body, deserialized into `T`.
+///
+/// Requests without a JSON `Content-Type` are rejected with 415 Unsupported Media Type,
+/// and bodies that fail to deserialize with 400 Bad Request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Json<T>(pub T);
+
+fn is_json(content_type: &ContentType) -> bool {
+    let media_type = content_type.media_type().to_ascii_lowercase();
+    media_type == "application/json" || media_type.ends_with("+json")
+}
+
+impl<T: DeserializeOwned> FromRequest for Json<T> {
+    fn from_request(req: &Request) -> Result<Self, Box<dyn HttpResponse>> {
+        let content_type = req
+            .headers()
+            .get::<String>(ContentType::name())
+            .and_then(|value| ContentType::decode(&value));
+
+        if !content_type.as_ref().is_some_and(is_json) {
+            return Err(UnsupportedMediaTypeError::with_message(
+                "Expected request with `Content-Type: application/json`",
+            )
+            .into());
+        }
+
+        let mut deserializer = serde_json::Deserializer::from_slice(req.body());
+        T::deserialize(&mut deserializer)
+            .and_then(|value| deserializer.end().map(|_| value))
+            .map(Json)
+            .map_err(|error| {
+                BadRequestError::with_message(format!("Invalid JSON body: {}", error)).into()
+            })
+    }
+}
+
+/// A typed header such as [`Authorization`](crate::headers::Authorization). Missing or
+/// malformed headers are rejected with 400 Bad Request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Header<T>(pub T);
+
+impl<T: TypedHeader> FromRequest for Header<T> {
+    fn from_request(req: &Request) -> Result<Self, Box<dyn HttpResponse>> {
+        let value = req.headers().get::<String>(T::name()).ok_or_else(|| {
+            Box::<dyn HttpResponse>::from(BadRequestError::with_message(format!(
+                "Missing header: {}",
+                T::name()
+            )))
+        })?;
+
+        T::decode(&value).map(Header).ok_or_else(|| {
+            BadRequestError::with_message(format!("Invalid header: {}", T::name())).into()
+        })
+    }
+}
+
+/// Shared state registered with [`Router::with_state`](crate::Router::with_state).
+///
+/// Requesting a state type that was never registered is a programming error, answered
+/// with 500 Internal Server Error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct State<T>(pub T);
+
+impl<T: Clone + Send + Sync + 'static> FromRequest for State<T> {
+    fn from_request(req: &Request) -> Result<Self, Box<dyn HttpResponse>> {
+        req.extensions().get::<State<T>>().cloned().ok_or_else(|| {
+            InternalServerError::with_message(format!(
+                "No state of type {} is registered",
+                std::any::type_name::<T>()
+            ))
+            .into()
+        })
+    }
+}
+
+/// A value attached to the request extensions, such as the authenticated user set by a
+/// [`Middleware`](crate::Middleware).
+///
+/// Requests without a value of type `T` are rejected with 500 Internal Server Error, since
+/// the middleware meant to attach it did not run. Use `Option<Extension<T>>` when the
+/// value is optional.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Extension<T>(pub T);
+
+impl<T: Clone + Send + Sync + 'static> FromRequest for Extension<T> {
+    fn from_request(req: &Request) -> Result<Self, Box<dyn HttpResponse>> {
+        req.extensions()
+            .get::<T>()
+            .cloned()
+            .map(Extension)
+            .ok_or_else(|| {
+                InternalServerError::with_message(format!(
+                    "No extension of type {} is attached to the request",
+                    std::any::type_name::<T>()
+                ))
+                .into()
+            })
+    }
+}
+
+/// Makes an extractor optional, yielding `None` instead of rejecting the request.
+impl<T: FromRequest> FromRequest for Option<T> {
+    fn from_request(req: &Request) -> Result<Self, Box<dyn HttpResponse>> {
+        Ok(T::from_request(req).ok())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use crate::{
+        Response, Router, StatusCode, handler, headers::Authorization, responses::OkResponse,
+    };
+
+    use super::*;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct CreateUser {
+        name: String,
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Filters {
+        page: u32,
+        tag: Option<String>,
+    }
+
+    async fn request(raw: &str) -> Request {
+        Request::from_reader(raw.as_bytes()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_path() {
+        let mut req = request("GET /users/42 HTTP/1.1\r\n\r\n").await;
+        req.set_params(vec![("id".to_string(), "42".to_string())]);
+
+        let Path((id,)) = Path::<(u64,)>::from_request(&req).ok().unwrap();
+        assert_eq!(id, 42);
+
+        let error = Path::<(u64, u64)>::from_request(&req).err().unwrap();
+        assert_eq!(error.status_code(), StatusCode::BadRequest);
+
+        req.set_params(vec![("name".to_string(), "J%C3%B6rg%2Fadmin".to_string())]);
+        let Path((name,)) = Path::<(String,)>::from_request(&req).ok().unwrap();
+        assert_eq!(name, "Jörg/admin");
+
+        req.set_params(vec![("name".to_string(), "100%".to_string())]);
+        let error = Path::<(String,)>::from_request(&req).err().unwrap();
+        assert_eq!(error.status_code(), StatusCode::BadRequest);
+    }
+
+    #[tokio::test]
+    async fn test_query() {
+        let req = request("GET /search?page=2 HTTP/1.1\r\n\r\n").await;
+
+        let Query(filters) = Query::<Filters>::from_request(&req).ok().unwrap();
+        assert_eq!(filters, Filters { page: 2, tag: None });
+
+        let req = request("GET /search?page=two HTTP/1.1\r\n\r\n").await;
+        assert!(Query::<Filters>::from_request(&req).is_err());
+
+        let req = request("GET /search?page=1&tag=caf%C3%A9+au%20lait HTTP/1.1\r\n\r\n").await;
+        let Query(filters) = Query::<Filters>::from_request(&req).ok().unwrap();
+        assert_eq!(filters.tag.as_deref(), Some("café au lait"));
+    }
+
+    #[tokio::test]
+    async fn test_query_repeated_keys() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Ids {
+            id: Vec<u32>,
+            sort: Option<String>,
+        }
+
+        let req = request("GET /items?id=1&sort=asc&id=2 HTTP/1.1\r\n\r\n").await;
+        let Query(ids) = Query::<Ids>::from_request(&req).ok().unwrap();
+        assert_eq!(
+            ids,
+            Ids {
+                id: vec![1, 2],
+                sort: Some("asc".to_string())
+            }
+        );
+
+        let req = request("GET /items?id=1 HTTP/1.1\r\n\r\n").await;
+        let Query(ids) = Query::<Ids>::from_request(&req).ok().unwrap();
+        assert_eq!(ids.id, [1]);
+
+        let req = request("GET /search?page=1&page=2 HTTP/1.1\r\n\r\n").await;
+        let error = Query::<Filters>::from_request(&req).err().unwrap();
+        assert_eq!(error.status_code(), StatusCode::BadRequest);
+    }
+
+    #[tokio::test]
+    async fn test_json() {
+        let body = r#"{"name":"Ada"}"#;
+        let req = request(&format!(
+            "POST /users HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        ))
+        .await;
+
+        let Json(user) = Json::<CreateUser>::from_request(&req).ok().unwrap();
+        assert_eq!(user.name, "Ada");
+
+        let req = request(&format!(
+            "POST /users HTTP/1.1\r\nContent-Type: text/plain\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        ))
+        .await;
+
+        let error = Json::<CreateUser>::from_request(&req).err().unwrap();
+        assert_eq!(error.status_code(), StatusCode::UnsupportedMediaType);
+    }
+
+    #[tokio::test]
+    async fn test_header() {
+        let req = request("GET / HTTP/1.1\r\nAuthorization: Bearer secret\r\n\r\n").await;
+
+        let Header(auth) = Header::<Authorization>::from_request(&req).ok().unwrap();
+        assert_eq!(auth.bearer(), Some("secret"));
+
+        let req = request("GET / HTTP/1.1\r\n\r\n").await;
+        assert!(Header::<Authorization>::from_request(&req).is_err());
+        assert!(
+            Option::<Header<Authorization>>::from_request(&req)
+                .ok()
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[derive(Clone)]
+    struct AppState {
+        greeting: &'static str,
+    }
+
+    #[tokio::test]
+    async fn test_state() {
+        let mut router = Router::new().with_state(AppState { greeting: "hello" });
+        router.get(
+            "/",
+            handler(|State(state): State<AppState>| OkResponse::from(state.greeting)),
+        );
+        router.get(
+            "/missing",
+            handler(|State(count): State<u32>| OkResponse::from(count)),
+        );
+        let routes = router.build();
+
+        let mut req = request("GET / HTTP/1.1\r\n\r\n").await;
+        let result = routes(&mut req, &mut Response::new());
+        assert_eq!(result.into_response(), b"\"hello\"");
+
+        let mut req = request("GET /missing HTTP/1.1\r\n\r\n").await;
+        let result = routes(&mut req, &mut Response::new());
+        assert_eq!(result.status_code(), StatusCode::InternalServerError);
+    }
+}
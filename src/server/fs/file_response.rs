@@ -0,0 +1,90 @@
+use std::{fs::File, io::Read, path::Path, time::UNIX_EPOCH};
+
+use crate::{
+    Body, Request, StatusCode,
+    headers::{Headers, keys},
+    responses::{HttpResponse, NotFoundError},
+};
+
+use super::{blocking, mime};
+
+/// Represents a 200 OK response whose body is streamed from a file.
+pub(crate) struct FileResponse {
+    file: File,
+    length: u64,
+    content_type: &'static str,
+    last_modified: Option<String>,
+    etag: String,
+}
+
+impl HttpResponse for FileResponse {
+    // Only used when the body is requested as bytes; the server streams through `into_body`
+    fn into_response(mut self: Box<Self>) -> Vec<u8> {
+        let mut data = Vec::with_capacity(self.length as usize);
+
+        match blocking(|| self.file.read_to_end(&mut data)) {
+            Ok(_) => data,
+            Err(err) => {
+                eprintln!("Failed to read file: {}", err);
+                Vec::new()
+            }
+        }
+    }
+
+    fn status_code(&self) -> StatusCode {
+        StatusCode::Ok
+    }
+
+    fn set_headers(&self, headers: &mut Headers) {
+        headers.set(keys::CONTENT_TYPE_KEY, self.content_type);
+        headers.set(keys::ETAG_HEADER, &self.etag);
+
+        if let Some(last_modified) = &self.last_modified {
+            headers.set(keys::LAST_MODIFIED_HEADER, last_modified);
+        }
+    }
+
+    fn into_body(self: Box<Self>) -> Body {
+        Body::File {
+            file: self.file,
+            offset: 0,
+            length: self.length,
+        }
+    }
+}
+
+/// Builds the response for a file on disk.
+///
+/// Conditional requests are answered by the server from the validators set here.
+pub(crate) fn respond(req: &Request, path: &Path) -> Box<dyn HttpResponse> {
+    let not_found =
+        || NotFoundError::with_message(format!("Cannot {} {}", req.method(), req.path()));
+
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(_) => return not_found().into(),
+    };
+
+    let metadata = match file.metadata() {
+        Ok(metadata) if metadata.is_file() => metadata,
+        _ => return not_found().into(),
+    };
+
+    let modified = metadata.modified().ok();
+    let last_modified = modified.map(httpdate::fmt_http_date);
+
+    let modified_secs = modified
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    let etag = format!("\"{:x}-{:x}\"", metadata.len(), modified_secs);
+
+    FileResponse {
+        file,
+        length: metadata.len(),
+        content_type: mime::content_type(path),
+        last_modified,
+        etag,
+    }
+    .into()
+}
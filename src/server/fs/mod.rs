@@ -0,0 +1,24 @@
+//! Handlers for serving static files.
+
+mod file_response;
+mod mime;
+mod serve_dir;
+mod serve_file;
+
+pub use serve_dir::ServeDir;
+pub use serve_file::ServeFile;
+
+use tokio::runtime::{Handle, RuntimeFlavor};
+
+/// Runs filesystem calls made by a handler, which is synchronous, without stalling the
+/// other tasks of its worker thread: they are moved to another worker while it blocks.
+///
+/// The current thread runtime has no other worker, so the calls run in place there.
+fn blocking<T>(f: impl FnOnce() -> T) -> T {
+    match Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(f)
+        }
+        _ => f(),
+    }
+}
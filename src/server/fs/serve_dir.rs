@@ -0,0 +1,184 @@
+use std::{path::PathBuf, sync::Arc};
+
+use crate::{
+    EndpointHandler, Request, Response,
+    percent::percent_decode,
+    responses::{HttpResponse, NotFoundError},
+};
+
+use super::{blocking, file_response};
+
+const DEFAULT_PATH_PARAM: &str = "path";
+const DEFAULT_INDEX_FILE: &str = "index.html";
+
+/// Serves files from a directory.
+///
+/// Mount it on a wildcard route and the captured path is resolved relative to the root
+/// directory. Directories are answered with their index file.
+///
+/// ```no_run
+/// use http_server::{Router, fs::ServeDir};
+///
+/// let mut router = Router::new();
+/// router.get("/assets/*path", ServeDir::new("dist").into());
+/// ```
+pub struct ServeDir {
+    root: PathBuf,
+    param: String,
+    index_file: Option<String>,
+}
+
+impl ServeDir {
+    /// Creates a new ServeDir serving files under `root`.
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        ServeDir {
+            root: root.into(),
+            param: DEFAULT_PATH_PARAM.to_string(),
+            index_file: Some(DEFAULT_INDEX_FILE.to_string()),
+        }
+    }
+
+    /// Sets the name of the wildcard route parameter holding the file path. Defaults to `path`.
+    pub fn with_param<S: Into<String>>(mut self, param: S) -> Self {
+        self.param = param.into();
+        self
+    }
+
+    /// Sets the file served for directory requests. Defaults to `index.html`.
+    pub fn with_index_file<S: Into<String>>(mut self, index_file: S) -> Self {
+        self.index_file = Some(index_file.into());
+        self
+    }
+
+    /// Disables serving an index file for directory requests.
+    pub fn without_index_file(mut self) -> Self {
+        self.index_file = None;
+        self
+    }
+
+    /// Resolves a request path to a file inside the root directory.
+    ///
+    /// Returns `None` for paths that try to leave the root, either through `..` segments
+    /// or through symbolic links pointing outside of it.
+    pub(crate) fn resolve(&self, relative: &str) -> Option<PathBuf> {
+        let decoded = percent_decode(relative)?;
+        let mut path = self.root.clone();
+
+        for segment in decoded.split('/') {
+            match segment {
+                "" | "." => continue,
+                ".." => return None,
+                _ if segment.contains('\\') || segment.contains('\0') => return None,
+                _ => path.push(segment),
+            }
+        }
+
+        if path.is_dir() {
+            path.push(self.index_file.as_ref()?);
+        }
+
+        let root = self.root.canonicalize().ok()?;
+        let path = path.canonicalize().ok()?;
+
+        if !path.starts_with(&root) {
+            return None;
+        }
+
+        Some(path)
+    }
+
+    fn serve(&self, req: &Request) -> Box<dyn HttpResponse> {
+        let relative = req.param(&self.param).unwrap_or("");
+
+        match self.resolve(relative) {
+            Some(path) => file_response::respond(req, &path),
+            None => NotFoundError::with_message(format!("Cannot {} {}", req.method(), req.path()))
+                .into(),
+        }
+    }
+}
+
+impl From<ServeDir> for EndpointHandler {
+    fn from(dir: ServeDir) -> Self {
+        Arc::new(move |req: &Request, _: &mut Response| blocking(|| dir.serve(req)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_root(name: &str) -> PathBuf {
+        let root =
+            std::env::temp_dir().join(format!("http-server-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(root.join("docs")).unwrap();
+        std::fs::write(root.join("index.html"), "<h1>home</h1>").unwrap();
+        std::fs::write(root.join("docs/guide.txt"), "guide").unwrap();
+        std::fs::write(root.join("docs/index.html"), "<h1>docs</h1>").unwrap();
+        root
+    }
+
+    #[test]
+    fn test_resolve_file() {
+        let root = temp_root("resolve-file");
+        let dir = ServeDir::new(&root);
+
+        let path = dir.resolve("docs/guide.txt").unwrap();
+        assert!(path.ends_with("docs/guide.txt"));
+
+        let path = dir.resolve("docs/gu%69de.txt").unwrap();
+        assert!(path.ends_with("docs/guide.txt"));
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_index_file() {
+        let root = temp_root("resolve-index");
+
+        let path = ServeDir::new(&root).resolve("").unwrap();
+        assert!(path.ends_with("index.html"));
+
+        let path = ServeDir::new(&root).resolve("docs/").unwrap();
+        assert!(path.ends_with("docs/index.html"));
+
+        assert!(
+            ServeDir::new(&root)
+                .without_index_file()
+                .resolve("docs")
+                .is_none()
+        );
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_blocks_traversal() {
+        let root = temp_root("resolve-traversal");
+        let dir = ServeDir::new(root.join("docs"));
+
+        assert!(dir.resolve("../index.html").is_none());
+        assert!(dir.resolve("%2e%2e/index.html").is_none());
+        assert!(dir.resolve("..%2findex.html").is_none());
+        assert!(dir.resolve("..\\index.html").is_none());
+        assert!(dir.resolve("missing.txt").is_none());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_serve_on_multi_thread_runtime() {
+        let root = temp_root("serve-multi-thread");
+        let handler = EndpointHandler::from(ServeDir::new(&root));
+
+        let mut req = Request::from_reader("GET /docs/guide.txt HTTP/1.1\r\n\r\n".as_bytes())
+            .await
+            .unwrap();
+        req.set_params(vec![("path".to_string(), "docs/guide.txt".to_string())]);
+
+        let response = handler(&req, &mut Response::new());
+        assert_eq!(response.status_code(), crate::StatusCode::Ok);
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,32 @@
+use std::{path::PathBuf, sync::Arc};
+
+use crate::{EndpointHandler, Request, Response};
+
+use super::{blocking, file_response};
+
+/// Serves a single file, regardless of the request path.
+///
+/// ```no_run
+/// use http_server::{Router, fs::ServeFile};
+///
+/// let mut router = Router::new();
+/// router.get("/favicon.ico", ServeFile::new("dist/favicon.ico").into());
+/// ```
+pub struct ServeFile {
+    path: PathBuf,
+}
+
+impl ServeFile {
+    /// Creates a new ServeFile for the file at `path`.
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        ServeFile { path: path.into() }
+    }
+}
+
+impl From<ServeFile> for EndpointHandler {
+    fn from(file: ServeFile) -> Self {
+        Arc::new(move |req: &Request, _: &mut Response| {
+            blocking(|| file_response::respond(req, &file.path))
+        })
+    }
+}
@@ -1,8 +1,95 @@
 use std::sync::Arc;
 
-use crate::{Request, Response, responses::HttpResponse};
+use crate::{Request, Response, extract::FromRequest, responses::HttpResponse};
 
 // TODO: Maybe would be better to send a specific struct instead of using Request and Response directly
 // For handler cookies and other things
 pub type EndpointHandler =
     Arc<dyn Fn(&Request, &mut Response) -> Box<dyn HttpResponse> + Send + Sync + 'static>;
+
+/// Runs before the handler of every route of a [`Router`](crate::Router), with mutable
+/// access to the request so that it can attach data, such as the authenticated user, to
+/// its [extensions](Request::extensions_mut). Returning a response answers the request
+/// without calling the handler.
+pub type Middleware = Arc<
+    dyn Fn(&mut Request, &mut Response) -> Option<Box<dyn HttpResponse>> + Send + Sync + 'static,
+>;
+
+/// A function whose arguments are all [extractors](crate::extract), such as
+/// `fn(Path<(u64,)>, Json<CreateUser>) -> OkResponse`.
+///
+/// Implemented for functions of up to eight arguments. `Args` is the tuple of argument
+/// types and only exists to tell the implementations apart.
+pub trait Handler<Args>: Send + Sync + 'static {
+    /// Extracts the arguments from the request and calls the function.
+    fn call(&self, req: &Request) -> Box<dyn HttpResponse>;
+}
+
+macro_rules! impl_handler {
+    ($($arg:ident),*) => {
+        impl<F, R, $($arg,)*> Handler<($($arg,)*)> for F
+        where
+            F: Fn($($arg,)*) -> R + Send + Sync + 'static,
+            R: Into<Box<dyn HttpResponse>>,
+            $($arg: FromRequest,)*
+        {
+            #[allow(non_snake_case, unused_variables)]
+            fn call(&self, req: &Request) -> Box<dyn HttpResponse> {
+                $(
+                    let $arg = match $arg::from_request(req) {
+                        Ok(value) => value,
+                        Err(rejection) => return rejection,
+                    };
+                )*
+
+                (self)($($arg,)*).into()
+            }
+        }
+    };
+}
+
+impl_handler!();
+impl_handler!(A1);
+impl_handler!(A1, A2);
+impl_handler!(A1, A2, A3);
+impl_handler!(A1, A2, A3, A4);
+impl_handler!(A1, A2, A3, A4, A5);
+impl_handler!(A1, A2, A3, A4, A5, A6);
+impl_handler!(A1, A2, A3, A4, A5, A6, A7);
+impl_handler!(A1, A2, A3, A4, A5, A6, A7, A8);
+
+/// Turns a function taking extractors into an [`EndpointHandler`] for the router.
+pub fn handler<H: Handler<Args>, Args: 'static>(handler: H) -> EndpointHandler {
+    Arc::new(move |req: &Request, _res: &mut Response| handler.call(req))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        StatusCode,
+        extract::{Path, Query},
+        responses::OkResponse,
+    };
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_extractor_handler() {
+        let endpoint = handler(|Path((id,)): Path<(u64,)>, Query(page): Query<u32>| {
+            OkResponse::from(id * 100 + page as u64)
+        });
+
+        let mut req = Request::from_reader(&b"GET /items/4?page=2 HTTP/1.1\r\n\r\n"[..])
+            .await
+            .unwrap();
+        req.set_params(vec![("id".to_string(), "4".to_string())]);
+
+        let result = endpoint(&req, &mut Response::new());
+        assert_eq!(result.status_code(), StatusCode::Ok);
+        assert_eq!(result.into_response(), b"402");
+
+        req.set_params(vec![("id".to_string(), "four".to_string())]);
+        let result = endpoint(&req, &mut Response::new());
+        assert_eq!(result.status_code(), StatusCode::BadRequest);
+    }
+}
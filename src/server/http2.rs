@@ -0,0 +1,712 @@
+//! HTTP/2 support, available with the `http2` feature.
+//!
+//! HTTP/2 is used when negotiated through ALPN over TLS, or over cleartext when a client
+//! starts with the HTTP/2 connection preface (prior knowledge) or asks for it with
+//! `Upgrade: h2c`. An upgraded request is answered over HTTP/2 as stream 1, unless its body
+//! is larger than the initial flow control window, in which case it is answered over
+//! HTTP/1.1.
+
+use std::{io, sync::Arc, time::Instant};
+
+use bytes::Bytes;
+use h2::{
+    RecvStream,
+    server::{self, SendResponse},
+};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+use crate::{
+    Request,
+    headers::Headers,
+    response::{Response, StatusCode},
+    responses::{BadRequestError, ContentTooLargeError, NotImplementedError},
+    server::{
+        connection::{Connection, Describe},
+        observe::{self, Observation},
+        router::RoutesHandler,
+        server::{Options, handle_error, respond},
+        upgrade::Rewind,
+    },
+};
+
+pub(crate) const ALPN_H2: &[u8] = b"h2";
+
+const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+const HTTP_VERSION: &str = "HTTP/2";
+const MAX_CONCURRENT_STREAMS: u32 = 100;
+const CHUNK_SIZE: usize = 16 * 1024;
+/// The largest request body read from a stream. Larger bodies are answered with 413
+/// Content Too Large.
+const MAX_BODY_SIZE: usize = 16 * 1024 * 1024;
+
+/// The default SETTINGS_MAX_FRAME_SIZE, and the default SETTINGS_INITIAL_WINDOW_SIZE that
+/// bounds the body of an upgraded request.
+const MAX_FRAME_SIZE: usize = 16 * 1024;
+const INITIAL_WINDOW_SIZE: usize = 65_535;
+
+const FRAME_HEADER_SIZE: usize = 9;
+const FRAME_DATA: u8 = 0x0;
+const FRAME_HEADERS: u8 = 0x1;
+const FRAME_SETTINGS: u8 = 0x4;
+const FLAG_END_STREAM: u8 = 0x1;
+const FLAG_END_HEADERS: u8 = 0x4;
+const FLAG_ACK: u8 = 0x1;
+
+const SWITCHING_PROTOCOLS: &[u8] =
+    b"HTTP/1.1 101 Switching Protocols\r\nConnection: Upgrade\r\nUpgrade: h2c\r\n\r\n";
+
+/// Headers that only apply to a single HTTP/1 connection and are forbidden in HTTP/2.
+const CONNECTION_HEADERS: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "transfer-encoding",
+    "upgrade",
+];
+
+/// The protocol spoken by a client.
+pub(super) enum Protocol<S> {
+    Http1(Rewind<S>),
+    Http2(Rewind<S>),
+}
+
+/// Detects whether the client speaks HTTP/2, from ALPN or else from the connection
+/// preface. Bytes read while looking for the preface are replayed by the returned stream.
+pub(super) async fn detect<S: Connection>(mut stream: S) -> io::Result<Protocol<S>> {
+    match stream.alpn_protocol() {
+        Some(protocol) if protocol == ALPN_H2 => {
+            return Ok(Protocol::Http2(Rewind::new(Vec::new(), stream)));
+        }
+        Some(_) => return Ok(Protocol::Http1(Rewind::new(Vec::new(), stream))),
+        None => {}
+    }
+
+    let mut prefix = Vec::new();
+    let mut buffer = [0; PREFACE.len()];
+
+    // Stop as soon as the bytes cannot be the preface, so HTTP/1 requests are not delayed
+    while prefix.len() < PREFACE.len() && PREFACE.starts_with(&prefix) {
+        let read = stream
+            .read(&mut buffer[..PREFACE.len() - prefix.len()])
+            .await?;
+        if read == 0 {
+            break;
+        }
+
+        prefix.extend_from_slice(&buffer[..read]);
+    }
+
+    let is_http2 = prefix == PREFACE;
+    let stream = Rewind::new(prefix, stream);
+
+    Ok(if is_http2 {
+        Protocol::Http2(stream)
+    } else {
+        Protocol::Http1(stream)
+    })
+}
+
+/// Returns the frames replaying `request` as stream 1 when it asks to be upgraded to h2c,
+/// following RFC 7540 section 3.2.
+///
+/// The values of `HTTP2-Settings` are only validated: the client sends the same settings
+/// in its first SETTINGS frame, which is processed before the replayed request.
+pub(super) fn upgrade_frames(request: &Request) -> Option<Vec<u8>> {
+    let headers = request.headers();
+    let has_token = |key: &str, token: &str| {
+        headers.get::<String>(key).is_some_and(|value| {
+            value
+                .split(',')
+                .any(|item| item.trim().eq_ignore_ascii_case(token))
+        })
+    };
+
+    if !has_token("Upgrade", "h2c")
+        || !has_token("Connection", "Upgrade")
+        || !has_token("Connection", "HTTP2-Settings")
+        || !headers
+            .get::<String>("HTTP2-Settings")
+            .is_some_and(|settings| is_settings_payload(&settings))
+        || request.body().len() > INITIAL_WINDOW_SIZE
+    {
+        return None;
+    }
+
+    let mut block = Vec::new();
+    encode_header(&mut block, ":method", request.method());
+    encode_header(&mut block, ":scheme", "http");
+    encode_header(&mut block, ":path", request.target());
+    if let Some(host) = headers.get::<String>("Host") {
+        encode_header(&mut block, ":authority", &host);
+    }
+
+    for (key, value) in headers.iter() {
+        let key = key.to_ascii_lowercase();
+        let is_connection_header = CONNECTION_HEADERS.contains(&key.as_str())
+            || key == "http2-settings"
+            || key == "host"
+            || (key == "te" && !value.eq_ignore_ascii_case("trailers"));
+
+        if !is_connection_header {
+            encode_header(&mut block, &key, value);
+        }
+    }
+
+    // Headers too large for a single frame would need CONTINUATION frames
+    if block.len() > MAX_FRAME_SIZE {
+        return None;
+    }
+
+    let body = request.body();
+    let mut frames = Vec::new();
+    let flags = if body.is_empty() {
+        FLAG_END_HEADERS | FLAG_END_STREAM
+    } else {
+        FLAG_END_HEADERS
+    };
+    write_frame(&mut frames, FRAME_HEADERS, flags, &block);
+
+    let mut chunks = body.chunks(MAX_FRAME_SIZE).peekable();
+    while let Some(chunk) = chunks.next() {
+        let flags = if chunks.peek().is_none() {
+            FLAG_END_STREAM
+        } else {
+            0
+        };
+        write_frame(&mut frames, FRAME_DATA, flags, chunk);
+    }
+
+    Some(frames)
+}
+
+/// Switches an HTTP/1.1 connection to HTTP/2 after an `Upgrade: h2c` request, then serves
+/// it with the request replayed by `frames` as stream 1.
+pub(super) async fn serve_upgrade<S: Connection>(
+    stream: S,
+    leftover: Vec<u8>,
+    frames: Vec<u8>,
+    handler: RoutesHandler,
+    options: Arc<Options>,
+    describe: Describe,
+) {
+    let mut stream = Rewind::new(leftover, stream);
+
+    let prefix = async {
+        stream.write_all(SWITCHING_PROTOCOLS).await?;
+        stream.flush().await?;
+        read_client_preface(&mut stream).await
+    };
+    let mut prefix = match prefix.await {
+        Ok(prefix) => prefix,
+        Err(err) => {
+            eprintln!("HTTP/2 upgrade failed: {}", err);
+            return;
+        }
+    };
+
+    // The request must come after the client SETTINGS, which h2 expects first
+    prefix.extend_from_slice(&frames);
+
+    let stream = Rewind::new(prefix, stream);
+    if let Err(err) = serve_connection(stream, handler, options, describe).await {
+        eprintln!("HTTP/2 connection failed: {}", err);
+    }
+}
+
+/// Reads the connection preface of the client, up to the end of its first SETTINGS frame.
+async fn read_client_preface<S: Connection>(stream: &mut S) -> io::Result<Vec<u8>> {
+    let invalid = |message| io::Error::new(io::ErrorKind::InvalidData, message);
+
+    let mut prefix = vec![0; PREFACE.len() + FRAME_HEADER_SIZE];
+    stream.read_exact(&mut prefix).await?;
+    if !prefix.starts_with(PREFACE) {
+        return Err(invalid("missing connection preface"));
+    }
+
+    let header = &prefix[PREFACE.len()..];
+    let length = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+    if header[3] != FRAME_SETTINGS || header[4] & FLAG_ACK != 0 || length > MAX_FRAME_SIZE {
+        return Err(invalid("expected a SETTINGS frame after the preface"));
+    }
+
+    let start = prefix.len();
+    prefix.resize(start + length, 0);
+    stream.read_exact(&mut prefix[start..]).await?;
+
+    Ok(prefix)
+}
+
+/// Checks that `value` is the base64url encoding of a SETTINGS payload, made of 6 byte
+/// parameters.
+fn is_settings_payload(value: &str) -> bool {
+    let value = value.trim().trim_end_matches('=');
+    let is_base64url = value
+        .bytes()
+        .all(|byte| byte.is_ascii_alphanumeric() || byte == b'-' || byte == b'_');
+
+    is_base64url && value.len() % 4 != 1 && (value.len() * 3 / 4).is_multiple_of(6)
+}
+
+fn write_frame(frames: &mut Vec<u8>, kind: u8, flags: u8, payload: &[u8]) {
+    frames.extend_from_slice(&(payload.len() as u32).to_be_bytes()[1..]);
+    frames.push(kind);
+    frames.push(flags);
+    // Stream 1, which the upgraded request implicitly opened
+    frames.extend_from_slice(&1u32.to_be_bytes());
+    frames.extend_from_slice(payload);
+}
+
+/// Encodes a header as an HPACK literal without indexing, so no dynamic table is shared.
+fn encode_header(block: &mut Vec<u8>, key: &str, value: &str) {
+    block.push(0);
+    encode_string(block, key.as_bytes());
+    encode_string(block, value.as_bytes());
+}
+
+fn encode_string(block: &mut Vec<u8>, value: &[u8]) {
+    // A 7 bit prefix integer, without Huffman coding
+    let mut length = value.len();
+    if length < 0x7f {
+        block.push(length as u8);
+    } else {
+        block.push(0x7f);
+        length -= 0x7f;
+        while length >= 0x80 {
+            block.push((length % 0x80) as u8 | 0x80);
+            length /= 0x80;
+        }
+        block.push(length as u8);
+    }
+
+    block.extend_from_slice(value);
+}
+
+/// Serves the streams of an HTTP/2 connection until the client closes it or the server
+/// shuts down. SETTINGS, flow control, HPACK and GOAWAY are handled by the `h2` crate.
+pub(super) async fn serve_connection<S: Connection>(
+    stream: S,
+    handler: RoutesHandler,
+    options: Arc<Options>,
+    describe: Describe,
+) -> Result<(), h2::Error> {
+    let mut connection = server::Builder::new()
+        .max_concurrent_streams(MAX_CONCURRENT_STREAMS)
+        .handshake::<_, Bytes>(stream)
+        .await?;
+
+    // Once the server shuts down, the client is told with GOAWAY not to open new streams
+    // and the open ones are completed
+    let shutdown = options.shutdown.clone();
+    let triggered = shutdown.triggered();
+    tokio::pin!(triggered);
+    let mut shutting_down = false;
+
+    loop {
+        let result = tokio::select! {
+            result = connection.accept() => result,
+            _ = &mut triggered, if !shutting_down => {
+                shutting_down = true;
+                connection.graceful_shutdown();
+                continue;
+            }
+        };
+        let Some(result) = result else {
+            break;
+        };
+        let (request, send) = result?;
+
+        let handler = handler.clone();
+        let options = options.clone();
+        let describe = describe.clone();
+
+        tokio::spawn(observe::in_current_span(async move {
+            if let Err(err) = serve_stream(request, send, handler, options, describe).await {
+                eprintln!("Failed to respond to HTTP/2 stream: {}", err);
+            }
+        }));
+    }
+
+    Ok(())
+}
+
+async fn serve_stream(
+    request: http::Request<RecvStream>,
+    mut send: SendResponse<Bytes>,
+    handler: RoutesHandler,
+    options: Arc<Options>,
+    describe: Describe,
+) -> Result<(), h2::Error> {
+    let start = Instant::now();
+    let (parts, mut body) = request.into_parts();
+
+    let data = read_body(&parts, &mut body).await?;
+
+    let mut response = Response::new();
+    let mut observation = None;
+    match data.map(|data| build_request(&parts, data)) {
+        Some(Some(mut request)) => {
+            describe(&mut request);
+            let mut started = Observation::start(&options, &mut request, start);
+            started
+                .instrument(respond(&mut request, &handler, &options, &mut response))
+                .await;
+            started.set_route(request.route());
+            observation = Some(started);
+        }
+        Some(None) => {
+            if let Some(metrics) = &options.metrics {
+                metrics.add_parse_error();
+            }
+            response.set_result(BadRequestError::with_message("Malformed request").into());
+            handle_error(None, &options, &mut response);
+        }
+        None => {
+            response.set_result(
+                ContentTooLargeError::with_message(format!(
+                    "Request body exceeds {} bytes",
+                    MAX_BODY_SIZE
+                ))
+                .into(),
+            );
+            handle_error(None, &options, &mut response);
+        }
+    }
+
+    if response.take_upgrade().is_some() {
+        response = Response::new();
+        response.set_result(
+            NotImplementedError::with_message("Protocol upgrades require HTTP/1.1").into(),
+        );
+        handle_error(None, &options, &mut response);
+    }
+    response.set_default_headers();
+
+    if let Some(observation) = &mut observation {
+        observation.set_status(response.status_code());
+    }
+
+    let mut head = http::Response::builder().status(response.status_code() as u16);
+    for (key, value) in response.headers().iter() {
+        let is_connection_header = CONNECTION_HEADERS
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case(key));
+
+        if !is_connection_header {
+            head = head.header(key.as_str(), value.as_str());
+        }
+    }
+    let head = match head.body(()) {
+        Ok(head) => head,
+        Err(err) => {
+            eprintln!("Invalid HTTP/2 response headers: {}", err);
+            send.send_reset(h2::Reason::INTERNAL_ERROR);
+            return Ok(());
+        }
+    };
+
+    let body = response.take_body();
+    let end_of_stream = parts.method == http::Method::HEAD
+        || response.status_code() == StatusCode::NotModified
+        || body.len() == Some(0);
+
+    let mut stream = send.send_response(head, end_of_stream)?;
+    if end_of_stream {
+        return Ok(());
+    }
+
+    let mut reader = match body.into_reader() {
+        Ok(reader) => reader,
+        Err(err) => {
+            eprintln!("Failed to read response body: {}", err);
+            stream.send_reset(h2::Reason::INTERNAL_ERROR);
+            return Ok(());
+        }
+    };
+
+    let mut buffer = vec![0; CHUNK_SIZE];
+    loop {
+        let read = match reader.read(&mut buffer).await {
+            Ok(read) => read,
+            Err(err) => {
+                eprintln!("Failed to read response body: {}", err);
+                stream.send_reset(h2::Reason::INTERNAL_ERROR);
+                return Ok(());
+            }
+        };
+
+        if read == 0 {
+            return stream.send_data(Bytes::new(), true);
+        }
+
+        // Only send as much as the flow control window of the client allows
+        let mut chunk = Bytes::copy_from_slice(&buffer[..read]);
+        while !chunk.is_empty() {
+            stream.reserve_capacity(chunk.len());
+
+            let capacity = match std::future::poll_fn(|cx| stream.poll_capacity(cx)).await {
+                Some(capacity) => capacity?,
+                None => return Ok(()),
+            };
+
+            let part = chunk.split_to(capacity.min(chunk.len()));
+            if let Some(observation) = &mut observation {
+                observation.add_bytes(part.len() as u64);
+            }
+            stream.send_data(part, false)?;
+        }
+    }
+}
+
+/// Reads the body of a stream, or returns `None` once it exceeds [`MAX_BODY_SIZE`]. The
+/// rest of the body is then refused with RST_STREAM after the response is sent.
+async fn read_body(
+    parts: &http::request::Parts,
+    body: &mut RecvStream,
+) -> Result<Option<Vec<u8>>, h2::Error> {
+    let content_length = parts
+        .headers
+        .get(http::header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok()?.parse::<usize>().ok());
+    if content_length.is_some_and(|length| length > MAX_BODY_SIZE) {
+        return Ok(None);
+    }
+
+    let mut data = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk?;
+        body.flow_control().release_capacity(chunk.len())?;
+
+        if data.len() + chunk.len() > MAX_BODY_SIZE {
+            return Ok(None);
+        }
+        data.extend_from_slice(&chunk);
+    }
+
+    Ok(Some(data))
+}
+
+fn build_request(parts: &http::request::Parts, body: Vec<u8>) -> Option<Request> {
+    let mut headers = Headers::new();
+
+    for (key, value) in parts.headers.iter() {
+        headers.add(key.as_str(), value.to_str().ok()?);
+    }
+
+    if let Some(authority) = parts.uri.authority()
+        && !headers.contains("Host")
+    {
+        headers.set("Host", authority.as_str());
+    }
+
+    let target = parts
+        .uri
+        .path_and_query()
+        .map(|target| target.as_str())
+        .unwrap_or("/");
+
+    Some(Request::from_parts(
+        parts.method.as_str(),
+        target,
+        HTTP_VERSION,
+        headers,
+        body,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncWriteExt;
+
+    use crate::{Router, extract::Path, handler, responses::OkResponse};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_detect_http1() {
+        let (mut client, server) = tokio::io::duplex(1024);
+        client.write_all(b"GET / HTTP/1.1\r\n\r\n").await.unwrap();
+
+        let Protocol::Http1(mut stream) = detect(server).await.unwrap() else {
+            panic!("expected HTTP/1");
+        };
+
+        let mut buffer = [0; 18];
+        stream.read_exact(&mut buffer).await.unwrap();
+        assert_eq!(&buffer, b"GET / HTTP/1.1\r\n\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_prior_knowledge_request() {
+        let mut router = Router::new();
+        router.get(
+            "/hello/:name",
+            handler(|Path(name): Path<String>| OkResponse::from(format!("hello {}", name))),
+        );
+        let routes = router.build();
+
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        tokio::spawn(async move {
+            let Ok(Protocol::Http2(stream)) = detect(server).await else {
+                panic!("expected HTTP/2");
+            };
+            let describe: Describe = Arc::new(|_: &mut Request| {});
+            serve_connection(stream, routes, Arc::new(Options::default()), describe)
+                .await
+                .unwrap();
+        });
+
+        let (mut client, connection) = h2::client::handshake(client).await.unwrap();
+        tokio::spawn(connection);
+
+        let request = http::Request::get("http://localhost/hello/ada?x=1")
+            .body(())
+            .unwrap();
+        let (response, _) = client.send_request(request, true).unwrap();
+        let response = response.await.unwrap();
+
+        assert_eq!(response.status(), 200);
+        assert!(response.headers().get("connection").is_none());
+
+        let mut body = response.into_body();
+        let mut data = Vec::new();
+        while let Some(chunk) = body.data().await {
+            data.extend_from_slice(&chunk.unwrap());
+        }
+        assert_eq!(data, b"\"hello ada\"");
+    }
+
+    #[tokio::test]
+    async fn test_body_too_large() {
+        let mut router = Router::new();
+        router.post("/upload", handler(|| OkResponse::from("uploaded")));
+        let routes = router.build();
+
+        let (client, server) = tokio::io::duplex(64 * 1024);
+        tokio::spawn(async move {
+            let describe: Describe = Arc::new(|_: &mut Request| {});
+            serve_connection(server, routes, Arc::new(Options::default()), describe)
+                .await
+                .unwrap();
+        });
+
+        let (mut client, connection) = h2::client::handshake(client).await.unwrap();
+        tokio::spawn(connection);
+
+        // Announced by Content-Length, before any data is sent
+        let request = http::Request::post("http://localhost/upload")
+            .header("content-length", (MAX_BODY_SIZE + 1).to_string())
+            .body(())
+            .unwrap();
+        let (response, _stream) = client.send_request(request, false).unwrap();
+        assert_eq!(response.await.unwrap().status(), 413);
+
+        // Streamed without a length, until it goes over the limit
+        let request = http::Request::post("http://localhost/upload")
+            .body(())
+            .unwrap();
+        let (response, mut stream) = client.send_request(request, false).unwrap();
+        let chunk = Bytes::from(vec![0; CHUNK_SIZE]);
+        let send = async {
+            for _ in 0..=MAX_BODY_SIZE / CHUNK_SIZE {
+                stream.reserve_capacity(CHUNK_SIZE);
+                while stream.capacity() < CHUNK_SIZE {
+                    match std::future::poll_fn(|cx| stream.poll_capacity(cx)).await {
+                        Some(Ok(_)) => {}
+                        _ => return,
+                    }
+                }
+                if stream.send_data(chunk.clone(), false).is_err() {
+                    return;
+                }
+            }
+        };
+        let (response, _) = tokio::join!(response, send);
+        assert_eq!(response.unwrap().status(), 413);
+    }
+
+    #[tokio::test]
+    async fn test_h2c_upgrade() {
+        let mut router = Router::new();
+        router.get(
+            "/hello/:name",
+            handler(|Path(name): Path<String>| OkResponse::from(format!("hello {}", name))),
+        );
+        let routes = router.build();
+
+        let request = Request::from_reader(
+            &b"GET /hello/ada HTTP/1.1\r\nHost: localhost\r\nConnection: Upgrade, HTTP2-Settings\r\n\
+              Upgrade: h2c\r\nHTTP2-Settings: AAMAAABkAAQCAAAAAAIAAAAA\r\n\r\n"[..],
+        )
+        .await
+        .unwrap();
+        let frames = upgrade_frames(&request).unwrap();
+
+        let (mut client, server) = tokio::io::duplex(64 * 1024);
+        tokio::spawn(async move {
+            let describe: Describe = Arc::new(|_: &mut Request| {});
+            serve_upgrade(
+                server,
+                Vec::new(),
+                frames,
+                routes,
+                Arc::new(Options::default()),
+                describe,
+            )
+            .await;
+        });
+
+        let mut switching = vec![0; SWITCHING_PROTOCOLS.len()];
+        client.read_exact(&mut switching).await.unwrap();
+        assert_eq!(switching, SWITCHING_PROTOCOLS);
+
+        client.write_all(PREFACE).await.unwrap();
+        client
+            .write_all(&[0, 0, 0, FRAME_SETTINGS, 0, 0, 0, 0, 0])
+            .await
+            .unwrap();
+
+        // Read frames until the end of stream 1, the response to the upgraded request
+        let mut status = None;
+        let mut data = Vec::new();
+        loop {
+            let mut header = [0; FRAME_HEADER_SIZE];
+            client.read_exact(&mut header).await.unwrap();
+            let length = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+            let mut payload = vec![0; length];
+            client.read_exact(&mut payload).await.unwrap();
+
+            let stream_id = u32::from_be_bytes([header[5], header[6], header[7], header[8]]);
+            if stream_id != 1 {
+                continue;
+            }
+
+            match header[3] {
+                // The indexed `:status: 200` field of the static table
+                FRAME_HEADERS => status = payload.first().copied(),
+                FRAME_DATA => data.extend_from_slice(&payload),
+                _ => {}
+            }
+            if header[4] & FLAG_END_STREAM != 0 {
+                break;
+            }
+        }
+
+        assert_eq!(status, Some(0x88));
+        assert_eq!(data, b"\"hello ada\"");
+    }
+
+    #[tokio::test]
+    async fn test_h2c_upgrade_requires_settings() {
+        let request = Request::from_reader(
+            &b"GET / HTTP/1.1\r\nConnection: Upgrade\r\nUpgrade: h2c\r\n\r\n"[..],
+        )
+        .await
+        .unwrap();
+        assert!(upgrade_frames(&request).is_none());
+
+        assert!(is_settings_payload("AAMAAABkAAQCAAAAAAIAAAAA"));
+        assert!(is_settings_payload(""));
+        assert!(!is_settings_payload("AAMAAABk, AAQCAAAA"));
+        assert!(!is_settings_payload("AAMA"));
+    }
+}
@@ -0,0 +1,276 @@
+//! Connection and request limits, enabled with
+//! [`Server::with_limits`](crate::Server::with_limits).
+//!
+//! The number of open connections and of requests being handled can each be capped.
+//! When a limit is reached, the server either waits for a connection or request to
+//! finish, or sheds the load by answering 503 Service Unavailable with a `Retry-After`
+//! header, as chosen with [`Overload`].
+//!
+//! The active counts are shared between the clones of a [`Limits`], whether they are made
+//! before or after it is given to the server, so a clone kept by the application can
+//! report them:
+//!
+//! ```no_run
+//! use std::time::Duration;
+//!
+//! use http_server::{Router, Server, limits::{Limits, Overload}};
+//!
+//! # async fn run() -> std::io::Result<()> {
+//! let limits = Limits::new()
+//!     .with_max_connections(10_000)
+//!     .with_max_requests(256)
+//!     .with_overload(Overload::Reject {
+//!         retry_after: Duration::from_secs(5),
+//!     });
+//!
+//! let monitor = limits.clone();
+//! tokio::spawn(async move {
+//!     loop {
+//!         tokio::time::sleep(Duration::from_secs(10)).await;
+//!         println!(
+//!             "{} connections, {} requests",
+//!             monitor.active_connections(),
+//!             monitor.active_requests()
+//!         );
+//!     }
+//! });
+//!
+//! Server::new("127.0.0.1:8080", Router::new())
+//!     .with_limits(limits)
+//!     .serve()
+//!     .await
+//! # }
+//! ```
+
+use std::{
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::responses::ServiceUnavailableError;
+
+/// The most connections over the limit answered at once. Further connections are closed
+/// without a response, so that a flood of connections cannot pile up tasks.
+const MAX_REJECTIONS: usize = 64;
+
+/// What the server does when a limit is reached.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Overload {
+    /// Waits for a slot to free up. New connections are left in the listen backlog of the
+    /// operating system, and requests wait before their handler runs.
+    Wait,
+    /// Answers 503 Service Unavailable, telling the client to retry after `retry_after`.
+    ///
+    /// Connections over the limit are answered over HTTP/1.1 and closed, or closed right
+    /// away when many of them are already being answered.
+    Reject { retry_after: Duration },
+}
+
+/// The maximum number of open connections and of requests in flight.
+///
+/// The limits are fixed once given to [`Server::with_limits`](crate::Server::with_limits).
+#[derive(Debug, Clone)]
+pub struct Limits {
+    max_connections: Option<usize>,
+    max_requests: Option<usize>,
+    overload: Overload,
+    /// Built once by the server, from its copy of the limits
+    state: Arc<OnceLock<State>>,
+}
+
+#[derive(Debug)]
+struct State {
+    connections: Slots,
+    requests: Slots,
+    rejections: Slots,
+}
+
+/// A number of slots, each held by an active connection or request.
+#[derive(Debug)]
+struct Slots {
+    capacity: usize,
+    available: Arc<Semaphore>,
+}
+
+impl Slots {
+    fn new(max: Option<usize>) -> Self {
+        let capacity = max.unwrap_or(Semaphore::MAX_PERMITS);
+
+        Slots {
+            capacity,
+            available: Arc::new(Semaphore::new(capacity)),
+        }
+    }
+
+    fn active(&self) -> usize {
+        self.capacity - self.available.available_permits()
+    }
+
+    fn try_acquire(&self) -> Option<OwnedSemaphorePermit> {
+        self.available.clone().try_acquire_owned().ok()
+    }
+
+    async fn acquire(&self) -> OwnedSemaphorePermit {
+        self.available
+            .clone()
+            .acquire_owned()
+            .await
+            // The semaphore is never closed
+            .expect("Limit semaphore closed")
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Limits {
+    /// Creates a new Limits without any limit, waiting when one is set and reached.
+    pub fn new() -> Self {
+        Limits {
+            max_connections: None,
+            max_requests: None,
+            overload: Overload::Wait,
+            state: Arc::new(OnceLock::new()),
+        }
+    }
+
+    /// Sets the maximum number of open connections, across all the listeners.
+    pub fn with_max_connections(mut self, max: usize) -> Self {
+        self.max_connections = Some(max);
+        self
+    }
+
+    /// Sets the maximum number of requests being handled at once, across all the
+    /// connections.
+    pub fn with_max_requests(mut self, max: usize) -> Self {
+        self.max_requests = Some(max);
+        self
+    }
+
+    /// Sets what happens when a limit is reached. Defaults to [`Overload::Wait`].
+    pub fn with_overload(mut self, overload: Overload) -> Self {
+        self.overload = overload;
+        self
+    }
+
+    /// Returns the number of open connections.
+    pub fn active_connections(&self) -> usize {
+        self.state
+            .get()
+            .map_or(0, |state| state.connections.active())
+    }
+
+    /// Returns the number of requests being handled.
+    pub fn active_requests(&self) -> usize {
+        self.state.get().map_or(0, |state| state.requests.active())
+    }
+
+    /// Builds the slots shared by every clone from these limits, unless a clone already
+    /// did.
+    pub(crate) fn build(&self) {
+        self.state();
+    }
+
+    fn state(&self) -> &State {
+        self.state.get_or_init(|| State {
+            connections: Slots::new(self.max_connections),
+            requests: Slots::new(self.max_requests),
+            rejections: Slots::new(Some(MAX_REJECTIONS)),
+        })
+    }
+
+    /// Waits for a free connection slot before accepting, when overload waits, so that
+    /// connections stay in the listen backlog while the server is full.
+    pub(crate) async fn connection_ready(&self) {
+        if self.overload == Overload::Wait {
+            drop(self.state().connections.acquire().await);
+        }
+    }
+
+    /// Takes a connection slot for an accepted connection, or returns `None` when the
+    /// connection is to be rejected.
+    pub(crate) async fn start_connection(&self) -> Option<OwnedSemaphorePermit> {
+        match self.overload {
+            // Another listener may have taken the slot in the meantime
+            Overload::Wait => Some(self.state().connections.acquire().await),
+            Overload::Reject { .. } => self.state().connections.try_acquire(),
+        }
+    }
+
+    /// Takes a slot to answer a connection over the limit, or returns `None` when too many
+    /// are being answered and the connection is to be closed.
+    pub(crate) fn start_rejection(&self) -> Option<OwnedSemaphorePermit> {
+        self.state().rejections.try_acquire()
+    }
+
+    #[cfg(test)]
+    pub(crate) fn active_rejections(&self) -> usize {
+        self.state().rejections.active()
+    }
+
+    /// Takes a request slot, held until the response is ready, or returns the error to
+    /// answer the request with.
+    pub(crate) async fn start_request(
+        &self,
+    ) -> Result<OwnedSemaphorePermit, ServiceUnavailableError> {
+        match self.overload {
+            Overload::Wait => Ok(self.state().requests.acquire().await),
+            Overload::Reject { .. } => self
+                .state()
+                .requests
+                .try_acquire()
+                .ok_or_else(|| self.rejection()),
+        }
+    }
+
+    /// Returns the error answering connections and requests over the limits.
+    pub(crate) fn rejection(&self) -> ServiceUnavailableError {
+        let error = ServiceUnavailableError::with_message("The server is overloaded");
+
+        match self.overload {
+            Overload::Reject { retry_after } => error.with_retry_after(retry_after),
+            Overload::Wait => error,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_reject_requests() {
+        // Clones share the counts even when made before the limits are configured
+        let monitor = Limits::new();
+        let limits = monitor
+            .clone()
+            .with_max_requests(1)
+            .with_overload(Overload::Reject {
+                retry_after: Duration::from_secs(3),
+            });
+        limits.build();
+
+        let Ok(first) = limits.start_request().await else {
+            panic!("The first request was rejected");
+        };
+        assert_eq!(monitor.active_requests(), 1);
+        assert_eq!(monitor.active_connections(), 0);
+
+        let Err(error) = limits.start_request().await else {
+            panic!("The second request was admitted");
+        };
+        assert_eq!(
+            crate::responses::HttpError::headers(&error),
+            [(crate::headers::keys::RETRY_AFTER_HEADER, "3".to_string())]
+        );
+
+        drop(first);
+        assert_eq!(monitor.active_requests(), 0);
+        assert!(limits.start_request().await.is_ok());
+    }
+}
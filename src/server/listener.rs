@@ -0,0 +1,399 @@
+use std::{fmt, io, net::SocketAddr};
+
+use tokio::net::{TcpListener, TcpStream};
+
+#[cfg(unix)]
+use std::{
+    fs,
+    os::{
+        fd::{FromRawFd, OwnedFd, RawFd},
+        unix::fs::{FileTypeExt, PermissionsExt},
+    },
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+};
+#[cfg(unix)]
+use tokio::net::{UnixListener, UnixStream};
+
+#[cfg(feature = "tls")]
+use crate::tls::TlsConfig;
+
+/// The first file descriptor passed by systemd socket activation.
+#[cfg(unix)]
+const LISTEN_FDS_START: RawFd = 3;
+
+/// Whether the sockets passed through `LISTEN_FDS` have been taken, since each file
+/// descriptor can only be owned once.
+#[cfg(unix)]
+static LISTEN_FDS_TAKEN: AtomicBool = AtomicBool::new(false);
+
+/// Where a [`Server`](crate::Server) accepts connections.
+///
+/// # Example
+///
+/// Serving behind a local reverse proxy over a Unix socket:
+///
+/// ```no_run
+/// use http_server::{Listener, Router, Server};
+///
+/// # async fn run() -> std::io::Result<()> {
+/// let listener = Listener::unix("/run/app/http.sock").with_permissions(0o660);
+///
+/// Server::from_listener(listener, Router::new()).serve().await
+/// # }
+/// ```
+pub struct Listener {
+    kind: Kind,
+    #[cfg(feature = "tls")]
+    tls: Option<TlsConfig>,
+}
+
+#[derive(Debug)]
+enum Kind {
+    Tcp(String),
+    StdTcp(std::net::TcpListener),
+    #[cfg(unix)]
+    Unix {
+        path: PathBuf,
+        mode: Option<u32>,
+    },
+    #[cfg(unix)]
+    StdUnix(std::os::unix::net::UnixListener),
+}
+
+impl Listener {
+    /// Creates a new Listener binding a TCP socket to `addr`, such as `127.0.0.1:8080`.
+    pub fn tcp(addr: &str) -> Self {
+        Listener {
+            #[cfg(feature = "tls")]
+            tls: None,
+            kind: Kind::Tcp(addr.to_string()),
+        }
+    }
+
+    /// Creates a new Listener binding a Unix domain socket at `path`.
+    ///
+    /// A socket file left at `path` by a previous run is replaced, and the file is removed
+    /// once the server stops.
+    #[cfg(unix)]
+    pub fn unix<P: AsRef<Path>>(path: P) -> Self {
+        Listener {
+            #[cfg(feature = "tls")]
+            tls: None,
+            kind: Kind::Unix {
+                path: path.as_ref().to_path_buf(),
+                mode: None,
+            },
+        }
+    }
+
+    /// Sets the permissions of the socket file of a Unix listener, such as `0o660` to
+    /// only let the owner and group connect. Other listeners are left unchanged.
+    #[cfg(unix)]
+    pub fn with_permissions(mut self, permissions: u32) -> Self {
+        if let Kind::Unix { mode, .. } = &mut self.kind {
+            *mode = Some(permissions);
+        }
+        self
+    }
+
+    /// Serves HTTPS on this listener, whatever the server was configured with.
+    #[cfg(feature = "tls")]
+    pub fn with_tls(mut self, tls: TlsConfig) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    #[cfg(feature = "tls")]
+    pub(crate) fn tls(&self) -> Option<&TlsConfig> {
+        self.tls.as_ref()
+    }
+
+    /// Creates a new Listener from an inherited file descriptor of a listening TCP or
+    /// Unix domain socket.
+    #[cfg(unix)]
+    pub fn from_fd(fd: OwnedFd) -> Self {
+        let listener = std::net::TcpListener::from(fd);
+
+        // Only TCP sockets have an address std can represent
+        if listener.local_addr().is_ok() {
+            return Listener::from(listener);
+        }
+
+        Listener::from(std::os::unix::net::UnixListener::from(OwnedFd::from(
+            listener,
+        )))
+    }
+
+    /// Returns the sockets passed by systemd socket activation, in the order of the
+    /// `ListenStream=` lines of the socket unit.
+    ///
+    /// The list is empty when `LISTEN_FDS` is not set for this process, and when the
+    /// sockets have already been taken by a previous call.
+    #[cfg(unix)]
+    pub fn from_listen_fds() -> io::Result<Vec<Listener>> {
+        let for_this_process = std::env::var("LISTEN_PID")
+            .ok()
+            .and_then(|pid| pid.parse::<u32>().ok())
+            .is_some_and(|pid| pid == std::process::id());
+
+        if !for_this_process {
+            return Ok(Vec::new());
+        }
+
+        let count = match std::env::var("LISTEN_FDS") {
+            Ok(count) => count.parse::<RawFd>().map_err(|_| {
+                io::Error::new(io::ErrorKind::InvalidInput, "Invalid LISTEN_FDS value")
+            })?,
+            Err(_) => return Ok(Vec::new()),
+        };
+
+        if LISTEN_FDS_TAKEN.swap(true, Ordering::SeqCst) {
+            return Ok(Vec::new());
+        }
+
+        let listeners = (LISTEN_FDS_START..LISTEN_FDS_START + count)
+            // SAFETY: systemd passes `count` open sockets from LISTEN_FDS_START onwards,
+            // and LISTEN_FDS_TAKEN ensures they are only owned once
+            .map(|fd| Listener::from_fd(unsafe { OwnedFd::from_raw_fd(fd) }))
+            .collect();
+
+        Ok(listeners)
+    }
+
+    /// Binds the socket, or prepares the already bound one for use with tokio.
+    pub(crate) async fn bind(self) -> io::Result<Bound> {
+        match self.kind {
+            Kind::Tcp(addr) => Ok(Bound::Tcp(TcpListener::bind(addr).await?)),
+            Kind::StdTcp(listener) => {
+                listener.set_nonblocking(true)?;
+                Ok(Bound::Tcp(TcpListener::from_std(listener)?))
+            }
+            #[cfg(unix)]
+            Kind::Unix { path, mode } => {
+                remove_stale_socket(&path)?;
+                let listener = UnixListener::bind(&path)?;
+                let cleanup = SocketFile(path);
+
+                if let Some(mode) = mode {
+                    fs::set_permissions(&cleanup.0, fs::Permissions::from_mode(mode))?;
+                }
+
+                Ok(Bound::Unix {
+                    listener,
+                    _file: Some(cleanup),
+                })
+            }
+            #[cfg(unix)]
+            Kind::StdUnix(listener) => {
+                listener.set_nonblocking(true)?;
+                Ok(Bound::Unix {
+                    listener: UnixListener::from_std(listener)?,
+                    _file: None,
+                })
+            }
+        }
+    }
+}
+
+impl From<std::net::TcpListener> for Listener {
+    /// Creates a new Listener from an already bound TCP listener.
+    fn from(listener: std::net::TcpListener) -> Self {
+        Listener {
+            #[cfg(feature = "tls")]
+            tls: None,
+            kind: Kind::StdTcp(listener),
+        }
+    }
+}
+
+#[cfg(unix)]
+impl From<std::os::unix::net::UnixListener> for Listener {
+    /// Creates a new Listener from an already bound Unix domain socket listener.
+    fn from(listener: std::os::unix::net::UnixListener) -> Self {
+        Listener {
+            #[cfg(feature = "tls")]
+            tls: None,
+            kind: Kind::StdUnix(listener),
+        }
+    }
+}
+
+impl fmt::Debug for Listener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("Listener");
+        debug.field("kind", &self.kind);
+        #[cfg(feature = "tls")]
+        debug.field("tls", &self.tls.is_some());
+        debug.finish()
+    }
+}
+
+impl fmt::Display for Listener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            Kind::Tcp(addr) => write!(f, "{}", addr),
+            Kind::StdTcp(listener) => match listener.local_addr() {
+                Ok(addr) => write!(f, "{}", addr),
+                Err(_) => write!(f, "TCP listener"),
+            },
+            #[cfg(unix)]
+            Kind::Unix { path, .. } => write!(f, "unix:{}", path.display()),
+            #[cfg(unix)]
+            Kind::StdUnix(listener) => match listener
+                .local_addr()
+                .ok()
+                .and_then(|addr| addr.as_pathname().map(Path::to_path_buf))
+            {
+                Some(path) => write!(f, "unix:{}", path.display()),
+                None => write!(f, "Unix listener"),
+            },
+        }
+    }
+}
+
+/// The address a listener is bound to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LocalAddr {
+    Tcp(SocketAddr),
+    /// The path of a Unix domain socket, if it has one.
+    #[cfg(unix)]
+    Unix(Option<PathBuf>),
+}
+
+impl LocalAddr {
+    /// Returns the address of a TCP listener, including the port picked by the system
+    /// when binding to port 0.
+    pub fn tcp(&self) -> Option<SocketAddr> {
+        match self {
+            LocalAddr::Tcp(addr) => Some(*addr),
+            #[cfg(unix)]
+            LocalAddr::Unix(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for LocalAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LocalAddr::Tcp(addr) => write!(f, "{}", addr),
+            #[cfg(unix)]
+            LocalAddr::Unix(Some(path)) => write!(f, "unix:{}", path.display()),
+            #[cfg(unix)]
+            LocalAddr::Unix(None) => write!(f, "unix:(unnamed)"),
+        }
+    }
+}
+
+/// Removes the socket file left at `path` by a server that did not stop cleanly, so it
+/// can be bound again. Other kinds of files are kept, and binding fails.
+#[cfg(unix)]
+fn remove_stale_socket(path: &Path) -> io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => fs::remove_file(path),
+        _ => Ok(()),
+    }
+}
+
+/// Removes the socket file of a Unix listener when dropped.
+#[cfg(unix)]
+pub(crate) struct SocketFile(PathBuf);
+
+#[cfg(unix)]
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// A socket accepting connections.
+pub(crate) enum Bound {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix {
+        listener: UnixListener,
+        /// Removed along with the listener, if it was bound by the server.
+        _file: Option<SocketFile>,
+    },
+}
+
+/// A connection accepted by a [`Bound`] listener.
+pub(crate) enum Accepted {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Bound {
+    pub(crate) fn local_addr(&self) -> io::Result<LocalAddr> {
+        match self {
+            Bound::Tcp(listener) => Ok(LocalAddr::Tcp(listener.local_addr()?)),
+            #[cfg(unix)]
+            Bound::Unix { listener, .. } => Ok(LocalAddr::Unix(
+                listener.local_addr()?.as_pathname().map(Path::to_path_buf),
+            )),
+        }
+    }
+
+    pub(crate) async fn accept(&self) -> io::Result<Accepted> {
+        match self {
+            Bound::Tcp(listener) => Ok(Accepted::Tcp(listener.accept().await?.0)),
+            #[cfg(unix)]
+            Bound::Unix { listener, .. } => Ok(Accepted::Unix(listener.accept().await?.0)),
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use crate::{Router, Server, responses::OkResponse};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_unix_socket() {
+        let path = std::env::temp_dir().join(format!("http-server-{}.sock", std::process::id()));
+
+        let mut router = Router::new();
+        router.get("/", crate::handler(|| OkResponse::from("over unix")));
+
+        let listener = Listener::unix(&path).with_permissions(0o600);
+        let server = tokio::spawn(Server::from_listener(listener, router).serve());
+
+        let mut stream = loop {
+            match UnixStream::connect(&path).await {
+                Ok(stream) => break stream,
+                Err(_) => tokio::task::yield_now().await,
+            }
+        };
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        stream.write_all(b"GET / HTTP/1.1\r\n\r\n").await.unwrap();
+        let mut output = String::new();
+        stream.read_to_string(&mut output).await.unwrap();
+        assert!(output.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(output.ends_with("\"over unix\""));
+
+        server.abort();
+        let _ = server.await;
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_from_fd() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let listener = Listener::from_fd(OwnedFd::from(listener));
+        assert_eq!(listener.to_string(), addr.to_string());
+
+        let path = std::env::temp_dir().join(format!("http-server-fd-{}.sock", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+        let listener = Listener::from_fd(OwnedFd::from(listener));
+        assert_eq!(listener.to_string(), format!("unix:{}", path.display()));
+        fs::remove_file(&path).unwrap();
+    }
+}
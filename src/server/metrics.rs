@@ -0,0 +1,439 @@
+//! Prometheus metrics, enabled with [`Server::with_metrics`](crate::Server::with_metrics).
+//!
+//! Requests are counted and timed by method, route and status class, and are labelled
+//! with the path of the matched route, such as `/users/:id`, rather than the requested
+//! path so that the number of series stays bounded. The metrics are served in the
+//! Prometheus text exposition format, at `/metrics` by default:
+//!
+//! | Metric | Type | Labels |
+//! |---|---|---|
+//! | `http_server_requests_total` | counter | `method`, `route`, `status` |
+//! | `http_server_request_duration_seconds` | histogram | `method`, `route`, `status` |
+//! | `http_server_requests_in_flight` | gauge | |
+//! | `http_server_open_connections` | gauge | |
+//! | `http_server_request_body_bytes_total` | counter | |
+//! | `http_server_response_body_bytes_total` | counter | |
+//! | `http_server_parse_errors_total` | counter | |
+
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicI64, AtomicU64, Ordering},
+    },
+    time::Instant,
+};
+
+use crate::{
+    Request, StatusCode,
+    headers::{Headers, keys},
+    responses::HttpResponse,
+};
+
+const DEFAULT_PATH: &str = "/metrics";
+const DEFAULT_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// The route label of requests that matched no route.
+const UNMATCHED_ROUTE: &str = "unmatched";
+/// The method label of requests with a non-standard method, which could otherwise be
+/// used to create any number of series.
+const OTHER_METHOD: &str = "_OTHER";
+const KNOWN_METHODS: &[&str] = &[
+    "GET", "HEAD", "POST", "PUT", "DELETE", "CONNECT", "OPTIONS", "TRACE", "PATCH",
+];
+
+/// The labels of a request series.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct SeriesKey {
+    method: &'static str,
+    route: String,
+    status: String,
+}
+
+struct Series {
+    count: u64,
+    sum: f64,
+    /// The number of requests that took at most each bucket bound.
+    buckets: Vec<u64>,
+}
+
+struct Registry {
+    buckets: Vec<f64>,
+    requests: Mutex<BTreeMap<SeriesKey, Series>>,
+    in_flight: AtomicI64,
+    open_connections: AtomicI64,
+    request_bytes: AtomicU64,
+    response_bytes: AtomicU64,
+    parse_errors: AtomicU64,
+}
+
+impl Registry {
+    fn new(buckets: Vec<f64>) -> Self {
+        Registry {
+            buckets,
+            requests: Mutex::new(BTreeMap::new()),
+            in_flight: AtomicI64::new(0),
+            open_connections: AtomicI64::new(0),
+            request_bytes: AtomicU64::new(0),
+            response_bytes: AtomicU64::new(0),
+            parse_errors: AtomicU64::new(0),
+        }
+    }
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn write_header(output: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(output, "# HELP {} {}", name, help);
+    let _ = writeln!(output, "# TYPE {} {}", name, kind);
+}
+
+/// The metrics of a server, which can be cloned to read them from elsewhere.
+#[derive(Clone)]
+pub struct Metrics {
+    path: String,
+    registry: Arc<Registry>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Metrics {
+    /// Creates a new Metrics served at `/metrics`, with latency buckets from 5 ms to 10 s.
+    pub fn new() -> Self {
+        Metrics {
+            path: DEFAULT_PATH.to_string(),
+            registry: Arc::new(Registry::new(DEFAULT_BUCKETS.to_vec())),
+        }
+    }
+
+    /// Sets the path the metrics are served at.
+    pub fn with_path(mut self, path: &str) -> Self {
+        self.path = path.to_string();
+        self
+    }
+
+    /// Sets the upper bounds, in seconds, of the request duration histogram buckets.
+    ///
+    /// This resets the metrics recorded so far.
+    pub fn with_buckets(mut self, mut buckets: Vec<f64>) -> Self {
+        buckets.retain(|bound| bound.is_finite());
+        buckets.sort_by(f64::total_cmp);
+        buckets.dedup();
+
+        self.registry = Arc::new(Registry::new(buckets));
+        self
+    }
+
+    /// Renders the metrics in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let registry = &self.registry;
+        let mut output = String::new();
+
+        let requests = match registry.requests.lock() {
+            Ok(requests) => requests,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+
+        write_header(
+            &mut output,
+            "http_server_requests_total",
+            "counter",
+            "Number of HTTP requests completed.",
+        );
+        for (key, series) in requests.iter() {
+            let _ = writeln!(
+                output,
+                "http_server_requests_total{{{}}} {}",
+                key.labels(),
+                series.count
+            );
+        }
+
+        write_header(
+            &mut output,
+            "http_server_request_duration_seconds",
+            "histogram",
+            "Time taken to serve HTTP requests.",
+        );
+        for (key, series) in requests.iter() {
+            let labels = key.labels();
+            for (bound, count) in registry.buckets.iter().zip(&series.buckets) {
+                let _ = writeln!(
+                    output,
+                    "http_server_request_duration_seconds_bucket{{{},le=\"{}\"}} {}",
+                    labels, bound, count
+                );
+            }
+            let _ = writeln!(
+                output,
+                "http_server_request_duration_seconds_bucket{{{},le=\"+Inf\"}} {}",
+                labels, series.count
+            );
+            let _ = writeln!(
+                output,
+                "http_server_request_duration_seconds_sum{{{}}} {}",
+                labels, series.sum
+            );
+            let _ = writeln!(
+                output,
+                "http_server_request_duration_seconds_count{{{}}} {}",
+                labels, series.count
+            );
+        }
+        drop(requests);
+
+        let values = [
+            (
+                "http_server_requests_in_flight",
+                "gauge",
+                "Number of HTTP requests being served.",
+                registry.in_flight.load(Ordering::Relaxed).to_string(),
+            ),
+            (
+                "http_server_open_connections",
+                "gauge",
+                "Number of open client connections.",
+                registry
+                    .open_connections
+                    .load(Ordering::Relaxed)
+                    .to_string(),
+            ),
+            (
+                "http_server_request_body_bytes_total",
+                "counter",
+                "Bytes received in request bodies.",
+                registry.request_bytes.load(Ordering::Relaxed).to_string(),
+            ),
+            (
+                "http_server_response_body_bytes_total",
+                "counter",
+                "Bytes sent in response bodies.",
+                registry.response_bytes.load(Ordering::Relaxed).to_string(),
+            ),
+            (
+                "http_server_parse_errors_total",
+                "counter",
+                "Number of requests that could not be parsed.",
+                registry.parse_errors.load(Ordering::Relaxed).to_string(),
+            ),
+        ];
+
+        for (name, kind, help, value) in values {
+            write_header(&mut output, name, kind, help);
+            let _ = writeln!(output, "{} {}", name, value);
+        }
+
+        output
+    }
+
+    /// Returns whether `req` asks for the metrics.
+    pub(crate) fn is_endpoint(&self, req: &Request) -> bool {
+        req.method() == "GET" && req.path() == self.path
+    }
+
+    pub(crate) fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub(crate) fn response(&self) -> Box<dyn HttpResponse> {
+        Box::new(MetricsResponse(self.render()))
+    }
+
+    /// Counts a connection as open until the returned guard is dropped.
+    pub(crate) fn open_connection(&self) -> ConnectionGuard {
+        self.registry
+            .open_connections
+            .fetch_add(1, Ordering::Relaxed);
+        ConnectionGuard(self.registry.clone())
+    }
+
+    pub(crate) fn add_parse_error(&self) {
+        self.registry.parse_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Counts a request as in flight until the returned recorder is dropped, at which
+    /// point it is added to the request series.
+    pub(crate) fn start_request(&self, req: &Request, start: Instant) -> RequestMetrics {
+        let registry = &self.registry;
+        registry.in_flight.fetch_add(1, Ordering::Relaxed);
+        registry
+            .request_bytes
+            .fetch_add(req.body().len() as u64, Ordering::Relaxed);
+
+        let method = KNOWN_METHODS
+            .iter()
+            .find(|method| **method == req.method())
+            .copied()
+            .unwrap_or(OTHER_METHOD);
+
+        RequestMetrics {
+            registry: registry.clone(),
+            method,
+            route: None,
+            status: StatusCode::Ok,
+            start,
+        }
+    }
+}
+
+impl SeriesKey {
+    fn labels(&self) -> String {
+        format!(
+            "method=\"{}\",route=\"{}\",status=\"{}\"",
+            self.method,
+            escape_label(&self.route),
+            self.status
+        )
+    }
+}
+
+struct MetricsResponse(String);
+
+impl HttpResponse for MetricsResponse {
+    fn into_response(self: Box<Self>) -> Vec<u8> {
+        self.0.into_bytes()
+    }
+
+    fn status_code(&self) -> StatusCode {
+        StatusCode::Ok
+    }
+
+    fn set_headers(&self, headers: &mut Headers) {
+        headers.set(keys::CONTENT_TYPE_KEY, CONTENT_TYPE);
+    }
+}
+
+/// An open connection, counted until it is dropped.
+pub(crate) struct ConnectionGuard(Arc<Registry>);
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.0.open_connections.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// The measurements of a request being served, recorded when it is dropped.
+pub(crate) struct RequestMetrics {
+    registry: Arc<Registry>,
+    method: &'static str,
+    route: Option<String>,
+    status: StatusCode,
+    start: Instant,
+}
+
+impl RequestMetrics {
+    pub(crate) fn set_route(&mut self, route: Option<&str>) {
+        self.route = route.map(str::to_string);
+    }
+
+    pub(crate) fn set_status(&mut self, status: StatusCode) {
+        self.status = status;
+    }
+
+    pub(crate) fn add_bytes(&self, bytes: u64) {
+        self.registry
+            .response_bytes
+            .fetch_add(bytes, Ordering::Relaxed);
+    }
+}
+
+impl Drop for RequestMetrics {
+    fn drop(&mut self) {
+        let registry = &self.registry;
+        registry.in_flight.fetch_sub(1, Ordering::Relaxed);
+
+        let duration = self.start.elapsed().as_secs_f64();
+        let key = SeriesKey {
+            method: self.method,
+            route: self
+                .route
+                .take()
+                .unwrap_or_else(|| UNMATCHED_ROUTE.to_string()),
+            status: format!("{}xx", self.status as u16 / 100),
+        };
+
+        let mut requests = match registry.requests.lock() {
+            Ok(requests) => requests,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        let series = requests.entry(key).or_insert_with(|| Series {
+            count: 0,
+            sum: 0.0,
+            buckets: vec![0; registry.buckets.len()],
+        });
+
+        series.count += 1;
+        series.sum += duration;
+        for (bound, count) in registry.buckets.iter().zip(&mut series.buckets) {
+            if duration <= *bound {
+                *count += 1;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_render() {
+        let metrics = Metrics::new().with_buckets(vec![60.0, 0.0]);
+        let _connection = metrics.open_connection();
+
+        let req =
+            Request::from_reader(&b"PUT /users/7 HTTP/1.1\r\nContent-Length: 2\r\n\r\nhi"[..])
+                .await
+                .unwrap();
+        for status in [StatusCode::Ok, StatusCode::NotFound, StatusCode::Ok] {
+            let mut request = metrics.start_request(&req, Instant::now());
+            request.set_route(Some("/users/:id"));
+            request.set_status(status);
+            request.add_bytes(5);
+        }
+
+        let _in_flight = metrics.start_request(&req, Instant::now());
+        metrics.add_parse_error();
+
+        let output = metrics.render();
+        let labels = "method=\"PUT\",route=\"/users/:id\",status=\"2xx\"";
+        for line in [
+            format!("http_server_requests_total{{{}}} 2", labels),
+            "http_server_requests_total{method=\"PUT\",route=\"/users/:id\",status=\"4xx\"} 1"
+                .to_string(),
+            format!(
+                "http_server_request_duration_seconds_bucket{{{},le=\"0\"}} 0",
+                labels
+            ),
+            format!(
+                "http_server_request_duration_seconds_bucket{{{},le=\"60\"}} 2",
+                labels
+            ),
+            format!(
+                "http_server_request_duration_seconds_bucket{{{},le=\"+Inf\"}} 2",
+                labels
+            ),
+            format!("http_server_request_duration_seconds_count{{{}}} 2", labels),
+            "http_server_requests_in_flight 1".to_string(),
+            "http_server_open_connections 1".to_string(),
+            "http_server_request_body_bytes_total 8".to_string(),
+            "http_server_response_body_bytes_total 15".to_string(),
+            "http_server_parse_errors_total 1".to_string(),
+        ] {
+            assert!(output.lines().any(|l| l == line), "missing {}", line);
+        }
+    }
+}
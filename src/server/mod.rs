@@ -1,8 +1,32 @@
+pub mod access_log;
+mod connection;
+pub mod extract;
+pub mod fs;
 mod handler;
+#[cfg(feature = "http2")]
+mod http2;
+pub mod limits;
+mod listener;
+pub mod metrics;
+mod observe;
+mod panic;
+mod random;
+pub mod request_id;
 pub mod responses;
+mod route;
 mod router;
+#[allow(clippy::module_inception)]
 mod server;
+mod shutdown;
+#[cfg(feature = "tls")]
+pub mod tls;
+pub mod trace;
+pub mod upgrade;
+#[cfg(feature = "websocket")]
+pub mod websocket;
 
 pub use handler::*;
+pub use listener::{Listener, LocalAddr};
+pub use panic::PanicReport;
 pub use router::Router;
-pub use server::Server;
+pub use server::{BoundServer, Server};
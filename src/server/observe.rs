@@ -0,0 +1,189 @@
+use std::{future::Future, net::SocketAddr, time::Instant};
+
+#[cfg(feature = "otlp")]
+use std::time::SystemTime;
+
+use crate::{
+    Request, StatusCode,
+    server::{access_log::LogEntry, metrics::RequestMetrics, server::Options, trace::TraceContext},
+};
+
+#[cfg(feature = "otlp")]
+use crate::server::trace::{SpanRecord, SpanSender};
+
+/// Returns the URL scheme the request was received with.
+#[cfg(any(feature = "tracing", feature = "otlp"))]
+fn scheme(_req: &Request) -> &'static str {
+    #[cfg(feature = "tls")]
+    if _req.tls_info().is_some() {
+        return "https";
+    }
+
+    "http"
+}
+
+/// Runs a connection inside a `connection` span when the `tracing` feature is enabled.
+pub(crate) async fn connection<F: Future>(peer_addr: Option<SocketAddr>, future: F) -> F::Output {
+    #[cfg(feature = "tracing")]
+    {
+        let span = tracing::info_span!(
+            "connection",
+            client.address = peer_addr.map(|addr| addr.ip().to_string()),
+            client.port = peer_addr.map(|addr| addr.port()),
+            network.transport = "tcp",
+        );
+        tracing::Instrument::instrument(future, span).await
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    {
+        let _ = peer_addr;
+        future.await
+    }
+}
+
+/// Keeps a task spawned for a connection inside the span of the connection.
+#[cfg(all(feature = "http2", feature = "tracing"))]
+pub(crate) fn in_current_span<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    tracing::Instrument::in_current_span(future)
+}
+
+#[cfg(all(feature = "http2", not(feature = "tracing")))]
+pub(crate) fn in_current_span<F: Future>(future: F) -> impl Future<Output = F::Output> {
+    future
+}
+
+/// Everything recorded about a request while it is served: its trace context, access
+/// log entry, metrics, `tracing` span and exported span. Records are completed when it is
+/// dropped.
+pub(crate) struct Observation {
+    entry: Option<LogEntry>,
+    metrics: Option<RequestMetrics>,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+    #[cfg(feature = "otlp")]
+    export: Option<(SpanSender, SpanRecord)>,
+}
+
+impl Observation {
+    /// Starts observing a request received at `start`, and gives it the request id and
+    /// trace context available to handlers.
+    pub(crate) fn start(options: &Options, req: &mut Request, start: Instant) -> Self {
+        if let Some(config) = &options.request_id {
+            let request_id = config.resolve(req);
+            req.set_request_id(request_id);
+        }
+
+        let context = TraceContext::from_headers(req);
+
+        let entry = options
+            .access_log
+            .clone()
+            .map(|log| LogEntry::new(log, req, start));
+
+        let metrics = options
+            .metrics
+            .as_ref()
+            .map(|metrics| metrics.start_request(req, start));
+
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!(
+            "request",
+            otel.name = req.method(),
+            otel.kind = "server",
+            otel.status_code = tracing::field::Empty,
+            http.request.method = req.method(),
+            http.response.status_code = tracing::field::Empty,
+            url.path = req.path(),
+            url.query = req.target().split_once('?').map(|(_, query)| query),
+            url.scheme = scheme(req),
+            network.protocol.version = req.http_version().trim_start_matches("HTTP/"),
+            user_agent.original = req.headers().get::<String>(crate::headers::keys::USER_AGENT_HEADER),
+            client.address = req.peer_addr().map(|addr| addr.ip().to_string()),
+            trace_id = %context.trace_id(),
+            span_id = %context.span_id(),
+            request_id = req.request_id(),
+        );
+
+        #[cfg(feature = "otlp")]
+        let export = options
+            .otlp
+            .clone()
+            .filter(|_| context.is_sampled())
+            .map(|sender| {
+                let started = SystemTime::now() - start.elapsed();
+                (sender, SpanRecord::new(req, &context, scheme(req), started))
+            });
+
+        req.extensions_mut().insert(context);
+
+        Observation {
+            entry,
+            metrics,
+            #[cfg(feature = "tracing")]
+            span,
+            #[cfg(feature = "otlp")]
+            export,
+        }
+    }
+
+    /// Runs the handling of the request inside its span.
+    pub(crate) async fn instrument<F: Future>(&self, future: F) -> F::Output {
+        #[cfg(feature = "tracing")]
+        return tracing::Instrument::instrument(future, self.span.clone()).await;
+
+        #[cfg(not(feature = "tracing"))]
+        future.await
+    }
+
+    /// Records the route that matched the request, once it has been handled.
+    pub(crate) fn set_route(&mut self, route: Option<&str>) {
+        if let Some(metrics) = &mut self.metrics {
+            metrics.set_route(route);
+        }
+    }
+
+    pub(crate) fn set_status(&mut self, status: StatusCode) {
+        if let Some(entry) = &mut self.entry {
+            entry.set_status(status);
+        }
+
+        if let Some(metrics) = &mut self.metrics {
+            metrics.set_status(status);
+        }
+
+        #[cfg(feature = "tracing")]
+        {
+            self.span.record("http.response.status_code", status as u16);
+            if status as u16 >= 500 {
+                self.span.record("otel.status_code", "ERROR");
+            }
+        }
+
+        #[cfg(feature = "otlp")]
+        if let Some((_, record)) = &mut self.export {
+            record.set_status(status as u16);
+        }
+    }
+
+    pub(crate) fn add_bytes(&mut self, bytes: u64) {
+        if let Some(entry) = &mut self.entry {
+            entry.add_bytes(bytes);
+        }
+
+        if let Some(metrics) = &self.metrics {
+            metrics.add_bytes(bytes);
+        }
+    }
+}
+
+#[cfg(feature = "otlp")]
+impl Drop for Observation {
+    fn drop(&mut self) {
+        if let Some((sender, mut record)) = self.export.take() {
+            record.finish(SystemTime::now());
+            // Spans are dropped rather than slowing requests down when the collector lags
+            let _ = sender.try_send(record);
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use std::{
+    any::Any,
+    backtrace::Backtrace,
+    cell::{Cell, RefCell},
+    fmt,
+    panic::{self, AssertUnwindSafe},
+    sync::{Arc, Once},
+};
+
+use crate::Request;
+
+/// Reports a handler panic, set with
+/// [`Server::with_panic_hook`](crate::Server::with_panic_hook).
+pub(crate) type PanicHook = Arc<dyn Fn(&PanicReport) + Send + Sync + 'static>;
+
+static INSTALL_HOOK: Once = Once::new();
+
+thread_local! {
+    /// Whether the current thread is running a handler whose panics are caught.
+    static CATCHING: Cell<bool> = const { Cell::new(false) };
+    /// The location and backtrace of the last panic caught on the current thread.
+    static CAPTURED: RefCell<Option<(Option<String>, Backtrace)>> = const { RefCell::new(None) };
+}
+
+/// Installs a panic hook recording where caught panics happened, since the backtrace is
+/// only available while unwinding starts. Other panics go to the previous hook.
+fn install_hook() {
+    INSTALL_HOOK.call_once(|| {
+        let previous = panic::take_hook();
+
+        panic::set_hook(Box::new(move |info| {
+            if !CATCHING.with(Cell::get) {
+                previous(info);
+                return;
+            }
+
+            let location = info.location().map(|location| location.to_string());
+            let backtrace = Backtrace::force_capture();
+            CAPTURED.with(|captured| *captured.borrow_mut() = Some((location, backtrace)));
+        }));
+    });
+}
+
+/// A panic caught by [`catch`], before it is tied to a request.
+pub(crate) struct Caught {
+    payload: Box<dyn Any + Send>,
+    location: Option<String>,
+    backtrace: Backtrace,
+}
+
+/// Runs `f`, catching any panic it raises.
+pub(crate) fn catch<T>(f: impl FnOnce() -> T) -> Result<T, Caught> {
+    install_hook();
+
+    let catching = CATCHING.with(|catching| catching.replace(true));
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    CATCHING.with(|current| current.set(catching));
+
+    result.map_err(|payload| {
+        let (location, backtrace) = CAPTURED
+            .with(|captured| captured.borrow_mut().take())
+            .unwrap_or_else(|| (None, Backtrace::disabled()));
+
+        Caught {
+            payload,
+            location,
+            backtrace,
+        }
+    })
+}
+
+/// A panic raised while a handler was serving a request.
+///
+/// Panics are turned into 500 Internal Server Error responses and written to standard
+/// error with their backtrace, and are passed to the hook set with
+/// [`Server::with_panic_hook`](crate::Server::with_panic_hook).
+pub struct PanicReport {
+    message: String,
+    location: Option<String>,
+    backtrace: Backtrace,
+    method: String,
+    target: String,
+    request_id: Option<String>,
+}
+
+impl PanicReport {
+    pub(crate) fn new(caught: Caught, req: &Request) -> Self {
+        let message = if let Some(message) = caught.payload.downcast_ref::<&str>() {
+            message.to_string()
+        } else if let Some(message) = caught.payload.downcast_ref::<String>() {
+            message.clone()
+        } else {
+            "Box<dyn Any>".to_string()
+        };
+
+        PanicReport {
+            message,
+            location: caught.location,
+            backtrace: caught.backtrace,
+            method: req.method().to_string(),
+            target: req.target().to_string(),
+            request_id: req.request_id().map(str::to_string),
+        }
+    }
+
+    /// Returns the message the handler panicked with.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the source location of the panic, such as `src/main.rs:12:5`.
+    pub fn location(&self) -> Option<&str> {
+        self.location.as_deref()
+    }
+
+    /// Returns the backtrace of the panic.
+    pub fn backtrace(&self) -> &Backtrace {
+        &self.backtrace
+    }
+
+    /// Returns the method of the request being served.
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// Returns the target of the request being served, including the query string.
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    /// Returns the id of the request being served, when the server assigns request ids.
+    pub fn request_id(&self) -> Option<&str> {
+        self.request_id.as_deref()
+    }
+}
+
+impl fmt::Debug for PanicReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PanicReport")
+            .field("message", &self.message)
+            .field("location", &self.location)
+            .field("method", &self.method)
+            .field("target", &self.target)
+            .field("request_id", &self.request_id)
+            .finish()
+    }
+}
+
+impl fmt::Display for PanicReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Handler panicked while serving {} {}",
+            self.method, self.target
+        )?;
+
+        if let Some(request_id) = &self.request_id {
+            write!(f, " (request id {})", request_id)?;
+        }
+
+        write!(f, ": {}", self.message)?;
+
+        if let Some(location) = &self.location {
+            write!(f, " at {}", location)?;
+        }
+
+        write!(f, "\nBacktrace:\n{}", self.backtrace)
+    }
+}
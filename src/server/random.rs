@@ -0,0 +1,18 @@
+use std::{
+    hash::{BuildHasher, RandomState},
+    sync::atomic::{AtomicU64, Ordering},
+    time::SystemTime,
+};
+
+static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Fills `buffer` with random bytes, suitable for identifiers but not for secrets.
+pub(crate) fn fill(buffer: &mut [u8]) {
+    for chunk in buffer.chunks_mut(8) {
+        // Each RandomState is seeded differently, and the counter keeps values unique even
+        // if two seeds collide
+        let value = RandomState::new()
+            .hash_one((COUNTER.fetch_add(1, Ordering::Relaxed), SystemTime::now()));
+        chunk.copy_from_slice(&value.to_le_bytes()[..chunk.len()]);
+    }
+}
@@ -0,0 +1,195 @@
+//! Request ids, enabled with [`Server::with_request_id`](crate::Server::with_request_id).
+//!
+//! Each request is given an id, taken from the `X-Request-Id` header sent by the client
+//! or a proxy, or generated when it is missing. The id is available through
+//! [`Request::request_id`], is echoed in the same response header, and is included in
+//! access log records and in the JSON body of error responses, so that a failed request
+//! can be found in the server logs.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::{
+    Request,
+    extract::FromRequest,
+    responses::{HttpResponse, InternalServerError},
+    server::random,
+};
+
+const DEFAULT_HEADER: &str = "X-Request-Id";
+
+/// Incoming ids longer than this are replaced, so they cannot bloat the logs.
+const MAX_INCOMING_LENGTH: usize = 128;
+
+// https://www.crockford.com/base32.html
+const CROCKFORD_ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// How generated ids are formatted.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RequestIdFormat {
+    /// A random UUID (version 4), such as `0b9a5c3e-2f6d-4c1b-8e7a-9d3f1a2b4c5d`.
+    Uuid,
+    /// A ULID, such as `01J9Z3Q4XK8V6M2N5P7R9T1W3Y`, which sorts by creation time.
+    Ulid,
+}
+
+/// Where request ids are read from and how missing ones are generated.
+#[derive(Debug, Clone)]
+pub struct RequestIdConfig {
+    header: String,
+    format: RequestIdFormat,
+    trust_incoming: bool,
+}
+
+impl Default for RequestIdConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RequestIdConfig {
+    /// Creates a new RequestIdConfig reading the `X-Request-Id` header and generating
+    /// UUIDs.
+    pub fn new() -> Self {
+        RequestIdConfig {
+            header: DEFAULT_HEADER.to_string(),
+            format: RequestIdFormat::Uuid,
+            trust_incoming: true,
+        }
+    }
+
+    /// Sets the header the id is read from and echoed in.
+    pub fn with_header(mut self, header: &str) -> Self {
+        self.header = header.to_string();
+        self
+    }
+
+    /// Sets the format of generated ids.
+    pub fn with_format(mut self, format: RequestIdFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Always generates a new id, ignoring the one sent by the client. Useful when the
+    /// server is not behind a proxy that sets the header.
+    pub fn without_incoming(mut self) -> Self {
+        self.trust_incoming = false;
+        self
+    }
+
+    pub(crate) fn header(&self) -> &str {
+        &self.header
+    }
+
+    /// Returns the id sent with `req` when it is acceptable, or a new one.
+    pub(crate) fn resolve(&self, req: &Request) -> String {
+        let incoming = req
+            .headers()
+            .get::<String>(&self.header)
+            .filter(|_| self.trust_incoming)
+            .filter(|id| is_valid(id));
+
+        incoming.unwrap_or_else(|| match self.format {
+            RequestIdFormat::Uuid => uuid(),
+            RequestIdFormat::Ulid => ulid(),
+        })
+    }
+}
+
+/// Accepts non-empty ids of visible ASCII characters, which are safe to log and echo.
+fn is_valid(id: &str) -> bool {
+    !id.is_empty() && id.len() <= MAX_INCOMING_LENGTH && id.bytes().all(|b| b.is_ascii_graphic())
+}
+
+fn uuid() -> String {
+    let mut bytes = [0; 16];
+    random::fill(&mut bytes);
+
+    // Version 4 and the RFC 9562 variant
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex = bytes
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+// https://github.com/ulid/spec
+fn ulid() -> String {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+
+    let mut randomness = [0; 16];
+    random::fill(&mut randomness[6..]);
+
+    // 48 bits of time followed by 80 random bits, as 26 characters of 5 bits
+    let value = (millis & ((1 << 48) - 1)) << 80 | u128::from_be_bytes(randomness);
+
+    (0..26)
+        .rev()
+        .map(|index| CROCKFORD_ALPHABET[((value >> (index * 5)) & 0x1f) as usize] as char)
+        .collect()
+}
+
+/// The id of the request, as an extractor.
+///
+/// Requests are rejected with 500 Internal Server Error when the server does not assign
+/// request ids.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+impl FromRequest for RequestId {
+    fn from_request(req: &Request) -> Result<Self, Box<dyn HttpResponse>> {
+        req.request_id()
+            .map(|id| RequestId(id.to_string()))
+            .ok_or_else(|| {
+                InternalServerError::with_message("The request has no request id").into()
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_resolve() {
+        let config = RequestIdConfig::new().with_header("X-Correlation-Id");
+
+        let req = Request::from_reader(&b"GET / HTTP/1.1\r\nX-Correlation-Id: abc-123\r\n\r\n"[..])
+            .await
+            .unwrap();
+        assert_eq!(config.resolve(&req), "abc-123");
+        assert_ne!(config.clone().without_incoming().resolve(&req), "abc-123");
+
+        let req = Request::from_reader(&b"GET / HTTP/1.1\r\nX-Correlation-Id: a b\r\n\r\n"[..])
+            .await
+            .unwrap();
+        let id = config.resolve(&req);
+        assert_eq!(id.len(), 36);
+        assert_eq!(&id[14..15], "4");
+    }
+
+    #[test]
+    fn test_ulid() {
+        let first = ulid();
+        let second = ulid();
+
+        assert_eq!(first.len(), 26);
+        assert!(first.bytes().all(|b| CROCKFORD_ALPHABET.contains(&b)));
+        assert_ne!(first, second);
+        // Ids generated in the same millisecond or later share or exceed the time prefix
+        assert!(second[..10] >= first[..10]);
+    }
+}
@@ -21,6 +21,12 @@ impl BadRequestError {
     }
 }
 
+impl Default for BadRequestError {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl HttpError for BadRequestError {
     fn message(&self) -> &str {
         &self.message
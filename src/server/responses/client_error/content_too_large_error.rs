@@ -0,0 +1,38 @@
+use crate::{response::StatusCode, responses::http_error::HttpError};
+
+/// Represents a 413 Content Too Large HTTP error.
+pub struct ContentTooLargeError {
+    message: String,
+}
+
+impl ContentTooLargeError {
+    /// Creates a new ContentTooLargeError with the default message.
+    pub fn new() -> Self {
+        ContentTooLargeError {
+            message: StatusCode::ContentTooLarge.as_str().to_string(),
+        }
+    }
+
+    /// Creates a new ContentTooLargeError with a custom message.
+    pub fn with_message<S: Into<String>>(message: S) -> Self {
+        ContentTooLargeError {
+            message: message.into(),
+        }
+    }
+}
+
+impl Default for ContentTooLargeError {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HttpError for ContentTooLargeError {
+    fn message(&self) -> &str {
+        &self.message
+    }
+
+    fn status_code(&self) -> StatusCode {
+        StatusCode::ContentTooLarge
+    }
+}
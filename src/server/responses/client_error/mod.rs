@@ -1,5 +1,17 @@
 mod bad_request_error;
+mod content_too_large_error;
+mod not_acceptable_error;
 mod not_found_error;
+mod precondition_failed_error;
+mod range_not_satisfiable_error;
+mod unsupported_media_type_error;
+mod upgrade_required_error;
 
 pub use bad_request_error::BadRequestError;
+pub use content_too_large_error::ContentTooLargeError;
+pub use not_acceptable_error::NotAcceptableError;
 pub use not_found_error::NotFoundError;
+pub use precondition_failed_error::PreconditionFailedError;
+pub use range_not_satisfiable_error::RangeNotSatisfiableError;
+pub use unsupported_media_type_error::UnsupportedMediaTypeError;
+pub use upgrade_required_error::UpgradeRequiredError;
@@ -0,0 +1,38 @@
+use crate::{response::StatusCode, responses::http_error::HttpError};
+
+/// Represents a 406 Not Acceptable HTTP error.
+pub struct NotAcceptableError {
+    message: String,
+}
+
+impl NotAcceptableError {
+    /// Creates a new NotAcceptableError with the default message.
+    pub fn new() -> Self {
+        NotAcceptableError {
+            message: StatusCode::NotAcceptable.as_str().to_string(),
+        }
+    }
+
+    /// Creates a new NotAcceptableError with a custom message.
+    pub fn with_message<S: Into<String>>(message: S) -> Self {
+        NotAcceptableError {
+            message: message.into(),
+        }
+    }
+}
+
+impl Default for NotAcceptableError {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HttpError for NotAcceptableError {
+    fn message(&self) -> &str {
+        &self.message
+    }
+
+    fn status_code(&self) -> StatusCode {
+        StatusCode::NotAcceptable
+    }
+}
@@ -21,6 +21,12 @@ impl NotFoundError {
     }
 }
 
+impl Default for NotFoundError {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl HttpError for NotFoundError {
     fn message(&self) -> &str {
         &self.message
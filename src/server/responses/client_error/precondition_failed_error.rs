@@ -0,0 +1,38 @@
+use crate::{response::StatusCode, responses::http_error::HttpError};
+
+/// Represents a 412 Precondition Failed HTTP error.
+pub struct PreconditionFailedError {
+    message: String,
+}
+
+impl PreconditionFailedError {
+    /// Creates a new PreconditionFailedError with the default message.
+    pub fn new() -> Self {
+        PreconditionFailedError {
+            message: StatusCode::PreconditionFailed.as_str().to_string(),
+        }
+    }
+
+    /// Creates a new PreconditionFailedError with a custom message.
+    pub fn with_message<S: Into<String>>(message: S) -> Self {
+        PreconditionFailedError {
+            message: message.into(),
+        }
+    }
+}
+
+impl Default for PreconditionFailedError {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HttpError for PreconditionFailedError {
+    fn message(&self) -> &str {
+        &self.message
+    }
+
+    fn status_code(&self) -> StatusCode {
+        StatusCode::PreconditionFailed
+    }
+}
@@ -0,0 +1,55 @@
+use crate::{headers::keys, response::StatusCode, responses::http_error::HttpError};
+
+/// Represents a 416 Range Not Satisfiable HTTP error.
+pub struct RangeNotSatisfiableError {
+    message: String,
+    complete_length: Option<u64>,
+}
+
+impl RangeNotSatisfiableError {
+    /// Creates a new RangeNotSatisfiableError with the default message.
+    pub fn new() -> Self {
+        RangeNotSatisfiableError {
+            message: StatusCode::RangeNotSatisfiable.as_str().to_string(),
+            complete_length: None,
+        }
+    }
+
+    /// Creates a new RangeNotSatisfiableError with a custom message.
+    pub fn with_message<S: Into<String>>(message: S) -> Self {
+        RangeNotSatisfiableError {
+            message: message.into(),
+            complete_length: None,
+        }
+    }
+
+    /// Tells the client the length of the representation with a
+    /// `Content-Range: bytes */<length>` header.
+    pub fn with_complete_length(mut self, length: u64) -> Self {
+        self.complete_length = Some(length);
+        self
+    }
+}
+
+impl Default for RangeNotSatisfiableError {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HttpError for RangeNotSatisfiableError {
+    fn message(&self) -> &str {
+        &self.message
+    }
+
+    fn status_code(&self) -> StatusCode {
+        StatusCode::RangeNotSatisfiable
+    }
+
+    fn headers(&self) -> Vec<(&'static str, String)> {
+        self.complete_length
+            .map(|length| (keys::CONTENT_RANGE_HEADER, format!("bytes */{}", length)))
+            .into_iter()
+            .collect()
+    }
+}
@@ -0,0 +1,54 @@
+use crate::{headers::keys, response::StatusCode, responses::http_error::HttpError};
+
+/// Represents a 415 Unsupported Media Type HTTP error.
+pub struct UnsupportedMediaTypeError {
+    message: String,
+    accept_encoding: Option<String>,
+}
+
+impl UnsupportedMediaTypeError {
+    /// Creates a new UnsupportedMediaTypeError with the default message.
+    pub fn new() -> Self {
+        UnsupportedMediaTypeError {
+            message: StatusCode::UnsupportedMediaType.as_str().to_string(),
+            accept_encoding: None,
+        }
+    }
+
+    /// Creates a new UnsupportedMediaTypeError with a custom message.
+    pub fn with_message<S: Into<String>>(message: S) -> Self {
+        UnsupportedMediaTypeError {
+            message: message.into(),
+            accept_encoding: None,
+        }
+    }
+
+    /// Lists the content codings the server accepts in an `Accept-Encoding` header.
+    pub fn with_accept_encoding<S: Into<String>>(mut self, accept_encoding: S) -> Self {
+        self.accept_encoding = Some(accept_encoding.into());
+        self
+    }
+}
+
+impl Default for UnsupportedMediaTypeError {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HttpError for UnsupportedMediaTypeError {
+    fn message(&self) -> &str {
+        &self.message
+    }
+
+    fn status_code(&self) -> StatusCode {
+        StatusCode::UnsupportedMediaType
+    }
+
+    fn headers(&self) -> Vec<(&'static str, String)> {
+        match &self.accept_encoding {
+            Some(value) => vec![(keys::ACCEPT_ENCODING_HEADER, value.clone())],
+            None => Vec::new(),
+        }
+    }
+}
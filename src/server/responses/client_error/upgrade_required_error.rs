@@ -0,0 +1,51 @@
+use crate::{response::StatusCode, responses::http_error::HttpError};
+
+/// Represents a 426 Upgrade Required HTTP error.
+pub struct UpgradeRequiredError {
+    message: String,
+    headers: Vec<(&'static str, String)>,
+}
+
+impl UpgradeRequiredError {
+    /// Creates a new UpgradeRequiredError with the default message.
+    pub fn new() -> Self {
+        UpgradeRequiredError {
+            message: StatusCode::UpgradeRequired.as_str().to_string(),
+            headers: Vec::new(),
+        }
+    }
+
+    /// Creates a new UpgradeRequiredError with a custom message.
+    pub fn with_message<S: Into<String>>(message: S) -> Self {
+        UpgradeRequiredError {
+            message: message.into(),
+            headers: Vec::new(),
+        }
+    }
+
+    /// Adds a header telling the client how to upgrade, such as `Upgrade: websocket`.
+    pub fn with_header<S: Into<String>>(mut self, key: &'static str, value: S) -> Self {
+        self.headers.push((key, value.into()));
+        self
+    }
+}
+
+impl Default for UpgradeRequiredError {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HttpError for UpgradeRequiredError {
+    fn message(&self) -> &str {
+        &self.message
+    }
+
+    fn status_code(&self) -> StatusCode {
+        StatusCode::UpgradeRequired
+    }
+
+    fn headers(&self) -> Vec<(&'static str, String)> {
+        self.headers.clone()
+    }
+}
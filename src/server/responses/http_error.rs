@@ -1,10 +1,65 @@
+use std::sync::Arc;
+
 use serde::Serialize;
 
-use crate::{response::StatusCode, responses::http_response::HttpResponse};
+use crate::{
+    Body, Request,
+    headers::{Headers, keys},
+    response::{CONTENT_TYPE_JSON, StatusCode},
+    responses::http_response::HttpResponse,
+};
 
+/// An error answered with a 4xx or 5xx response.
+///
+/// Errors are sent as `{"error", "message", "status_code"}` JSON objects, unless the
+/// server has an error handler, set with
+/// [`Server::with_error_handler`](crate::Server::with_error_handler), which renders them.
 pub trait HttpError: Sync + Send {
+    /// Returns the description of what went wrong.
     fn message(&self) -> &str;
     fn status_code(&self) -> StatusCode;
+
+    /// Returns additional headers to send with the error, such as `Retry-After`.
+    fn headers(&self) -> Vec<(&'static str, String)> {
+        Vec::new()
+    }
+}
+
+/// Renders every error response of a server, set with
+/// [`Server::with_error_handler`](crate::Server::with_error_handler). The request is
+/// missing when it could not be parsed.
+pub(crate) type ErrorHandler =
+    Arc<dyn Fn(&dyn HttpError, Option<&Request>) -> Box<dyn HttpResponse> + Send + Sync>;
+
+/// A copy of an error that has been turned into a response, kept for the error handler.
+pub(crate) struct CapturedError {
+    message: String,
+    status_code: StatusCode,
+    headers: Vec<(&'static str, String)>,
+}
+
+impl CapturedError {
+    pub(crate) fn new(error: &dyn HttpError) -> Self {
+        CapturedError {
+            message: error.message().to_string(),
+            status_code: error.status_code(),
+            headers: error.headers(),
+        }
+    }
+}
+
+impl HttpError for CapturedError {
+    fn message(&self) -> &str {
+        &self.message
+    }
+
+    fn status_code(&self) -> StatusCode {
+        self.status_code
+    }
+
+    fn headers(&self) -> Vec<(&'static str, String)> {
+        self.headers.clone()
+    }
 }
 
 #[derive(Serialize, Debug)]
@@ -12,16 +67,24 @@ pub(crate) struct HttpErrorResponse {
     error: String,
     message: String,
     status_code: u16,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
 }
 
 impl HttpErrorResponse {
-    pub fn new(error: &str, status_code: StatusCode) -> Self {
+    pub fn new(message: &str, status_code: StatusCode) -> Self {
         HttpErrorResponse {
-            error: error.to_string(),
-            message: status_code.as_str().to_string(),
+            error: status_code.as_str().to_string(),
+            message: message.to_string(),
             status_code: status_code as u16,
+            request_id: None,
         }
     }
+
+    fn to_vec(&self) -> Vec<u8> {
+        // FIXME: Handle serialization errors properly
+        serde_json::to_vec(self).unwrap_or_else(|_| "Error serializing".to_string().into_bytes())
+    }
 }
 
 impl<T> HttpResponse for T
@@ -29,12 +92,45 @@ where
     T: HttpError,
 {
     fn into_response(self: Box<Self>) -> Vec<u8> {
-        // FIXME: Handle serialization errors properly
-        serde_json::to_vec(&HttpErrorResponse::new(self.message(), self.status_code()))
-            .unwrap_or_else(|_| "Error serializing".to_string().into_bytes())
+        HttpErrorResponse::new(self.message(), self.status_code()).to_vec()
+    }
+
+    fn into_body_with_request_id(self: Box<Self>, request_id: Option<&str>) -> Body {
+        let mut response = HttpErrorResponse::new(self.message(), self.status_code());
+        response.request_id = request_id.map(str::to_string);
+        Body::Full(response.to_vec())
     }
 
     fn status_code(&self) -> StatusCode {
         <Self as HttpError>::status_code(self)
     }
+
+    fn as_error(&self) -> Option<&dyn HttpError> {
+        Some(self)
+    }
+
+    fn set_headers(&self, headers: &mut Headers) {
+        headers.set(keys::CONTENT_TYPE_KEY, CONTENT_TYPE_JSON);
+
+        for (key, value) in <Self as HttpError>::headers(self) {
+            headers.set(key, &value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::responses::NotFoundError;
+
+    use super::*;
+
+    #[test]
+    fn test_error_body() {
+        let error = Box::new(NotFoundError::with_message("No such user"));
+        let body: serde_json::Value = serde_json::from_slice(&error.into_response()).unwrap();
+
+        assert_eq!(body["error"], "Not Found");
+        assert_eq!(body["message"], "No such user");
+        assert_eq!(body["status_code"], 404);
+    }
 }
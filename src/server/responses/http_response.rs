@@ -1,12 +1,43 @@
-use crate::StatusCode;
+use crate::{Body, StatusCode, headers::Headers, responses::HttpError, server::upgrade::OnUpgrade};
 
 pub trait HttpResponse {
     fn into_response(self: Box<Self>) -> Vec<u8>;
     fn status_code(&self) -> StatusCode;
+
+    /// Sets headers that belong to this response, such as its content type.
+    fn set_headers(&self, _headers: &mut Headers) {}
+
+    /// Consumes the response and returns its body.
+    ///
+    /// Defaults to the bytes produced by `into_response`; responses backed by
+    /// something other than memory override it to stream their content.
+    fn into_body(self: Box<Self>) -> Body {
+        Body::Full(self.into_response())
+    }
+
+    /// Consumes the response and returns its body, given the id of the request it answers
+    /// when the server assigns request ids.
+    ///
+    /// Defaults to `into_body`; errors include the id in their JSON body.
+    fn into_body_with_request_id(self: Box<Self>, _request_id: Option<&str>) -> Body {
+        self.into_body()
+    }
+
+    /// Returns the error this response reports, if it is one, so that the error handler of
+    /// the server can render it.
+    fn as_error(&self) -> Option<&dyn HttpError> {
+        None
+    }
+
+    /// Takes the handler that receives the connection once a 101 Switching Protocols
+    /// response has been sent.
+    fn take_upgrade(&mut self) -> Option<OnUpgrade> {
+        None
+    }
 }
 
 impl<T: HttpResponse + 'static> From<T> for Box<dyn HttpResponse> {
     fn from(result: T) -> Self {
         Box::new(result)
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,3 @@
+mod switching_protocols_response;
+
+pub use switching_protocols_response::SwitchingProtocolsResponse;
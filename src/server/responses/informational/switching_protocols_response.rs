@@ -0,0 +1,60 @@
+use std::future::Future;
+
+use crate::{
+    StatusCode,
+    headers::Headers,
+    responses::HttpResponse,
+    server::upgrade::{self, OnUpgrade, Upgraded},
+};
+
+/// Represents a 101 Switching Protocols HTTP response, after which the connection is
+/// handed over to another protocol.
+///
+/// The response should name the new protocol in an `Upgrade` header, along with
+/// `Connection: Upgrade`. Over HTTP/2, where upgrades do not exist, it is answered with
+/// 501 Not Implemented instead.
+pub struct SwitchingProtocolsResponse {
+    headers: Vec<(String, String)>,
+    on_upgrade: Option<OnUpgrade>,
+}
+
+impl SwitchingProtocolsResponse {
+    /// Creates a new SwitchingProtocolsResponse whose connection is passed to `callback`
+    /// once the response has been sent.
+    pub fn new<F, Fut>(callback: F) -> Self
+    where
+        F: FnOnce(Upgraded) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        SwitchingProtocolsResponse {
+            headers: Vec::new(),
+            on_upgrade: Some(upgrade::on_upgrade(callback)),
+        }
+    }
+
+    /// Adds a header to the response, such as `Upgrade`.
+    pub fn with_header(mut self, key: &str, value: &str) -> Self {
+        self.headers.push((key.to_string(), value.to_string()));
+        self
+    }
+}
+
+impl HttpResponse for SwitchingProtocolsResponse {
+    fn into_response(self: Box<Self>) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn status_code(&self) -> StatusCode {
+        StatusCode::SwitchingProtocols
+    }
+
+    fn set_headers(&self, headers: &mut Headers) {
+        for (key, value) in &self.headers {
+            headers.set(key, value);
+        }
+    }
+
+    fn take_upgrade(&mut self) -> Option<OnUpgrade> {
+        self.on_upgrade.take()
+    }
+}
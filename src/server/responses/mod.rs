@@ -2,11 +2,20 @@ mod client_error;
 mod http_error;
 mod http_response;
 mod informational;
+mod problem_details;
+mod redirection;
+mod serialization;
 mod server_error;
 mod successful;
 
-pub(crate) use http_response::HttpResponse;
+pub use http_error::HttpError;
+pub(crate) use http_error::{CapturedError, ErrorHandler};
+pub use http_response::HttpResponse;
 
 pub use client_error::*;
+pub use informational::*;
+pub use problem_details::ProblemDetails;
+pub use redirection::*;
+pub use serialization::*;
 pub use server_error::*;
 pub use successful::*;
@@ -0,0 +1,159 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::{
+    StatusCode,
+    headers::{Headers, keys},
+    responses::{HttpError, HttpResponse},
+};
+
+const CONTENT_TYPE_PROBLEM_JSON: &str = "application/problem+json";
+
+/// An error response in the RFC 9457 Problem Details format, sent as
+/// `application/problem+json`.
+///
+/// # Example
+///
+/// Rendering every error of a server as problem details:
+///
+/// ```no_run
+/// use http_server::{Router, Server, responses::ProblemDetails};
+///
+/// # async fn run() -> std::io::Result<()> {
+/// Server::new("127.0.0.1:8080", Router::new())
+///     .with_error_handler(|error, request| {
+///         let mut problem = ProblemDetails::from_error(error);
+///         if let Some(request) = request {
+///             problem = problem.with_instance(request.path());
+///         }
+///         problem.into()
+///     })
+///     .serve()
+///     .await
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ProblemDetails {
+    status: StatusCode,
+    members: Map<String, Value>,
+    headers: Vec<(&'static str, String)>,
+}
+
+impl ProblemDetails {
+    /// Creates a new ProblemDetails with `status` and its reason phrase as title.
+    pub fn new(status: StatusCode) -> Self {
+        let mut members = Map::new();
+        members.insert("title".to_string(), status.as_str().into());
+        members.insert("status".to_string(), (status as u16).into());
+
+        ProblemDetails {
+            status,
+            members,
+            headers: Vec::new(),
+        }
+    }
+
+    /// Creates a new ProblemDetails describing `error`, whose message becomes the detail.
+    /// The headers of the error, such as `Retry-After`, are kept.
+    pub fn from_error(error: &dyn HttpError) -> Self {
+        let mut problem = Self::new(error.status_code());
+        problem.headers = error.headers();
+
+        if error.message() != error.status_code().as_str() {
+            problem = problem.with_detail(error.message());
+        }
+
+        problem
+    }
+
+    /// Sets the URI identifying the type of problem. Defaults to `about:blank`.
+    pub fn with_type(self, uri: &str) -> Self {
+        self.with_member("type", uri)
+    }
+
+    /// Sets the short summary of the type of problem.
+    pub fn with_title(self, title: &str) -> Self {
+        self.with_member("title", title)
+    }
+
+    /// Sets the explanation specific to this occurrence of the problem.
+    pub fn with_detail(self, detail: &str) -> Self {
+        self.with_member("detail", detail)
+    }
+
+    /// Sets the URI identifying this occurrence of the problem.
+    pub fn with_instance(self, uri: &str) -> Self {
+        self.with_member("instance", uri)
+    }
+
+    /// Adds an extension member, such as the id of the request. Values that cannot be
+    /// serialized are left out.
+    pub fn with_extension<T: Serialize>(self, name: &str, value: T) -> Self {
+        match serde_json::to_value(value) {
+            Ok(value) => self.with_member(name, value),
+            Err(_) => self,
+        }
+    }
+
+    fn with_member<V: Into<Value>>(mut self, name: &str, value: V) -> Self {
+        self.members.insert(name.to_string(), value.into());
+        self
+    }
+}
+
+impl HttpResponse for ProblemDetails {
+    fn into_response(self: Box<Self>) -> Vec<u8> {
+        serde_json::to_vec(&self.members).unwrap_or_default()
+    }
+
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+
+    fn set_headers(&self, headers: &mut Headers) {
+        headers.set(keys::CONTENT_TYPE_KEY, CONTENT_TYPE_PROBLEM_JSON);
+
+        for (key, value) in &self.headers {
+            headers.set(key, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::responses::UpgradeRequiredError;
+
+    use super::*;
+
+    #[test]
+    fn test_from_error() {
+        let error = UpgradeRequiredError::with_message("Use WebSockets")
+            .with_header(keys::UPGRADE_HEADER, "websocket");
+        let problem = ProblemDetails::from_error(&error)
+            .with_type("https://example.com/problems/upgrade")
+            .with_extension("request_id", "req-1");
+
+        let mut headers = Headers::new();
+        problem.set_headers(&mut headers);
+        assert_eq!(
+            headers.get::<String>(keys::CONTENT_TYPE_KEY).unwrap(),
+            "application/problem+json"
+        );
+        assert_eq!(
+            headers.get::<String>(keys::UPGRADE_HEADER).unwrap(),
+            "websocket"
+        );
+
+        let body: Value = serde_json::from_slice(&Box::new(problem).into_response()).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "type": "https://example.com/problems/upgrade",
+                "title": "Upgrade Required",
+                "status": 426,
+                "detail": "Use WebSockets",
+                "request_id": "req-1",
+            })
+        );
+    }
+}
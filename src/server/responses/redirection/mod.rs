@@ -0,0 +1,3 @@
+mod not_modified_response;
+
+pub use not_modified_response::NotModifiedResponse;
@@ -0,0 +1,43 @@
+use crate::{StatusCode, headers::Headers, responses::HttpResponse};
+
+/// Represents a 304 Not Modified HTTP response.
+pub struct NotModifiedResponse {
+    headers: Vec<(String, String)>,
+}
+
+impl NotModifiedResponse {
+    /// Creates a new NotModifiedResponse without validator headers.
+    pub fn new() -> Self {
+        NotModifiedResponse {
+            headers: Vec::new(),
+        }
+    }
+
+    /// Adds a header that should be repeated on the 304, such as `ETag` or `Last-Modified`.
+    pub fn with_header(mut self, key: &str, value: &str) -> Self {
+        self.headers.push((key.to_string(), value.to_string()));
+        self
+    }
+}
+
+impl Default for NotModifiedResponse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HttpResponse for NotModifiedResponse {
+    fn into_response(self: Box<Self>) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn status_code(&self) -> StatusCode {
+        StatusCode::NotModified
+    }
+
+    fn set_headers(&self, headers: &mut Headers) {
+        for (key, value) in &self.headers {
+            headers.set(key, value);
+        }
+    }
+}
@@ -0,0 +1,218 @@
+use serde::Serialize;
+
+/// An error raised while serializing a response body.
+#[derive(Debug)]
+pub struct SerializationError {
+    message: String,
+}
+
+impl SerializationError {
+    fn new<E: std::fmt::Display>(error: E) -> Self {
+        SerializationError {
+            message: error.to_string(),
+        }
+    }
+}
+
+impl std::fmt::Display for SerializationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Serialization failed: {}", self.message)
+    }
+}
+
+impl std::error::Error for SerializationError {}
+
+/// Turns data into a response body of a specific media type.
+pub trait Serializer {
+    /// Returns the `Content-Type` of the bodies this serializer produces.
+    fn content_type(&self) -> &'static str;
+
+    /// Serializes the data into the bytes of a response body.
+    fn serialize<T: Serialize + ?Sized>(&self, data: &T) -> Result<Vec<u8>, SerializationError>;
+}
+
+/// Serializes bodies as compact JSON.
+pub struct Json;
+
+impl Serializer for Json {
+    fn content_type(&self) -> &'static str {
+        "application/json; charset=utf-8"
+    }
+
+    fn serialize<T: Serialize + ?Sized>(&self, data: &T) -> Result<Vec<u8>, SerializationError> {
+        serde_json::to_vec(data).map_err(SerializationError::new)
+    }
+}
+
+/// Serializes bodies as indented JSON.
+pub struct PrettyJson;
+
+impl Serializer for PrettyJson {
+    fn content_type(&self) -> &'static str {
+        "application/json; charset=utf-8"
+    }
+
+    fn serialize<T: Serialize + ?Sized>(&self, data: &T) -> Result<Vec<u8>, SerializationError> {
+        serde_json::to_vec_pretty(data).map_err(SerializationError::new)
+    }
+}
+
+/// Serializes bodies as MessagePack, with structs encoded as maps.
+#[cfg(feature = "msgpack")]
+pub struct MessagePack;
+
+#[cfg(feature = "msgpack")]
+impl Serializer for MessagePack {
+    fn content_type(&self) -> &'static str {
+        "application/msgpack"
+    }
+
+    fn serialize<T: Serialize + ?Sized>(&self, data: &T) -> Result<Vec<u8>, SerializationError> {
+        rmp_serde::to_vec_named(data).map_err(SerializationError::new)
+    }
+}
+
+/// Serializes bodies as CBOR.
+#[cfg(feature = "cbor")]
+pub struct Cbor;
+
+#[cfg(feature = "cbor")]
+impl Serializer for Cbor {
+    fn content_type(&self) -> &'static str {
+        "application/cbor"
+    }
+
+    fn serialize<T: Serialize + ?Sized>(&self, data: &T) -> Result<Vec<u8>, SerializationError> {
+        let mut buffer = Vec::new();
+        ciborium::into_writer(data, &mut buffer).map_err(SerializationError::new)?;
+        Ok(buffer)
+    }
+}
+
+/// Serializes bodies as `application/x-www-form-urlencoded`.
+#[cfg(feature = "form")]
+pub struct Form;
+
+#[cfg(feature = "form")]
+impl Serializer for Form {
+    fn content_type(&self) -> &'static str {
+        "application/x-www-form-urlencoded"
+    }
+
+    fn serialize<T: Serialize + ?Sized>(&self, data: &T) -> Result<Vec<u8>, SerializationError> {
+        serde_urlencoded::to_string(data)
+            .map(String::into_bytes)
+            .map_err(SerializationError::new)
+    }
+}
+
+/// One of the built-in serializers, chosen at runtime.
+///
+/// Pairs with [`Request::negotiate`](crate::Request::negotiate) to pick a format from the
+/// `Accept` header.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    PrettyJson,
+    #[cfg(feature = "msgpack")]
+    MessagePack,
+    #[cfg(feature = "cbor")]
+    Cbor,
+    #[cfg(feature = "form")]
+    Form,
+}
+
+impl Format {
+    /// Formats enabled in this build. `PrettyJson` is left out since it shares its media
+    /// type with `Json`.
+    pub const ALL: &[Format] = &[
+        Format::Json,
+        #[cfg(feature = "msgpack")]
+        Format::MessagePack,
+        #[cfg(feature = "cbor")]
+        Format::Cbor,
+        #[cfg(feature = "form")]
+        Format::Form,
+    ];
+
+    /// Returns the media type of the format, without parameters.
+    pub fn media_type(&self) -> &'static str {
+        let content_type = self.content_type();
+        content_type.split(';').next().unwrap_or(content_type)
+    }
+
+    /// Returns the format producing the given media type, ignoring any parameters.
+    pub fn from_media_type(media_type: &str) -> Option<Format> {
+        let essence = media_type.split(';').next()?.trim();
+
+        Format::ALL
+            .iter()
+            .find(|format| format.media_type().eq_ignore_ascii_case(essence))
+            .copied()
+    }
+}
+
+impl Serializer for Format {
+    fn content_type(&self) -> &'static str {
+        match self {
+            Format::Json => Json.content_type(),
+            Format::PrettyJson => PrettyJson.content_type(),
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => MessagePack.content_type(),
+            #[cfg(feature = "cbor")]
+            Format::Cbor => Cbor.content_type(),
+            #[cfg(feature = "form")]
+            Format::Form => Form.content_type(),
+        }
+    }
+
+    fn serialize<T: Serialize + ?Sized>(&self, data: &T) -> Result<Vec<u8>, SerializationError> {
+        match self {
+            Format::Json => Json.serialize(data),
+            Format::PrettyJson => PrettyJson.serialize(data),
+            #[cfg(feature = "msgpack")]
+            Format::MessagePack => MessagePack.serialize(data),
+            #[cfg(feature = "cbor")]
+            Format::Cbor => Cbor.serialize(data),
+            #[cfg(feature = "form")]
+            Format::Form => Form.serialize(data),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_from_media_type() {
+        assert_eq!(
+            Format::from_media_type("application/json; charset=utf-8"),
+            Some(Format::Json)
+        );
+        assert_eq!(Format::from_media_type("text/csv"), None);
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn test_message_pack() {
+        assert_eq!(
+            Format::from_media_type("application/msgpack"),
+            Some(Format::MessagePack)
+        );
+        assert_eq!(
+            MessagePack.serialize(&[1, 2]).unwrap(),
+            vec![0x92, 0x01, 0x02]
+        );
+    }
+
+    #[cfg(feature = "form")]
+    #[test]
+    fn test_form() {
+        let data = [("name", "Ada Lovelace"), ("year", "1815")];
+        assert_eq!(
+            Form.serialize(&data).unwrap(),
+            b"name=Ada+Lovelace&year=1815"
+        );
+    }
+}
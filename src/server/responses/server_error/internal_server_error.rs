@@ -0,0 +1,38 @@
+use crate::{response::StatusCode, responses::http_error::HttpError};
+
+/// Represents a 500 Internal Server Error HTTP error.
+pub struct InternalServerError {
+    message: String,
+}
+
+impl InternalServerError {
+    /// Creates a new InternalServerError with the default message.
+    pub fn new() -> Self {
+        InternalServerError {
+            message: StatusCode::InternalServerError.as_str().to_string(),
+        }
+    }
+
+    /// Creates a new InternalServerError with a custom message.
+    pub fn with_message<S: Into<String>>(message: S) -> Self {
+        InternalServerError {
+            message: message.into(),
+        }
+    }
+}
+
+impl Default for InternalServerError {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HttpError for InternalServerError {
+    fn message(&self) -> &str {
+        &self.message
+    }
+
+    fn status_code(&self) -> StatusCode {
+        StatusCode::InternalServerError
+    }
+}
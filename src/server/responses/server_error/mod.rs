@@ -1,3 +1,7 @@
+mod internal_server_error;
 mod not_implemented_error;
+mod service_unavailable_error;
 
+pub use internal_server_error::InternalServerError;
 pub use not_implemented_error::NotImplementedError;
+pub use service_unavailable_error::ServiceUnavailableError;
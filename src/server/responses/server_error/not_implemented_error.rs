@@ -1,4 +1,4 @@
-use crate::{response::StatusCode, responses::http_error::HttpError, };
+use crate::{response::StatusCode, responses::http_error::HttpError};
 
 /// Represents a 501 Not Implemented HTTP error.
 pub struct NotImplementedError {
@@ -21,6 +21,12 @@ impl NotImplementedError {
     }
 }
 
+impl Default for NotImplementedError {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl HttpError for NotImplementedError {
     fn message(&self) -> &str {
         &self.message
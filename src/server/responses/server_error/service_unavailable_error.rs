@@ -0,0 +1,81 @@
+use std::time::Duration;
+
+use crate::{headers::keys, response::StatusCode, responses::http_error::HttpError};
+
+/// Represents a 503 Service Unavailable HTTP error.
+pub struct ServiceUnavailableError {
+    message: String,
+    retry_after: Option<Duration>,
+}
+
+impl ServiceUnavailableError {
+    /// Creates a new ServiceUnavailableError with the default message.
+    pub fn new() -> Self {
+        ServiceUnavailableError {
+            message: StatusCode::ServiceUnavailable.as_str().to_string(),
+            retry_after: None,
+        }
+    }
+
+    /// Creates a new ServiceUnavailableError with a custom message.
+    pub fn with_message<S: Into<String>>(message: S) -> Self {
+        ServiceUnavailableError {
+            message: message.into(),
+            retry_after: None,
+        }
+    }
+
+    /// Tells the client how long to wait before retrying with a `Retry-After` header, in
+    /// whole seconds rounded up, and at least one second.
+    pub fn with_retry_after(mut self, delay: Duration) -> Self {
+        self.retry_after = Some(delay);
+        self
+    }
+}
+
+impl Default for ServiceUnavailableError {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HttpError for ServiceUnavailableError {
+    fn message(&self) -> &str {
+        &self.message
+    }
+
+    fn status_code(&self) -> StatusCode {
+        StatusCode::ServiceUnavailable
+    }
+
+    fn headers(&self) -> Vec<(&'static str, String)> {
+        self.retry_after
+            .map(|delay| {
+                let seconds = delay.as_secs() + u64::from(delay.subsec_nanos() > 0);
+                (keys::RETRY_AFTER_HEADER, seconds.max(1).to_string())
+            })
+            .into_iter()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_after_rounds_up() {
+        let retry_after = |delay| {
+            ServiceUnavailableError::new()
+                .with_retry_after(delay)
+                .headers()
+                .remove(0)
+                .1
+        };
+
+        assert_eq!(retry_after(Duration::from_millis(500)), "1");
+        assert_eq!(retry_after(Duration::ZERO), "1");
+        assert_eq!(retry_after(Duration::from_secs(3)), "3");
+        assert_eq!(retry_after(Duration::from_millis(3001)), "4");
+    }
+}
@@ -1,3 +1,7 @@
 mod ok_response;
+mod sse_response;
+mod tunnel_response;
 
 pub use ok_response::OkResponse;
+pub use sse_response::{SseEvent, SseResponse};
+pub use tunnel_response::TunnelResponse;
@@ -1,36 +1,154 @@
 use serde::Serialize;
 
-use crate::{StatusCode, responses::HttpResponse};
+use crate::{
+    Body, StatusCode,
+    headers::{Headers, keys},
+    responses::{HttpError, HttpResponse, InternalServerError, Json, Serializer},
+};
 
 /// Represents a 200 OK HTTP response.
+///
+/// If the data cannot be serialized, the error is written to standard error and the
+/// response is sent as a 500 Internal Server Error with a generic message instead, which
+/// the error handler of the server renders like any other error.
 pub struct OkResponse {
-    data: Vec<u8>,
+    data: Result<Vec<u8>, InternalServerError>,
+    content_type: Option<&'static str>,
 }
 
 impl OkResponse {
     /// Creates a new OkResponse with no data.
     pub fn new() -> Self {
-        OkResponse { data: Vec::new() }
+        OkResponse {
+            data: Ok(Vec::new()),
+            content_type: None,
+        }
     }
 
-    /// Creates a new OkResponse with the given serializable data.
+    /// Creates a new OkResponse with the given data serialized as JSON.
     pub fn from<T: Serialize>(data: T) -> Self {
-        // FIXME: Handle serialization errors properly
-        let serialization = serde_json::to_vec(&data)
-            .unwrap_or_else(|e| format!("Serialization failed: {}", e).as_bytes().to_vec());
+        Self::with_serializer(data, Json)
+    }
+
+    /// Creates a new OkResponse with the given data serialized by `serializer`, which also
+    /// sets the `Content-Type` of the response.
+    pub fn with_serializer<T: Serialize, S: Serializer>(data: T, serializer: S) -> Self {
+        // The details stay in the server logs rather than reaching the client
+        let data = serializer.serialize(&data).map_err(|error| {
+            eprintln!("Failed to serialize response: {}", error);
+            InternalServerError::new()
+        });
 
         OkResponse {
-            data: serialization,
+            data,
+            content_type: Some(serializer.content_type()),
         }
     }
 }
 
+impl Default for OkResponse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl HttpResponse for OkResponse {
     fn into_response(self: Box<Self>) -> Vec<u8> {
-        self.data
+        match self.data {
+            Ok(data) => data,
+            Err(error) => Box::new(error).into_response(),
+        }
+    }
+
+    fn into_body_with_request_id(self: Box<Self>, request_id: Option<&str>) -> Body {
+        match self.data {
+            Ok(data) => Body::Full(data),
+            Err(error) => Box::new(error).into_body_with_request_id(request_id),
+        }
     }
 
     fn status_code(&self) -> StatusCode {
-        StatusCode::Ok
+        match self.data {
+            Ok(_) => StatusCode::Ok,
+            Err(_) => StatusCode::InternalServerError,
+        }
+    }
+
+    fn set_headers(&self, headers: &mut Headers) {
+        if let Err(error) = &self.data {
+            error.set_headers(headers);
+            return;
+        }
+
+        if let Some(content_type) = self.content_type {
+            headers.set(keys::CONTENT_TYPE_KEY, content_type);
+        }
+    }
+
+    fn as_error(&self) -> Option<&dyn HttpError> {
+        self.data
+            .as_ref()
+            .err()
+            .map(|error| error as &dyn HttpError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::responses::PrettyJson;
+
+    use super::*;
+
+    #[test]
+    fn test_json_response() {
+        let response: Box<dyn HttpResponse> = OkResponse::from(vec![1, 2, 3]).into();
+
+        let mut headers = Headers::new();
+        response.set_headers(&mut headers);
+
+        assert_eq!(response.status_code(), StatusCode::Ok);
+        assert_eq!(
+            headers.get::<String>(keys::CONTENT_TYPE_KEY).unwrap(),
+            Json.content_type()
+        );
+        assert_eq!(response.into_response(), b"[1,2,3]");
+    }
+
+    #[test]
+    fn test_custom_serializer() {
+        let response: Box<dyn HttpResponse> =
+            OkResponse::with_serializer(vec![1], PrettyJson).into();
+
+        assert_eq!(response.into_response(), b"[\n  1\n]");
+    }
+
+    #[test]
+    fn test_serialization_failure_is_internal_server_error() {
+        // JSON objects only have string keys
+        let data = HashMap::from([((1, 2), 3)]);
+        let response: Box<dyn HttpResponse> = OkResponse::from(data).into();
+
+        assert_eq!(response.status_code(), StatusCode::InternalServerError);
+
+        let body = String::from_utf8(response.into_response()).unwrap();
+        assert!(body.contains("\"status_code\":500"));
+        assert!(!body.contains("key must be a string"));
+    }
+
+    #[test]
+    fn test_serialization_failure_is_reported_as_error() {
+        let data = HashMap::from([((1, 2), 3)]);
+        let response: Box<dyn HttpResponse> = OkResponse::from(data).into();
+
+        let error = response.as_error().unwrap();
+        assert_eq!(error.status_code(), StatusCode::InternalServerError);
+
+        let Body::Full(body) = response.into_body_with_request_id(Some("req-42")) else {
+            panic!("The error body should be in memory");
+        };
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["request_id"], "req-42");
     }
 }
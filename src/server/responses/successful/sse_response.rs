@@ -0,0 +1,274 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use futures_core::Stream;
+use tokio::{
+    io::{AsyncRead, ReadBuf},
+    sync::mpsc,
+    time::{Instant, Sleep},
+};
+
+use crate::{
+    Body, StatusCode,
+    headers::{Headers, keys},
+    responses::HttpResponse,
+};
+
+const DEFAULT_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+const KEEP_ALIVE_COMMENT: &[u8] = b": keep-alive\n\n";
+
+/// An event sent by an [`SseResponse`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SseEvent {
+    event: Option<String>,
+    data: Option<String>,
+    id: Option<String>,
+    retry: Option<Duration>,
+}
+
+impl SseEvent {
+    /// Creates a new SseEvent carrying `data`, which may span several lines.
+    pub fn data<S: Into<String>>(data: S) -> Self {
+        SseEvent {
+            data: Some(data.into()),
+            ..Default::default()
+        }
+    }
+
+    /// Sets the type of the event, which clients listen to with `addEventListener`.
+    pub fn with_event<S: Into<String>>(mut self, event: S) -> Self {
+        self.event = Some(event.into());
+        self
+    }
+
+    /// Sets the id of the event, which clients send back in `Last-Event-ID` when they
+    /// reconnect.
+    pub fn with_id<S: Into<String>>(mut self, id: S) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// Sets how long clients wait before reconnecting when the stream is interrupted.
+    pub fn with_retry(mut self, retry: Duration) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    fn encode(&self, buffer: &mut Vec<u8>) {
+        // Line breaks would end the field early, so they are dropped from single-line
+        // fields and split into several lines for the data
+        let single_line = |value: &str| value.replace(['\r', '\n'], "");
+
+        if let Some(event) = &self.event {
+            buffer.extend_from_slice(format!("event: {}\n", single_line(event)).as_bytes());
+        }
+
+        if let Some(id) = &self.id {
+            buffer.extend_from_slice(format!("id: {}\n", single_line(id)).as_bytes());
+        }
+
+        if let Some(retry) = self.retry {
+            buffer.extend_from_slice(format!("retry: {}\n", retry.as_millis()).as_bytes());
+        }
+
+        if let Some(data) = &self.data {
+            for line in data.split("\r\n").flat_map(|line| line.split(['\r', '\n'])) {
+                buffer.extend_from_slice(format!("data: {}\n", line).as_bytes());
+            }
+        }
+
+        buffer.push(b'\n');
+    }
+}
+
+type EventStream = Pin<Box<dyn Stream<Item = SseEvent> + Send>>;
+
+/// Represents a 200 OK HTTP response that streams Server-Sent Events.
+///
+/// A comment is sent whenever no event was sent for the keep-alive interval, so that
+/// proxies do not close the idle connection. Clients that reconnect send the id of the
+/// last event they received, available through the
+/// [`LastEventId`](crate::headers::LastEventId) header.
+pub struct SseResponse {
+    events: EventStream,
+    keep_alive: Option<Duration>,
+}
+
+impl SseResponse {
+    /// Creates a new SseResponse sending the events of `events` until it ends, with a
+    /// keep-alive every 15 seconds.
+    pub fn new<S: Stream<Item = SseEvent> + Send + 'static>(events: S) -> Self {
+        SseResponse {
+            events: Box::pin(events),
+            keep_alive: Some(DEFAULT_KEEP_ALIVE),
+        }
+    }
+
+    /// Creates a new SseResponse sending the events received from `receiver` until every
+    /// sender is dropped.
+    pub fn from_receiver(receiver: mpsc::Receiver<SseEvent>) -> Self {
+        Self::new(ReceiverStream(receiver))
+    }
+
+    /// Sets how long the stream may stay idle before a keep-alive comment is sent.
+    pub fn with_keep_alive(mut self, interval: Duration) -> Self {
+        self.keep_alive = Some(interval);
+        self
+    }
+
+    /// Disables the keep-alive comments.
+    pub fn without_keep_alive(mut self) -> Self {
+        self.keep_alive = None;
+        self
+    }
+}
+
+impl HttpResponse for SseResponse {
+    fn into_response(self: Box<Self>) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn status_code(&self) -> StatusCode {
+        StatusCode::Ok
+    }
+
+    fn set_headers(&self, headers: &mut Headers) {
+        headers.set(keys::CONTENT_TYPE_KEY, "text/event-stream");
+        headers.set(keys::CACHE_CONTROL_HEADER, "no-cache");
+    }
+
+    fn into_body(self: Box<Self>) -> Body {
+        Body::stream(EventReader {
+            events: self.events,
+            keep_alive: self
+                .keep_alive
+                .map(|interval| (interval, Box::pin(tokio::time::sleep(interval)))),
+            buffer: Vec::new(),
+            position: 0,
+        })
+    }
+}
+
+struct ReceiverStream(mpsc::Receiver<SseEvent>);
+
+impl Stream for ReceiverStream {
+    type Item = SseEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<SseEvent>> {
+        self.0.poll_recv(cx)
+    }
+}
+
+/// Encodes events as they are produced, interleaved with keep-alive comments.
+struct EventReader {
+    events: EventStream,
+    keep_alive: Option<(Duration, Pin<Box<Sleep>>)>,
+    buffer: Vec<u8>,
+    position: usize,
+}
+
+impl EventReader {
+    fn reset_keep_alive(&mut self) {
+        if let Some((interval, sleep)) = &mut self.keep_alive {
+            sleep.as_mut().reset(Instant::now() + *interval);
+        }
+    }
+}
+
+impl AsyncRead for EventReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        while self.position == self.buffer.len() {
+            self.buffer.clear();
+            self.position = 0;
+
+            match self.events.as_mut().poll_next(cx) {
+                Poll::Ready(Some(event)) => {
+                    event.encode(&mut self.buffer);
+                    self.reset_keep_alive();
+                }
+                Poll::Ready(None) => return Poll::Ready(Ok(())),
+                Poll::Pending => {
+                    let Some((_, sleep)) = &mut self.keep_alive else {
+                        return Poll::Pending;
+                    };
+
+                    if sleep.as_mut().poll(cx).is_pending() {
+                        return Poll::Pending;
+                    }
+
+                    self.buffer.extend_from_slice(KEEP_ALIVE_COMMENT);
+                    self.reset_keep_alive();
+                }
+            }
+        }
+
+        let remaining = &self.buffer[self.position..];
+        let len = remaining.len().min(buf.remaining());
+        buf.put_slice(&remaining[..len]);
+        self.position += len;
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+
+    #[test]
+    fn test_encode_event() {
+        let mut buffer = Vec::new();
+        SseEvent::data("first\nsecond")
+            .with_event("update")
+            .with_id("7")
+            .with_retry(Duration::from_secs(3))
+            .encode(&mut buffer);
+
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "event: update\nid: 7\nretry: 3000\ndata: first\ndata: second\n\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_events_until_senders_drop() {
+        let (sender, receiver) = mpsc::channel(4);
+        let response = Box::new(SseResponse::from_receiver(receiver));
+        let mut reader = response.into_body().into_reader().unwrap();
+
+        sender.send(SseEvent::data("a")).await.unwrap();
+        sender.send(SseEvent::data("b").with_id("2")).await.unwrap();
+        drop(sender);
+
+        let mut output = String::new();
+        reader.read_to_string(&mut output).await.unwrap();
+        assert_eq!(output, "data: a\n\nid: 2\ndata: b\n\n");
+    }
+
+    #[tokio::test]
+    async fn test_keep_alive() {
+        let (sender, receiver) = mpsc::channel(4);
+        let response = Box::new(
+            SseResponse::from_receiver(receiver).with_keep_alive(Duration::from_millis(20)),
+        );
+        let mut reader = response.into_body().into_reader().unwrap();
+
+        let mut buffer = vec![0; 64];
+        let read = reader.read(&mut buffer).await.unwrap();
+        assert_eq!(&buffer[..read], KEEP_ALIVE_COMMENT);
+
+        drop(sender);
+        assert_eq!(reader.read(&mut buffer).await.unwrap(), 0);
+    }
+}
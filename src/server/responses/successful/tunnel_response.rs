@@ -0,0 +1,59 @@
+use std::future::Future;
+
+use crate::{
+    StatusCode,
+    headers::Headers,
+    responses::HttpResponse,
+    server::upgrade::{self, OnUpgrade, Upgraded},
+};
+
+/// Represents a 200 OK HTTP response to a `CONNECT` request, after which the connection
+/// becomes a tunnel to the requested authority.
+///
+/// The response carries no body, and tunnels are only supported over HTTP/1.1. Over
+/// HTTP/2 it is answered with 501 Not Implemented instead.
+pub struct TunnelResponse {
+    headers: Vec<(String, String)>,
+    on_upgrade: Option<OnUpgrade>,
+}
+
+impl TunnelResponse {
+    /// Creates a new TunnelResponse whose connection is passed to `callback` once the
+    /// response has been sent.
+    pub fn new<F, Fut>(callback: F) -> Self
+    where
+        F: FnOnce(Upgraded) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        TunnelResponse {
+            headers: Vec::new(),
+            on_upgrade: Some(upgrade::on_upgrade(callback)),
+        }
+    }
+
+    /// Adds a header to the response.
+    pub fn with_header(mut self, key: &str, value: &str) -> Self {
+        self.headers.push((key.to_string(), value.to_string()));
+        self
+    }
+}
+
+impl HttpResponse for TunnelResponse {
+    fn into_response(self: Box<Self>) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn status_code(&self) -> StatusCode {
+        StatusCode::Ok
+    }
+
+    fn set_headers(&self, headers: &mut Headers) {
+        for (key, value) in &self.headers {
+            headers.set(key, value);
+        }
+    }
+
+    fn take_upgrade(&mut self) -> Option<OnUpgrade> {
+        self.on_upgrade.take()
+    }
+}
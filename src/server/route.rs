@@ -0,0 +1,141 @@
+const PATH_SEPARATOR: char = '/';
+const PARAM_PREFIX: char = ':';
+const WILDCARD_PREFIX: char = '*';
+
+enum Segment {
+    Static(String),
+    Param(String),
+    Wildcard(String),
+}
+
+/// A route path such as `/users/:id` or `/assets/*path`.
+///
+/// `:name` matches exactly one path segment and `*name` matches the rest of
+/// the path, including nothing at all. A wildcard is only allowed as the last
+/// segment, and parsing a pattern with segments after it panics.
+pub(crate) struct RoutePattern {
+    segments: Vec<Segment>,
+}
+
+fn split_path(path: &str) -> impl Iterator<Item = &str> {
+    path.split(PATH_SEPARATOR)
+        .filter(|segment| !segment.is_empty())
+}
+
+impl RoutePattern {
+    pub(crate) fn parse(pattern: &str) -> Self {
+        let mut segments = Vec::new();
+
+        for segment in split_path(pattern) {
+            if let Some(Segment::Wildcard(name)) = segments.last() {
+                panic!(
+                    "Invalid route {}: segments after the wildcard *{} would never match",
+                    pattern, name
+                );
+            }
+
+            if let Some(name) = segment.strip_prefix(PARAM_PREFIX) {
+                segments.push(Segment::Param(name.to_string()));
+            } else if let Some(name) = segment.strip_prefix(WILDCARD_PREFIX) {
+                segments.push(Segment::Wildcard(name.to_string()));
+            } else {
+                segments.push(Segment::Static(segment.to_string()));
+            }
+        }
+
+        RoutePattern { segments }
+    }
+
+    /// Returns true if the pattern has no parameters or wildcards.
+    pub(crate) fn is_static(&self) -> bool {
+        self.segments
+            .iter()
+            .all(|segment| matches!(segment, Segment::Static(_)))
+    }
+
+    /// Matches a request path against the pattern, returning the captured parameters in
+    /// the order they appear in the pattern.
+    pub(crate) fn matches(&self, path: &str) -> Option<Vec<(String, String)>> {
+        let mut params = Vec::new();
+        let mut parts = split_path(path);
+
+        for segment in &self.segments {
+            match segment {
+                Segment::Static(expected) => {
+                    if parts.next() != Some(expected.as_str()) {
+                        return None;
+                    }
+                }
+                Segment::Param(name) => {
+                    let value = parts.next()?;
+                    params.push((name.clone(), value.to_string()));
+                }
+                Segment::Wildcard(name) => {
+                    let rest = parts.by_ref().collect::<Vec<&str>>().join("/");
+                    params.push((name.clone(), rest));
+                }
+            }
+        }
+
+        if parts.next().is_some() {
+            return None;
+        }
+
+        Some(params)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_static_pattern() {
+        let pattern = RoutePattern::parse("/users/me");
+
+        assert!(pattern.is_static());
+        assert!(pattern.matches("/users/me").is_some());
+        assert!(pattern.matches("/users/you").is_none());
+        assert!(pattern.matches("/users/me/settings").is_none());
+    }
+
+    #[test]
+    fn test_param_pattern() {
+        let pattern = RoutePattern::parse("/users/:id/posts/:post");
+
+        assert!(!pattern.is_static());
+
+        let params = pattern.matches("/users/42/posts/7").unwrap();
+        assert_eq!(
+            params,
+            vec![
+                ("id".to_string(), "42".to_string()),
+                ("post".to_string(), "7".to_string())
+            ]
+        );
+
+        assert!(pattern.matches("/users/42/posts").is_none());
+    }
+
+    #[test]
+    fn test_wildcard_pattern() {
+        let pattern = RoutePattern::parse("/assets/*path");
+
+        let params = pattern.matches("/assets/css/site.css").unwrap();
+        assert_eq!(
+            params,
+            vec![("path".to_string(), "css/site.css".to_string())]
+        );
+
+        let params = pattern.matches("/assets").unwrap();
+        assert_eq!(params, vec![("path".to_string(), String::new())]);
+
+        assert!(pattern.matches("/other/site.css").is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "segments after the wildcard *path")]
+    fn test_segments_after_wildcard() {
+        RoutePattern::parse("/assets/*path/edit");
+    }
+}
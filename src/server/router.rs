@@ -1,10 +1,123 @@
 use std::{collections::HashMap, sync::Arc};
 
-use crate::{EndpointHandler, Request, Response, responses::NotFoundError};
+use crate::{
+    EndpointHandler, Extensions, Middleware, Request, Response,
+    extract::State,
+    responses::{HttpResponse, NotFoundError},
+    server::route::RoutePattern,
+};
+
+/// Handler produced by [`Router::build`], which resolves route parameters on the request
+/// before calling the matching endpoint.
+pub(crate) type RoutesHandler =
+    Arc<dyn Fn(&mut Request, &mut Response) -> Box<dyn HttpResponse> + Send + Sync + 'static>;
+
+/// Attaches a copy of the router state to the extensions of a request.
+type StateInjector = Arc<dyn Fn(&mut Extensions) + Send + Sync + 'static>;
+
+/// A handler along with the states and middleware of the nested routers it was
+/// registered on.
+#[derive(Clone)]
+struct Endpoint {
+    handler: EndpointHandler,
+    states: Vec<StateInjector>,
+    middleware: Vec<Middleware>,
+}
+
+impl Endpoint {
+    fn new(handler: EndpointHandler) -> Self {
+        Endpoint {
+            handler,
+            states: Vec::new(),
+            middleware: Vec::new(),
+        }
+    }
+
+    /// Adds the states and middleware of the router the endpoint is nested in. The
+    /// middleware of outer routers runs first, and the states of inner routers take
+    /// precedence.
+    fn nested(mut self, states: &[StateInjector], middleware: &[Middleware]) -> Self {
+        self.states.splice(0..0, states.iter().cloned());
+        self.middleware.splice(0..0, middleware.iter().cloned());
+        self
+    }
+
+    fn call(&self, req: &mut Request, res: &mut Response) -> Box<dyn HttpResponse> {
+        for inject in &self.states {
+            inject(req.extensions_mut());
+        }
+
+        if let Some(response) = run_middleware(&self.middleware, req, res) {
+            return response;
+        }
+
+        (self.handler)(req, res)
+    }
+}
+
+/// Runs `middleware` in order, stopping at the first one that answers the request.
+fn run_middleware(
+    middleware: &[Middleware],
+    req: &mut Request,
+    res: &mut Response,
+) -> Option<Box<dyn HttpResponse>> {
+    middleware
+        .iter()
+        .find_map(|middleware| middleware(req, res))
+}
+
+struct Route {
+    method: String,
+    path: String,
+    pattern: RoutePattern,
+    endpoint: Endpoint,
+}
 
 /// Router for managing HTTP endpoints.
+///
+/// Paths can contain `:name` parameters, which match a single segment, and a trailing
+/// `*name` wildcard, which matches the rest of the path. Captured values are available
+/// through [`Request::param`]. Registering a path with segments after its wildcard
+/// panics.
+///
+/// Middleware registered with [`Router::with_middleware`] runs before the handler of every
+/// matched route, fallback and CONNECT request, in the order it was registered.
+///
+/// Requests that match no route are passed to the fallback handler of the most specific
+/// nested router whose prefix they start with, then to the fallback of the router, and
+/// are otherwise rejected with 404 Not Found.
 pub struct Router {
-    endpoints: HashMap<String, EndpointHandler>,
+    endpoints: HashMap<String, Endpoint>,
+    routes: Vec<Route>,
+    connect: Option<Endpoint>,
+    fallback: Option<EndpointHandler>,
+    /// The fallbacks of nested routers, by path prefix.
+    nested_fallbacks: Vec<(String, Endpoint)>,
+    states: Vec<StateInjector>,
+    middleware: Vec<Middleware>,
+}
+
+/// Joins a nesting prefix and a route path, so that `/` routes match the prefix itself.
+fn join_path(prefix: &str, path: &str) -> String {
+    let prefix = prefix.trim_end_matches('/');
+
+    match path.trim_start_matches('/') {
+        "" if prefix.is_empty() => "/".to_string(),
+        "" => prefix.to_string(),
+        rest => format!("{}/{}", prefix, rest),
+    }
+}
+
+/// Returns whether `path` is `prefix` or one of its sub-paths.
+fn has_prefix(path: &str, prefix: &str) -> bool {
+    path.strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/') || prefix.ends_with('/'))
+}
+
+impl Default for Router {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Router {
@@ -12,45 +125,401 @@ impl Router {
     pub fn new() -> Self {
         Router {
             endpoints: HashMap::new(),
+            routes: Vec::new(),
+            connect: None,
+            fallback: None,
+            nested_fallbacks: Vec::new(),
+            states: Vec::new(),
+            middleware: Vec::new(),
+        }
+    }
+
+    /// Shares `state` with every handler of the router, which receive it through the
+    /// [`State`] extractor.
+    ///
+    /// The state is cloned for each request, so large or mutable data should be wrapped in
+    /// an `Arc`. Several states of different types can be registered.
+    pub fn with_state<S: Clone + Send + Sync + 'static>(mut self, state: S) -> Self {
+        self.states
+            .push(Arc::new(move |extensions: &mut Extensions| {
+                extensions.insert(State(state.clone()));
+            }));
+        self
+    }
+
+    /// Runs `middleware` before the handler of every route, fallback and CONNECT request,
+    /// such as one checking credentials and attaching the authenticated user to the
+    /// request extensions, where handlers read it with the
+    /// [`Extension`](crate::extract::Extension) extractor.
+    ///
+    /// Returning `Some` answers the request with that response without calling the
+    /// handler or the middleware registered after it.
+    pub fn with_middleware<F>(mut self, middleware: F) -> Self
+    where
+        F: Fn(&mut Request, &mut Response) -> Option<Box<dyn HttpResponse>> + Send + Sync + 'static,
+    {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    fn add(&mut self, method: &str, path: &str, handler: EndpointHandler) {
+        self.add_endpoint(method, path, Endpoint::new(handler));
+    }
+
+    fn add_endpoint(&mut self, method: &str, path: &str, endpoint: Endpoint) {
+        let pattern = RoutePattern::parse(path);
+
+        if pattern.is_static() {
+            self.endpoints
+                .insert(format!("{} {}", method, path), endpoint);
+            return;
         }
+
+        self.routes.push(Route {
+            method: method.to_string(),
+            path: path.to_string(),
+            pattern,
+            endpoint,
+        });
     }
 
     /// Registers a POST endpoint with the given path and handler.
     pub fn post(&mut self, path: &str, handler: EndpointHandler) {
-        self.endpoints.insert(format!("POST {}", path), handler);
+        self.add("POST", path, handler);
     }
 
     /// Registers a GET endpoint with the given path and handler.
     pub fn get(&mut self, path: &str, handler: EndpointHandler) {
-        self.endpoints.insert(format!("GET {}", path), handler);
+        self.add("GET", path, handler);
     }
 
     /// Registers a DELETE endpoint with the given path and handler.
     pub fn delete(&mut self, path: &str, handler: EndpointHandler) {
-        self.endpoints.insert(format!("DELETE {}", path), handler);
+        self.add("DELETE", path, handler);
     }
 
     /// Registers a PUT endpoint with the given path and handler.
     pub fn put(&mut self, path: &str, handler: EndpointHandler) {
-        self.endpoints.insert(format!("PUT {}", path), handler);
+        self.add("PUT", path, handler);
     }
 
     /// Registers a PATCH endpoint with the given path and handler.
     pub fn patch(&mut self, path: &str, handler: EndpointHandler) {
-        self.endpoints.insert(format!("PATCH {}", path), handler);
+        self.add("PATCH", path, handler);
+    }
+
+    /// Registers the handler of every CONNECT request, whose target is the authority to
+    /// tunnel to, such as `example.com:443`, available through [`Request::path`].
+    ///
+    /// The handler usually answers with a [`TunnelResponse`](crate::responses::TunnelResponse).
+    pub fn connect(&mut self, handler: EndpointHandler) {
+        self.connect = Some(Endpoint::new(handler));
+    }
+
+    /// Registers the handler of requests that match no route, such as one serving the
+    /// `index.html` of a single-page application or a custom 404 Not Found body.
+    pub fn fallback(&mut self, handler: EndpointHandler) {
+        self.fallback = Some(handler);
+    }
+
+    /// Registers the routes of `router` under `prefix`, so that a `/users/:id` route
+    /// nested at `/api` matches `/api/users/42`.
+    ///
+    /// The states and middleware of `router` only apply to its own handlers, and its
+    /// fallback handles the unmatched requests whose path starts with `prefix`. Its
+    /// CONNECT handler handles every CONNECT request, unless this router already has one.
+    pub fn nest(&mut self, prefix: &str, router: Router) {
+        let states = router.states;
+        let middleware = router.middleware;
+
+        if self.connect.is_none() {
+            self.connect = router
+                .connect
+                .map(|endpoint| endpoint.nested(&states, &middleware));
+        }
+
+        for (key, endpoint) in router.endpoints {
+            let (method, path) = key.split_once(' ').unwrap_or(("", &key));
+            self.add_endpoint(
+                method,
+                &join_path(prefix, path),
+                endpoint.nested(&states, &middleware),
+            );
+        }
+
+        for route in router.routes {
+            self.add_endpoint(
+                &route.method,
+                &join_path(prefix, &route.path),
+                route.endpoint.nested(&states, &middleware),
+            );
+        }
+
+        for (nested_prefix, endpoint) in router.nested_fallbacks {
+            self.nested_fallbacks.push((
+                join_path(prefix, &nested_prefix),
+                endpoint.nested(&states, &middleware),
+            ));
+        }
+
+        if let Some(handler) = router.fallback {
+            self.nested_fallbacks.push((
+                join_path(prefix, "/"),
+                Endpoint::new(handler).nested(&states, &middleware),
+            ));
+        }
+
+        // The most specific prefix is tried first
+        self.nested_fallbacks
+            .sort_by_key(|(prefix, _)| std::cmp::Reverse(prefix.len()));
     }
 
-    pub(crate) fn build(self) -> EndpointHandler {
-        Arc::new(move |req: &Request, res: &mut Response| {
+    /// Registers a WebSocket endpoint with the given path. Each accepted connection is
+    /// passed to `handler`, which runs until the connection is no longer needed.
+    ///
+    /// Handlers that need the request should be registered with [`Router::get`] and take
+    /// a [`WebSocketUpgrade`](crate::websocket::WebSocketUpgrade) argument instead.
+    #[cfg(feature = "websocket")]
+    pub fn ws<F, Fut>(&mut self, path: &str, handler: F)
+    where
+        F: Fn(crate::websocket::WebSocket) -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = ()> + Send + 'static,
+    {
+        let handler = Arc::new(handler);
+
+        self.get(
+            path,
+            crate::handler(move |upgrade: crate::websocket::WebSocketUpgrade| {
+                let handler = handler.clone();
+                upgrade.on_upgrade(move |socket| handler(socket))
+            }),
+        );
+    }
+
+    pub(crate) fn build(self) -> RoutesHandler {
+        Arc::new(move |req: &mut Request, res: &mut Response| {
+            for inject in &self.states {
+                inject(req.extensions_mut());
+            }
+
+            if req.method() == "CONNECT"
+                && let Some(endpoint) = &self.connect
+            {
+                return run_middleware(&self.middleware, req, res)
+                    .unwrap_or_else(|| endpoint.call(req, res));
+            }
+
             let key = format!("{} {}", req.method(), req.path());
 
-            if let Some(handler) = self.endpoints.get(&key) {
-                return handler(req, res);
+            if let Some(endpoint) = self.endpoints.get(&key) {
+                let path = req.path().to_string();
+                req.set_route(&path);
+                return run_middleware(&self.middleware, req, res)
+                    .unwrap_or_else(|| endpoint.call(req, res));
+            }
+
+            for route in self.routes.iter().filter(|r| r.method == req.method()) {
+                if let Some(params) = route.pattern.matches(req.path()) {
+                    req.set_params(params);
+                    req.set_route(&route.path);
+                    return run_middleware(&self.middleware, req, res)
+                        .unwrap_or_else(|| route.endpoint.call(req, res));
+                }
+            }
+
+            let nested = self
+                .nested_fallbacks
+                .iter()
+                .find(|(prefix, _)| has_prefix(req.path(), prefix));
+
+            if let Some((_, endpoint)) = nested {
+                return run_middleware(&self.middleware, req, res)
+                    .unwrap_or_else(|| endpoint.call(req, res));
+            }
+
+            if let Some(handler) = &self.fallback {
+                return run_middleware(&self.middleware, req, res)
+                    .unwrap_or_else(|| handler(req, res));
             }
 
             let error =
                 NotFoundError::with_message(format!("Cannot {} {}", req.method(), req.path()));
-            return error.into();
+            error.into()
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        StatusCode,
+        extract::{Extension, Path},
+        handler,
+        headers::keys,
+        responses::{BadRequestError, OkResponse},
+    };
+
+    use super::*;
+
+    async fn call(routes: &RoutesHandler, raw: &str) -> (Request, Vec<u8>) {
+        let mut req = Request::from_reader(raw.as_bytes()).await.unwrap();
+        let mut res = Response::new();
+        let body = routes(&mut req, &mut res).into_response();
+        (req, body)
+    }
+
+    #[tokio::test]
+    async fn test_nested_routes_and_fallbacks() {
+        let mut users = Router::new().with_state(7_u32);
+        users.get(
+            "/:id",
+            handler(|Path(id): Path<String>, State(n): State<u32>| {
+                OkResponse::from(format!("user {} {}", id, n))
+            }),
+        );
+        users.fallback(handler(|| OkResponse::from("no such user route")));
+
+        let mut api = Router::new();
+        api.get("/", handler(|| OkResponse::from("api")));
+        api.nest("/users", users);
+        api.fallback(handler(|| OkResponse::from("api 404")));
+
+        let mut router = Router::new();
+        router.nest("/api", api);
+        router.fallback(Arc::new(|req, _| {
+            OkResponse::from(format!("index.html for {}", req.path())).into()
+        }));
+        let routes = router.build();
+
+        let (req, body) = call(&routes, "GET /api/users/42 HTTP/1.1\r\n\r\n").await;
+        assert_eq!(body, b"\"user 42 7\"");
+        assert_eq!(req.route(), Some("/api/users/:id"));
+
+        let (_, body) = call(&routes, "GET /api HTTP/1.1\r\n\r\n").await;
+        assert_eq!(body, b"\"api\"");
+
+        let (_, body) = call(&routes, "GET /api/users/42/posts HTTP/1.1\r\n\r\n").await;
+        assert_eq!(body, b"\"no such user route\"");
+
+        let (_, body) = call(&routes, "GET /api/orders HTTP/1.1\r\n\r\n").await;
+        assert_eq!(body, b"\"api 404\"");
+
+        let (_, body) = call(&routes, "GET /apiary HTTP/1.1\r\n\r\n").await;
+        assert_eq!(body, b"\"index.html for /apiary\"");
+    }
+
+    #[tokio::test]
+    async fn test_nested_states() {
+        let mut inner = Router::new().with_state(3_u32);
+        inner.get("/", handler(|State(n): State<u32>| OkResponse::from(n)));
+
+        let mut middle = Router::new().with_state(2_u32);
+        middle.get("/", handler(|State(n): State<u32>| OkResponse::from(n)));
+        middle.nest("/inner", inner);
+
+        let mut router = Router::new().with_state(1_u32);
+        router.nest("/middle", middle);
+        let routes = router.build();
+
+        // The state of the innermost router wins over the ones it is nested in
+        let (_, body) = call(&routes, "GET /middle/inner HTTP/1.1\r\n\r\n").await;
+        assert_eq!(body, b"3");
+
+        let (_, body) = call(&routes, "GET /middle HTTP/1.1\r\n\r\n").await;
+        assert_eq!(body, b"2");
+    }
+
+    #[derive(Clone)]
+    struct User(String);
+
+    #[tokio::test]
+    async fn test_middleware() {
+        let mut admin = Router::new().with_middleware(|req, _| {
+            let user = req.extensions().get::<User>()?;
+            (user.0 != "root").then(|| BadRequestError::with_message("Not an admin").into())
+        });
+        admin.get(
+            "/stats",
+            handler(|Extension(User(name)): Extension<User>| {
+                OkResponse::from(format!("stats for {}", name))
+            }),
+        );
+
+        let mut router = Router::new().with_middleware(|req, _| {
+            match req.headers().get::<String>(keys::AUTHORIZATION_HEADER) {
+                Some(token) => {
+                    let name = token.trim_start_matches("Bearer ").to_string();
+                    req.extensions_mut().insert(User(name));
+                    None
+                }
+                None => Some(BadRequestError::with_message("Missing credentials").into()),
+            }
+        });
+        router.get(
+            "/me",
+            handler(|Extension(User(name)): Extension<User>| OkResponse::from(name)),
+        );
+        router.nest("/admin", admin);
+        let routes = router.build();
+
+        let (_, body) = call(
+            &routes,
+            "GET /me HTTP/1.1\r\nAuthorization: Bearer alice\r\n\r\n",
+        )
+        .await;
+        assert_eq!(body, b"\"alice\"");
+
+        let (_, body) = call(
+            &routes,
+            "GET /admin/stats HTTP/1.1\r\nAuthorization: Bearer root\r\n\r\n",
+        )
+        .await;
+        assert_eq!(body, b"\"stats for root\"");
+
+        for raw in [
+            "GET /me HTTP/1.1\r\n\r\n",
+            "GET /admin/stats HTTP/1.1\r\nAuthorization: Bearer alice\r\n\r\n",
+        ] {
+            let mut req = Request::from_reader(raw.as_bytes()).await.unwrap();
+            let result = routes(&mut req, &mut Response::new());
+            assert_eq!(result.status_code(), StatusCode::BadRequest);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_connect_middleware() {
+        let mut router = Router::new().with_middleware(|req, _| {
+            let authorized = req.headers().contains("Proxy-Authorization");
+            (!authorized).then(|| BadRequestError::with_message("Missing credentials").into())
+        });
+        router.connect(handler(|| OkResponse::from("tunnel")));
+        let routes = router.build();
+
+        let (_, body) = call(
+            &routes,
+            "CONNECT example.com:443 HTTP/1.1\r\nProxy-Authorization: Basic Zm9v\r\n\r\n",
+        )
+        .await;
+        assert_eq!(body, b"\"tunnel\"");
+
+        let raw = "CONNECT example.com:443 HTTP/1.1\r\n\r\n";
+        let mut req = Request::from_reader(raw.as_bytes()).await.unwrap();
+        let result = routes(&mut req, &mut Response::new());
+        assert_eq!(result.status_code(), StatusCode::BadRequest);
+    }
+
+    #[tokio::test]
+    async fn test_nested_connect() {
+        let mut proxy = Router::new().with_state("proxy");
+        proxy.connect(handler(|State(name): State<&'static str>| {
+            OkResponse::from(name)
+        }));
+
+        let mut router = Router::new();
+        router.nest("/proxy", proxy);
+        let routes = router.build();
+
+        let (_, body) = call(&routes, "CONNECT example.com:443 HTTP/1.1\r\n\r\n").await;
+        assert_eq!(body, b"\"proxy\"");
+    }
+}
@@ -1,60 +1,1221 @@
+use std::{
+    io,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
 use crate::{
-    Router,
+    Compression, ETagMode, Router,
     request::Request,
-    response::{Response, StatusCode},
+    response::Response,
+    responses::{BadRequestError, ErrorHandler, HttpError, HttpResponse, InternalServerError},
+    server::{
+        access_log::AccessLog,
+        connection::{Connection, Describe},
+        limits::Limits,
+        listener::{Accepted, Bound, Listener, LocalAddr},
+        metrics::Metrics,
+        observe::{self, Observation},
+        panic::{self, PanicHook, PanicReport},
+        request_id::RequestIdConfig,
+        router::RoutesHandler,
+        shutdown::Shutdown,
+        upgrade::{Rewind, Upgraded},
+    },
 };
-use tokio::{io::AsyncWriteExt, net::TcpListener};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    sync::OwnedSemaphorePermit,
+    task::JoinSet,
+};
+
+#[cfg(feature = "http2")]
+use crate::server::http2;
+#[cfg(feature = "tls")]
+use crate::tls::TlsConfig;
+#[cfg(feature = "otlp")]
+use crate::trace::{OtlpExporter, SpanSender};
+#[cfg(feature = "tls")]
+use tokio_rustls::TlsAcceptor;
+
+const DEFAULT_MAX_DECOMPRESSED_SIZE: u64 = 16 * 1024 * 1024;
+const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_READ_TIMEOUT: Duration = Duration::from_secs(30);
+/// How long a connection over the limit has to send its request before it is answered.
+const REJECTED_READ_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long a client has to complete the TLS handshake, during which its connection holds
+/// a slot when the connections are limited.
+#[cfg(feature = "tls")]
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Settings shared by every connection of a server.
+#[derive(Clone)]
+pub(super) struct Options {
+    etag: Option<ETagMode>,
+    compression: Option<Compression>,
+    max_decompressed_size: u64,
+    read_timeout: Duration,
+    pub(super) access_log: Option<Arc<AccessLog>>,
+    pub(super) metrics: Option<Metrics>,
+    pub(super) request_id: Option<RequestIdConfig>,
+    pub(super) panic_hook: Option<PanicHook>,
+    pub(super) error_handler: Option<ErrorHandler>,
+    pub(super) shutdown: Shutdown,
+    pub(super) limits: Option<Limits>,
+    #[cfg(feature = "otlp")]
+    pub(super) otlp: Option<SpanSender>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            etag: None,
+            compression: None,
+            max_decompressed_size: DEFAULT_MAX_DECOMPRESSED_SIZE,
+            read_timeout: DEFAULT_READ_TIMEOUT,
+            access_log: None,
+            metrics: None,
+            request_id: None,
+            panic_hook: None,
+            error_handler: None,
+            shutdown: Shutdown::default(),
+            limits: None,
+            #[cfg(feature = "otlp")]
+            otlp: None,
+        }
+    }
+}
 
 /// Represents an HTTP server.
 pub struct Server {
-    addr: String,
+    listeners: Vec<Listener>,
+    shutdown_timeout: Duration,
     router: Router,
+    options: Options,
+    #[cfg(feature = "tls")]
+    tls: Option<TlsConfig>,
+    #[cfg(feature = "otlp")]
+    otlp: Option<OtlpExporter>,
 }
 
 impl Server {
     /// Creates a new Server instance with the specified address and router.
     pub fn new(addr: &str, router: Router) -> Self {
+        Self::from_listener(Listener::tcp(addr), router)
+    }
+
+    /// Creates a new Server instance accepting connections on `listener`, such as a Unix
+    /// domain socket or a socket passed by systemd.
+    pub fn from_listener(listener: Listener, router: Router) -> Self {
         Server {
-            addr: addr.to_string(),
+            listeners: vec![listener],
+            shutdown_timeout: DEFAULT_SHUTDOWN_TIMEOUT,
             router,
+            options: Options::default(),
+            #[cfg(feature = "tls")]
+            tls: None,
+            #[cfg(feature = "otlp")]
+            otlp: None,
         }
     }
 
+    /// Compresses response bodies using the encodings accepted by each client.
+    ///
+    /// The encodings are enabled with the `gzip`, `deflate`, `br` and `zstd` cargo
+    /// features, one of which is required.
+    #[cfg(any(feature = "gzip", feature = "deflate", feature = "br", feature = "zstd"))]
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.options.compression = Some(compression);
+        self
+    }
+
+    /// Generates an `ETag` from the body of successful responses that do not set one.
+    ///
+    /// Responses carrying an `ETag` or `Last-Modified` header are always checked against
+    /// `If-None-Match`, `If-Modified-Since`, `If-Match` and `If-Unmodified-Since`, and
+    /// answered with 304 Not Modified or 412 Precondition Failed when they apply.
+    pub fn with_etag(mut self, mode: ETagMode) -> Self {
+        self.options.etag = Some(mode);
+        self
+    }
+
+    /// Sets the maximum size, in bytes, of a request body after removing its
+    /// `Content-Encoding`. Larger bodies are rejected with 413 Content Too Large.
+    ///
+    /// Defaults to 16 MiB.
+    pub fn with_max_decompressed_size(mut self, max_size: u64) -> Self {
+        self.options.max_decompressed_size = max_size;
+        self
+    }
+
+    /// Serves HTTPS instead of plain HTTP, on the listeners without TLS settings of their
+    /// own.
+    #[cfg(feature = "tls")]
+    pub fn with_tls(mut self, tls: TlsConfig) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// Writes a record of every request to `access_log` once its response has been sent.
+    ///
+    /// A graceful shutdown waits for the queued records to be written before returning.
+    pub fn with_access_log(mut self, access_log: AccessLog) -> Self {
+        self.options.access_log = Some(Arc::new(access_log));
+        self
+    }
+
+    /// Records Prometheus metrics about connections and requests, and serves them at the
+    /// path of `metrics`.
+    pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.options.metrics = Some(metrics);
+        self
+    }
+
+    /// Gives every request an id, read from a request header or generated, and echoes it
+    /// in the same response header.
+    pub fn with_request_id(mut self, config: RequestIdConfig) -> Self {
+        self.options.request_id = Some(config);
+        self
+    }
+
+    /// Calls `hook` with every panic raised by a handler, for example to send it to an
+    /// error tracker.
+    ///
+    /// Panics are always answered with 500 Internal Server Error and written to standard
+    /// error with their backtrace, whether or not a hook is set.
+    pub fn with_panic_hook<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&PanicReport) + Send + Sync + 'static,
+    {
+        self.options.panic_hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// Renders every error response with `handler`, including the 400 Bad Request sent
+    /// for requests that cannot be parsed and the 404 Not Found of unknown routes. The
+    /// request is `None` when it could not be parsed.
+    ///
+    /// The handler can answer with [`ProblemDetails`](crate::responses::ProblemDetails),
+    /// an HTML page or any other [`HttpResponse`]. Errors are otherwise sent as JSON.
+    pub fn with_error_handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&dyn HttpError, Option<&Request>) -> Box<dyn HttpResponse> + Send + Sync + 'static,
+    {
+        self.options.error_handler = Some(Arc::new(handler));
+        self
+    }
+
+    /// Limits the number of open connections and of requests being handled, waiting or
+    /// answering 503 Service Unavailable when a limit is reached.
+    ///
+    /// Requests to the metrics endpoint set with [`Server::with_metrics`] do not count
+    /// against the request limit, but their connections count against the connection
+    /// limit like any other.
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        limits.build();
+        self.options.limits = Some(limits);
+        self
+    }
+
+    /// Exports a span for every sampled request to an OpenTelemetry collector.
+    #[cfg(feature = "otlp")]
+    pub fn with_otlp(mut self, exporter: OtlpExporter) -> Self {
+        self.otlp = Some(exporter);
+        self
+    }
+
+    /// Serves every request on `listener` too, such as an IPv6 address next to an IPv4
+    /// one, or an HTTPS port next to an HTTP one.
+    pub fn with_listener(mut self, listener: Listener) -> Self {
+        self.listeners.push(listener);
+        self
+    }
+
+    /// Sets how long a graceful shutdown waits for open connections to finish before
+    /// giving up on them.
+    ///
+    /// Defaults to 30 seconds.
+    pub fn with_shutdown_timeout(mut self, timeout: Duration) -> Self {
+        self.shutdown_timeout = timeout;
+        self
+    }
+
+    /// Sets how long a client has to send its request, body included, once connected.
+    /// The connection is closed after that, freeing its slot when the connections are
+    /// limited.
+    ///
+    /// Defaults to 30 seconds.
+    pub fn with_read_timeout(mut self, timeout: Duration) -> Self {
+        self.options.read_timeout = timeout;
+        self
+    }
+
+    /// Binds every listener without accepting connections yet, so that the bound
+    /// addresses can be read, for example to find the port picked for port 0.
+    pub async fn bind(self) -> io::Result<BoundServer> {
+        let handler = self.router.build();
+
+        #[allow(unused_mut)]
+        let mut options = self.options;
+        #[cfg(feature = "otlp")]
+        if let Some(exporter) = self.otlp {
+            options.otlp = Some(exporter.spawn());
+        }
+        let shutdown = options.shutdown.clone();
+        let access_log = options.access_log.clone();
+        let options = Arc::new(options);
+
+        let mut listeners = Vec::with_capacity(self.listeners.len());
+        let mut local_addrs = Vec::with_capacity(self.listeners.len());
+        #[cfg(feature = "tls")]
+        let mut watched = self.tls.iter().cloned().collect::<Vec<_>>();
+
+        for listener in self.listeners {
+            #[cfg(feature = "tls")]
+            let tls = match listener.tls() {
+                Some(tls) => {
+                    watched.push(tls.clone());
+                    Some(tls.acceptor())
+                }
+                None => self.tls.as_ref().map(TlsConfig::acceptor),
+            };
+
+            let listener = listener.bind().await?;
+            local_addrs.push(listener.local_addr()?);
+
+            let acceptor = Acceptor {
+                handler: handler.clone(),
+                options: options.clone(),
+                #[cfg(feature = "tls")]
+                tls,
+            };
+            listeners.push((listener, acceptor));
+        }
+
+        // Certificates are only watched once every listener is bound
+        #[cfg(feature = "tls")]
+        for tls in watched {
+            tls.watch(&shutdown);
+        }
+
+        Ok(BoundServer {
+            listeners,
+            local_addrs,
+            shutdown,
+            shutdown_timeout: self.shutdown_timeout,
+            access_log,
+        })
+    }
+
     /// Starts the server and begins listening for incoming connections.
     pub async fn serve(self) -> Result<(), std::io::Error> {
-        let listener = TcpListener::bind(&self.addr).await?;
+        self.bind().await?.serve().await
+    }
+
+    /// Starts the server, and shuts it down gracefully once `signal` completes.
+    ///
+    /// See [`BoundServer::serve_with_shutdown`].
+    pub async fn serve_with_shutdown<F>(self, signal: F) -> io::Result<()>
+    where
+        F: Future<Output = ()>,
+    {
+        self.bind().await?.serve_with_shutdown(signal).await
+    }
+}
+
+/// A server whose listeners are bound, created with [`Server::bind`].
+pub struct BoundServer {
+    listeners: Vec<(Bound, Acceptor)>,
+    local_addrs: Vec<LocalAddr>,
+    shutdown: Shutdown,
+    shutdown_timeout: Duration,
+    access_log: Option<Arc<AccessLog>>,
+}
+
+impl BoundServer {
+    /// Returns the addresses the listeners are bound to, in the order they were added.
+    pub fn local_addrs(&self) -> &[LocalAddr] {
+        &self.local_addrs
+    }
+
+    /// Begins accepting connections on every listener.
+    pub async fn serve(self) -> io::Result<()> {
+        self.serve_with_shutdown(std::future::pending()).await
+    }
+
+    /// Begins accepting connections on every listener, and shuts the server down
+    /// gracefully once `signal` completes, such as on Ctrl-C.
+    ///
+    /// All the listeners stop accepting connections, connections yet to send a request are
+    /// closed, HTTP/2 clients are told not to open new streams, and requests in progress
+    /// are completed. The server returns once every
+    /// connection is closed, or after the shutdown timeout, leaving the connections still
+    /// open, such as WebSockets, to the runtime.
+    ///
+    /// A failing listener shuts the server down in the same way, and its error is returned.
+    pub async fn serve_with_shutdown<F>(self, signal: F) -> io::Result<()>
+    where
+        F: Future<Output = ()>,
+    {
+        let mut accepting = JoinSet::new();
+        for (listener, acceptor) in self.listeners {
+            accepting.spawn(accept(listener, acceptor));
+        }
+
+        let result = tokio::select! {
+            _ = signal => Ok(()),
+            Some(result) = accepting.join_next() => result.unwrap_or_else(|err| Err(io::Error::other(err))),
+        };
+
+        // Dropping the listeners also removes the socket files of Unix listeners
+        accepting.shutdown().await;
+        self.shutdown.trigger();
+
+        let closed = tokio::time::timeout(self.shutdown_timeout, self.shutdown.closed()).await;
+        if closed.is_err() {
+            eprintln!(
+                "Shutdown timed out with {} connections open",
+                self.shutdown.open_connections()
+            );
+        }
+
+        // Records still queued for the access log would be lost when the process exits
+        if let Some(access_log) = self.access_log {
+            let flushed = tokio::task::spawn_blocking(move || access_log.flush());
+            if tokio::time::timeout(self.shutdown_timeout, flushed)
+                .await
+                .is_err()
+            {
+                eprintln!("Shutdown timed out writing the access log");
+            }
+        }
 
-        let routes_handler = self.router.build();
+        result
+    }
+}
 
-        loop {
-            let (mut stream, _) = listener.accept().await?;
+/// Accepts the connections of `listener` until it fails.
+async fn accept(listener: Bound, acceptor: Acceptor) -> io::Result<()> {
+    loop {
+        acceptor.ready().await;
+        let accepted = listener.accept().await?;
+        let admission = acceptor.admit().await;
 
-            let handler = routes_handler.clone();
+        match accepted {
+            Accepted::Tcp(stream) => acceptor.spawn(stream, admission),
+            #[cfg(unix)]
+            Accepted::Unix(stream) => acceptor.spawn(stream, admission),
+        }
+    }
+}
 
+/// Serves the connections accepted by a listener.
+#[derive(Clone)]
+struct Acceptor {
+    handler: RoutesHandler,
+    options: Arc<Options>,
+    #[cfg(feature = "tls")]
+    tls: Option<TlsAcceptor>,
+}
+
+impl Acceptor {
+    /// Waits until the next connection can be served, when the connections are limited
+    /// and the server waits for slots to free up.
+    async fn ready(&self) {
+        if let Some(limits) = &self.options.limits {
+            limits.connection_ready().await;
+        }
+    }
+
+    /// Decides whether an accepted connection is served.
+    async fn admit(&self) -> Admission {
+        let Some(limits) = &self.options.limits else {
+            return Admission::Admitted(None);
+        };
+
+        match limits.start_connection().await {
+            Some(slot) => Admission::Admitted(Some(slot)),
+            None => match limits.start_rejection() {
+                Some(slot) => Admission::Rejected(slot),
+                None => Admission::Dropped,
+            },
+        }
+    }
+
+    /// Serves `stream` on a new task, after the TLS handshake when TLS is enabled.
+    fn spawn<S: Connection + 'static>(&self, stream: S, admission: Admission) {
+        if let Admission::Dropped = admission {
+            return;
+        }
+
+        let handler = self.handler.clone();
+        let options = self.options.clone();
+
+        #[cfg(feature = "tls")]
+        if let Some(tls) = self.tls.clone() {
             tokio::spawn(async move {
-                let mut response = Response::new();
-
-                match Request::from_reader(&mut stream).await {
-                    Ok(request) => {
-                        let result = (handler)(&request, &mut response);
-                        response.set_result(result);
-                    }
-                    Err(_) => {
-                        response.set_status_code(StatusCode::BadRequest);
-                    }
+                match tokio::time::timeout(TLS_HANDSHAKE_TIMEOUT, tls.accept(stream)).await {
+                    Ok(Ok(stream)) => admit(stream, handler, options, admission).await,
+                    Ok(Err(err)) => eprintln!("TLS handshake failed: {}", err),
+                    Err(_) => eprintln!("TLS handshake timed out"),
                 }
+            });
+            return;
+        }
 
-                response.set_default_headers();
+        tokio::spawn(admit(stream, handler, options, admission));
+    }
+}
+
+/// Whether a connection is served, holding its slot when the connections are limited.
+enum Admission {
+    Admitted(Option<OwnedSemaphorePermit>),
+    /// Answered with 503 Service Unavailable, holding a rejection slot.
+    Rejected(OwnedSemaphorePermit),
+    /// Closed without a response, as too many connections are being rejected.
+    Dropped,
+}
+
+async fn admit<S: Connection + 'static>(
+    stream: S,
+    handler: RoutesHandler,
+    options: Arc<Options>,
+    admission: Admission,
+) {
+    match admission {
+        Admission::Admitted(_slot) => handle_connection(stream, handler, options).await,
+        Admission::Rejected(_slot) => reject_connection(stream, &options).await,
+        Admission::Dropped => {}
+    }
+}
+
+/// Answers a connection over the connection limit with 503 Service Unavailable. The
+/// request is read first, so that the client gets the response rather than a reset.
+async fn reject_connection<S: Connection>(mut stream: S, options: &Options) {
+    let Some(limits) = &options.limits else {
+        return;
+    };
+
+    let read = tokio::time::timeout(
+        REJECTED_READ_TIMEOUT,
+        Request::from_reader_with_leftover(&mut stream),
+    )
+    .await;
+    let request = match read {
+        Ok(Ok((request, _))) => Some(request),
+        _ => None,
+    };
+
+    let mut response = Response::new();
+    response.set_result(limits.rejection().into());
+    handle_error(request.as_ref(), options, &mut response);
+    response.set_default_headers();
+
+    if let Err(err) = response.write_response(&mut stream).await {
+        eprintln!("Failed to write response: {}", err);
+        return;
+    }
+    if let Err(err) = stream.flush().await {
+        eprintln!("Failed to flush stream: {}", err);
+    }
+}
+
+async fn handle_connection<S: Connection + 'static>(
+    mut stream: S,
+    handler: RoutesHandler,
+    options: Arc<Options>,
+) {
+    let _open = options.shutdown.track();
+    let _connection = options
+        .metrics
+        .as_ref()
+        .map(|metrics| metrics.open_connection());
+
+    let peer_addr = stream.peer_addr();
+    let describe: Describe = {
+        let describe = stream.describe();
+        Arc::new(move |request: &mut Request| {
+            request.set_peer_addr(peer_addr);
+            describe(request);
+        })
+    };
 
-                if let Err(err) = response.write_response(&mut stream).await {
-                    eprintln!("Failed to write response: {}", err);
+    let deadline = tokio::time::Instant::now() + options.read_timeout;
+    observe::connection(peer_addr, async move {
+        // Connections that did not start a request are closed once the server shuts down
+        let mut prefix = vec![0; 1024];
+        let read = tokio::select! {
+            read = tokio::time::timeout_at(deadline, stream.read(&mut prefix)) => read,
+            _ = options.shutdown.triggered() => return,
+        };
+        match read {
+            Ok(Ok(0)) | Err(_) => return,
+            Ok(Ok(read)) => prefix.truncate(read),
+            Ok(Err(err)) => {
+                eprintln!("Failed to read from stream: {}", err);
+                return;
+            }
+        }
+        let stream = Rewind::new(prefix, stream);
+
+        #[cfg(feature = "http2")]
+        let stream = match tokio::time::timeout_at(deadline, http2::detect(stream)).await {
+            Ok(Ok(http2::Protocol::Http2(stream))) => {
+                if let Err(err) = http2::serve_connection(stream, handler, options, describe).await
+                {
+                    eprintln!("HTTP/2 connection failed: {}", err);
                 }
+                return;
+            }
+            Ok(Ok(http2::Protocol::Http1(stream))) => stream,
+            Ok(Err(err)) => {
+                eprintln!("Failed to read from stream: {}", err);
+                return;
+            }
+            Err(_) => return,
+        };
+
+        serve_http1(stream, handler, options, describe, deadline).await;
+    })
+    .await;
+}
+
+async fn serve_http1<S: Connection + 'static>(
+    mut stream: S,
+    handler: RoutesHandler,
+    options: Arc<Options>,
+    describe: Describe,
+    deadline: tokio::time::Instant,
+) {
+    let start = Instant::now();
+    let mut response = Response::new();
+    let mut leftover = Vec::new();
+    let mut observation = None;
+
+    let read =
+        tokio::time::timeout_at(deadline, Request::from_reader_with_leftover(&mut stream)).await;
+    // Clients too slow to send their request are not worth answering
+    let Ok(read) = read else {
+        return;
+    };
+
+    match read {
+        Ok((mut request, rest)) => {
+            #[cfg(feature = "http2")]
+            if let Some(frames) = http2::upgrade_frames(&request) {
+                http2::serve_upgrade(stream, rest, frames, handler, options, describe).await;
+                return;
+            }
+
+            leftover = rest;
+            describe(&mut request);
+            let mut started = Observation::start(&options, &mut request, start);
+            started
+                .instrument(respond(&mut request, &handler, &options, &mut response))
+                .await;
+            started.set_route(request.route());
+            observation = Some(started);
+        }
+        Err(_) => {
+            if let Some(metrics) = &options.metrics {
+                metrics.add_parse_error();
+            }
+            response.set_result(BadRequestError::with_message("Malformed request").into());
+            handle_error(None, &options, &mut response);
+        }
+    }
+
+    response.set_default_headers();
+
+    let written = response.write_response(&mut stream).await;
+    if let Err(err) = &written {
+        eprintln!("Failed to write response: {}", err);
+    }
+
+    if let Some(observation) = &mut observation {
+        observation.set_status(response.status_code());
+        observation.add_bytes(written.unwrap_or(0));
+    }
+
+    // Ensure all data is flushed to the stream
+    if let Err(err) = stream.flush().await {
+        eprintln!("Failed to flush stream: {}", err);
+        return;
+    }
+
+    // The request is complete once the connection is handed over
+    drop(observation);
+
+    if let Some(on_upgrade) = response.take_upgrade() {
+        on_upgrade(Upgraded::new(Box::new(stream), leftover)).await;
+    }
+}
+
+pub(super) async fn respond(
+    request: &mut Request,
+    handler: &RoutesHandler,
+    options: &Options,
+    response: &mut Response,
+) {
+    echo_request_id(request, options, response);
+
+    if let Some(metrics) = &options.metrics
+        && metrics.is_endpoint(request)
+    {
+        request.set_route(metrics.path());
+        response.set_result(metrics.response());
+        return;
+    }
+
+    let _request_slot = match &options.limits {
+        Some(limits) => match limits.start_request().await {
+            Ok(slot) => Some(slot),
+            Err(error) => {
+                response.set_result(error.into());
+                handle_error(Some(request), options, response);
+                return;
+            }
+        },
+        None => None,
+    };
+
+    if let Err(error) = request.decompress_body(options.max_decompressed_size).await {
+        response.set_result(error);
+        handle_error(Some(request), options, response);
+        return;
+    }
+
+    let result = match panic::catch(|| (handler)(request, response)) {
+        Ok(result) => result,
+        Err(caught) => {
+            let report = PanicReport::new(caught, request);
+            eprintln!("{}", report);
+            if let Some(hook) = &options.panic_hook {
+                hook(&report);
+            }
+
+            // Discard the headers the handler set before panicking
+            *response = Response::new();
+            echo_request_id(request, options, response);
+            InternalServerError::new().into()
+        }
+    };
+    response.set_result(result);
 
-                // Ensure all data is flushed to the stream
-                if let Err(err) = stream.flush().await {
-                    eprintln!("Failed to flush stream: {}", err);
+    // The connection is about to be handed over, so there is no body to validate or encode
+    if response.has_upgrade() {
+        return;
+    }
+
+    if let Some(mode) = options.etag {
+        response.apply_etag(mode);
+    }
+    response.apply_conditional(request);
+    // A 304 Not Modified only keeps the validators of the response
+    echo_request_id(request, options, response);
+    response.apply_range(request);
+
+    handle_error(Some(request), options, response);
+
+    if let Some(compression) = &options.compression {
+        response.apply_compression(request, compression).await;
+    }
+}
+
+/// Replaces an error response with the one rendered by the error handler, if any.
+pub(super) fn handle_error(request: Option<&Request>, options: &Options, response: &mut Response) {
+    let (Some(handler), Some(error)) = (&options.error_handler, response.take_error()) else {
+        return;
+    };
+
+    let result = handler(&error, request);
+
+    *response = Response::new();
+    if let Some(request) = request {
+        echo_request_id(request, options, response);
+    }
+    response.set_result(result);
+
+    // Headers such as Content-Range and Retry-After belong to the error, however it is
+    // rendered
+    if response.status_code() == HttpError::status_code(&error) {
+        let headers = response.headers_mut();
+        for (key, value) in HttpError::headers(&error) {
+            if !headers.contains(key) {
+                headers.set(key, &value);
+            }
+        }
+    }
+}
+
+fn echo_request_id(request: &Request, options: &Options, response: &mut Response) {
+    if let Some(config) = &options.request_id
+        && let Some(request_id) = request.request_id()
+    {
+        response.set_request_id(config.header(), request_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use std::sync::Mutex;
+
+    use crate::responses::{HttpResponse, TunnelResponse};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_connect_tunnel() {
+        let mut router = Router::new();
+        router.connect(Arc::new(|req, _| {
+            assert_eq!(req.path(), "example.com:443");
+
+            TunnelResponse::new(|upgraded| async move {
+                let (mut reader, mut writer) = tokio::io::split(upgraded);
+                let _ = tokio::io::copy(&mut reader, &mut writer).await;
+            })
+            .into()
+        }));
+        let routes = router.build();
+
+        let (mut client, server) = tokio::io::duplex(1024);
+        tokio::spawn(handle_connection(
+            server,
+            routes,
+            Arc::new(Options::default()),
+        ));
+
+        // Bytes sent right behind the request must reach the tunnel
+        client
+            .write_all(b"CONNECT example.com:443 HTTP/1.1\r\nHost: example.com:443\r\n\r\nping")
+            .await
+            .unwrap();
+
+        let expected = b"HTTP/1.1 200 OK\r\n\r\nping";
+        let mut buffer = vec![0; expected.len()];
+        client.read_exact(&mut buffer).await.unwrap();
+        assert_eq!(buffer, expected);
+
+        client.write_all(b"pong").await.unwrap();
+        let mut buffer = [0; 4];
+        client.read_exact(&mut buffer).await.unwrap();
+        assert_eq!(&buffer, b"pong");
+    }
+
+    #[tokio::test]
+    async fn test_request_id_in_error() {
+        let options = Options {
+            request_id: Some(RequestIdConfig::new()),
+            ..Options::default()
+        };
+
+        let (mut client, server) = tokio::io::duplex(1024);
+        tokio::spawn(handle_connection(
+            server,
+            Router::new().build(),
+            Arc::new(options),
+        ));
+
+        client
+            .write_all(b"GET /missing HTTP/1.1\r\nX-Request-Id: req-42\r\n\r\n")
+            .await
+            .unwrap();
+        let mut output = String::new();
+        client.read_to_string(&mut output).await.unwrap();
+
+        assert!(output.contains("X-Request-Id: req-42\r\n"));
+        let body = output.split("\r\n\r\n").nth(1).unwrap();
+        let body: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(body["request_id"], "req-42");
+        assert_eq!(body["status_code"], 404);
+    }
+
+    #[tokio::test]
+    async fn test_request_id_when_not_modified() {
+        let mut router = Router::new();
+        router.get(
+            "/",
+            Arc::new(|_, res: &mut Response| {
+                res.headers_mut().set("ETag", "\"v1\"");
+                crate::responses::OkResponse::from("cached").into()
+            }),
+        );
+        let options = Options {
+            request_id: Some(RequestIdConfig::new()),
+            ..Options::default()
+        };
+
+        let (mut client, server) = tokio::io::duplex(1024);
+        tokio::spawn(handle_connection(server, router.build(), Arc::new(options)));
+
+        client
+            .write_all(b"GET / HTTP/1.1\r\nIf-None-Match: \"v1\"\r\nX-Request-Id: req-42\r\n\r\n")
+            .await
+            .unwrap();
+        let mut output = String::new();
+        client.read_to_string(&mut output).await.unwrap();
+
+        assert!(output.starts_with("HTTP/1.1 304 Not Modified\r\n"));
+        assert!(output.contains("X-Request-Id: req-42\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_handler_panic() {
+        let mut router = Router::new();
+        router.get(
+            "/boom",
+            Arc::new(|_, res: &mut Response| -> Box<dyn HttpResponse> {
+                res.headers_mut().set("X-Partial", "yes");
+                panic!("boom")
+            }),
+        );
+
+        let reports = Arc::new(Mutex::new(Vec::new()));
+        let options = Options {
+            panic_hook: Some(Arc::new({
+                let reports = reports.clone();
+                move |report: &PanicReport| {
+                    reports.lock().unwrap().push(format!(
+                        "{} {}",
+                        report.target(),
+                        report.message()
+                    ));
                 }
+            })),
+            ..Options::default()
+        };
+
+        let (mut client, server) = tokio::io::duplex(1024);
+        tokio::spawn(handle_connection(server, router.build(), Arc::new(options)));
+
+        client
+            .write_all(b"GET /boom?x=1 HTTP/1.1\r\n\r\n")
+            .await
+            .unwrap();
+        let mut output = String::new();
+        client.read_to_string(&mut output).await.unwrap();
+
+        assert!(output.starts_with("HTTP/1.1 500 Internal Server Error\r\n"));
+        assert!(!output.contains("X-Partial"));
+        let body = output.split("\r\n\r\n").nth(1).unwrap();
+        let body: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(body["status_code"], 500);
+
+        assert_eq!(*reports.lock().unwrap(), ["/boom?x=1 boom"]);
+    }
+
+    #[tokio::test]
+    async fn test_error_handler() {
+        let options = Arc::new(Options {
+            error_handler: Some(Arc::new(
+                |error: &dyn HttpError, request: Option<&Request>| {
+                    let path = request.map(|request| request.path()).unwrap_or("-");
+                    crate::responses::ProblemDetails::from_error(error)
+                        .with_instance(path)
+                        .into()
+                },
+            )),
+            ..Options::default()
+        });
+
+        for (raw, status, instance) in [
+            (&b"GET /missing HTTP/1.1\r\n\r\n"[..], 404, "/missing"),
+            (&b"NOT A REQUEST\r\n\r\n"[..], 400, "-"),
+        ] {
+            let (mut client, server) = tokio::io::duplex(1024);
+            tokio::spawn(handle_connection(
+                server,
+                Router::new().build(),
+                options.clone(),
+            ));
+
+            client.write_all(raw).await.unwrap();
+            let mut output = String::new();
+            client.read_to_string(&mut output).await.unwrap();
+
+            assert!(output.contains("Content-Type: application/problem+json\r\n"));
+            let body = output.split("\r\n\r\n").nth(1).unwrap();
+            let body: serde_json::Value = serde_json::from_str(body).unwrap();
+            assert_eq!(body["status"], status);
+            assert_eq!(body["instance"], instance);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_error_handler_keeps_error_headers() {
+        let mut router = Router::new();
+        router.get(
+            "/file",
+            Arc::new(|_, _| crate::responses::OkResponse::from("hello").into()),
+        );
+        let routes = router.build();
+
+        let limits = crate::limits::Limits::new()
+            .with_max_requests(0)
+            .with_overload(crate::limits::Overload::Reject {
+                retry_after: Duration::from_secs(2),
+            });
+        limits.build();
+
+        let error_handler: ErrorHandler = Arc::new(|error: &dyn HttpError, _| {
+            crate::responses::ProblemDetails::new(error.status_code()).into()
+        });
+
+        for (limits, expected) in [
+            (None, "Content-Range: bytes */7\r\n"),
+            (Some(limits), "Retry-After: 2\r\n"),
+        ] {
+            let options = Arc::new(Options {
+                error_handler: Some(error_handler.clone()),
+                limits,
+                ..Options::default()
             });
+
+            let (mut client, server) = tokio::io::duplex(1024);
+            tokio::spawn(handle_connection(server, routes.clone(), options));
+
+            client
+                .write_all(b"GET /file HTTP/1.1\r\nRange: bytes=100-\r\n\r\n")
+                .await
+                .unwrap();
+            let mut output = String::new();
+            client.read_to_string(&mut output).await.unwrap();
+
+            assert!(output.contains("Content-Type: application/problem+json\r\n"));
+            assert!(
+                output.contains(expected),
+                "missing {:?} in {}",
+                expected,
+                output
+            );
         }
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_listeners_and_graceful_shutdown() {
+        let mut router = Router::new();
+        router.get(
+            "/slow",
+            Arc::new(|_, _| {
+                std::thread::sleep(Duration::from_millis(200));
+                crate::responses::OkResponse::from("done").into()
+            }),
+        );
+
+        let server = Server::new("127.0.0.1:0", router)
+            .with_listener(Listener::tcp("127.0.0.1:0"))
+            .bind()
+            .await
+            .unwrap();
+        let addrs = server
+            .local_addrs()
+            .iter()
+            .map(|addr| addr.tcp().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(addrs.len(), 2);
+        assert!(addrs.iter().all(|addr| addr.port() != 0));
+
+        let (stop, stopped) = tokio::sync::oneshot::channel();
+        let serving = tokio::spawn(server.serve_with_shutdown(async {
+            let _ = stopped.await;
+        }));
+
+        let mut client = tokio::net::TcpStream::connect(addrs[1]).await.unwrap();
+        client
+            .write_all(b"GET /slow HTTP/1.1\r\n\r\n")
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        stop.send(()).unwrap();
+
+        // The request in progress is completed before the server returns
+        let mut output = String::new();
+        client.read_to_string(&mut output).await.unwrap();
+        assert!(output.starts_with("HTTP/1.1 200 OK\r\n"));
+        serving.await.unwrap().unwrap();
+
+        for addr in addrs {
+            assert!(tokio::net::TcpStream::connect(addr).await.is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_closes_idle_connections() {
+        let server = Server::new("127.0.0.1:0", Router::new())
+            .bind()
+            .await
+            .unwrap();
+        let addr = server.local_addrs()[0].tcp().unwrap();
+
+        let (stop, stopped) = tokio::sync::oneshot::channel();
+        let serving = tokio::spawn(server.serve_with_shutdown(async {
+            let _ = stopped.await;
+        }));
+
+        let mut idle = tokio::net::TcpStream::connect(addr).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        stop.send(()).unwrap();
+
+        // The server does not wait for a request that was never started
+        tokio::time::timeout(Duration::from_secs(5), serving)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+        let mut output = Vec::new();
+        idle.read_to_end(&mut output).await.unwrap();
+        assert!(output.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_writes_access_log() {
+        #[derive(Clone, Default)]
+        struct Lines(Arc<Mutex<Vec<u8>>>);
+
+        impl std::io::Write for Lines {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let lines = Lines::default();
+        let server = Server::new("127.0.0.1:0", Router::new())
+            .with_access_log(AccessLog::writer(lines.clone()))
+            .bind()
+            .await
+            .unwrap();
+        let addr = server.local_addrs()[0].tcp().unwrap();
+
+        let (stop, stopped) = tokio::sync::oneshot::channel();
+        let serving = tokio::spawn(server.serve_with_shutdown(async {
+            let _ = stopped.await;
+        }));
+
+        let mut client = tokio::net::TcpStream::connect(addr).await.unwrap();
+        client.write_all(b"GET / HTTP/1.1\r\n\r\n").await.unwrap();
+        client.read_to_end(&mut Vec::new()).await.unwrap();
+        stop.send(()).unwrap();
+        serving.await.unwrap().unwrap();
+
+        let output = String::from_utf8(lines.0.lock().unwrap().clone()).unwrap();
+        assert!(output.contains("\"GET / HTTP/1.1\" 404"));
+    }
+
+    #[tokio::test]
+    async fn test_connection_limit() {
+        let limits = crate::limits::Limits::new()
+            .with_max_connections(1)
+            .with_overload(crate::limits::Overload::Reject {
+                retry_after: Duration::from_secs(2),
+            });
+        let server = Server::new("127.0.0.1:0", Router::new())
+            .with_limits(limits.clone())
+            .bind()
+            .await
+            .unwrap();
+        let addr = server.local_addrs()[0].tcp().unwrap();
+        tokio::spawn(server.serve());
+
+        // The first connection holds the only slot until it sends its request
+        let mut first = tokio::net::TcpStream::connect(addr).await.unwrap();
+        while limits.active_connections() == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        let mut second = tokio::net::TcpStream::connect(addr).await.unwrap();
+        second.write_all(b"GET / HTTP/1.1\r\n\r\n").await.unwrap();
+        let mut output = String::new();
+        second.read_to_string(&mut output).await.unwrap();
+        assert!(output.starts_with("HTTP/1.1 503 Service Unavailable\r\n"));
+        assert!(output.contains("Retry-After: 2\r\n"));
+
+        first.write_all(b"GET / HTTP/1.1\r\n\r\n").await.unwrap();
+        let mut output = String::new();
+        first.read_to_string(&mut output).await.unwrap();
+        assert!(output.starts_with("HTTP/1.1 404 Not Found\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_idle_connections_free_their_slot() {
+        let limits = crate::limits::Limits::new().with_max_connections(1);
+        let server = Server::new("127.0.0.1:0", Router::new())
+            .with_limits(limits.clone())
+            .with_read_timeout(Duration::from_millis(200))
+            .bind()
+            .await
+            .unwrap();
+        let addr = server.local_addrs()[0].tcp().unwrap();
+        tokio::spawn(server.serve());
+
+        // A client that never sends its request is closed once the read times out
+        let mut silent = tokio::net::TcpStream::connect(addr).await.unwrap();
+        while limits.active_connections() == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        // A client that closes its side before the end of its request is answered
+        let mut half_closed = tokio::net::TcpStream::connect(addr).await.unwrap();
+        half_closed.write_all(b"GET / HTTP/1.1\r\n").await.unwrap();
+        half_closed.shutdown().await.unwrap();
+
+        let mut output = Vec::new();
+        silent.read_to_end(&mut output).await.unwrap();
+        assert!(output.is_empty());
+
+        let mut output = String::new();
+        half_closed.read_to_string(&mut output).await.unwrap();
+        assert!(output.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+
+        let mut last = tokio::net::TcpStream::connect(addr).await.unwrap();
+        last.write_all(b"GET / HTTP/1.1\r\n\r\n").await.unwrap();
+        let mut output = String::new();
+        tokio::time::timeout(Duration::from_secs(5), last.read_to_string(&mut output))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(output.starts_with("HTTP/1.1 404 Not Found\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_rejection_flood() {
+        let limits = crate::limits::Limits::new()
+            .with_max_connections(1)
+            .with_overload(crate::limits::Overload::Reject {
+                retry_after: Duration::from_secs(2),
+            });
+        let server = Server::new("127.0.0.1:0", Router::new())
+            .with_limits(limits.clone())
+            .bind()
+            .await
+            .unwrap();
+        let addr = server.local_addrs()[0].tcp().unwrap();
+        tokio::spawn(server.serve());
+
+        let _first = tokio::net::TcpStream::connect(addr).await.unwrap();
+        while limits.active_connections() == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        // Connections that never send a request keep their rejection slot until timing out
+        let mut flood = Vec::new();
+        for _ in 0..100 {
+            flood.push(tokio::net::TcpStream::connect(addr).await.unwrap());
+        }
+
+        // Those over the rejection slots are closed right away rather than waiting
+        let mut last = flood.pop().unwrap();
+        let mut buffer = [0; 1];
+        let read = tokio::time::timeout(Duration::from_secs(1), last.read(&mut buffer)).await;
+        assert!(matches!(read, Ok(Ok(0)) | Ok(Err(_))));
+
+        assert_eq!(limits.active_rejections(), 64);
+        assert_eq!(limits.active_connections(), 1);
+    }
 }
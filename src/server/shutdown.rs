@@ -0,0 +1,60 @@
+use tokio::sync::watch;
+
+/// Tells the connections of a server to finish, and tracks how many are still open.
+#[derive(Clone)]
+pub(crate) struct Shutdown {
+    triggered: watch::Sender<bool>,
+    open: watch::Sender<usize>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Shutdown {
+            triggered: watch::Sender::new(false),
+            open: watch::Sender::new(0),
+        }
+    }
+}
+
+impl Shutdown {
+    /// Counts a connection as open until the returned guard is dropped.
+    pub(crate) fn track(&self) -> OpenConnection {
+        self.open.send_modify(|open| *open += 1);
+        OpenConnection {
+            open: self.open.clone(),
+        }
+    }
+
+    pub(crate) fn trigger(&self) {
+        self.triggered.send_replace(true);
+    }
+
+    /// Resolves once the server starts shutting down.
+    pub(crate) async fn triggered(&self) {
+        let _ = self
+            .triggered
+            .subscribe()
+            .wait_for(|triggered| *triggered)
+            .await;
+    }
+
+    /// Resolves once every connection is closed.
+    pub(crate) async fn closed(&self) {
+        let _ = self.open.subscribe().wait_for(|open| *open == 0).await;
+    }
+
+    pub(crate) fn open_connections(&self) -> usize {
+        *self.open.borrow()
+    }
+}
+
+/// An open connection, counted by [`Shutdown`].
+pub(crate) struct OpenConnection {
+    open: watch::Sender<usize>,
+}
+
+impl Drop for OpenConnection {
+    fn drop(&mut self) {
+        self.open.send_modify(|open| *open -= 1);
+    }
+}
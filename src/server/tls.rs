@@ -0,0 +1,717 @@
+//! HTTPS support, available with the `tls` feature.
+
+use std::{
+    io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use tokio_rustls::{
+    TlsAcceptor,
+    rustls::{
+        RootCertStore, ServerConfig, ServerConnection,
+        crypto::ring,
+        pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject},
+        server::{
+            ClientHello, ResolvesServerCert, WebPkiClientVerifier, danger::ClientCertVerifier,
+        },
+        sign::CertifiedKey,
+    },
+    server::TlsStream,
+};
+use x509_parser::{certificate::X509Certificate, extensions::GeneralName, prelude::FromDer};
+
+use crate::{
+    Request,
+    server::{
+        connection::{Connection, Describe},
+        shutdown::Shutdown,
+    },
+};
+
+const ALPN_HTTP_1_1: &[u8] = b"http/1.1";
+
+fn invalid_data<E: std::fmt::Display>(path: &Path, error: E) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("{}: {}", path.display(), error),
+    )
+}
+
+fn load_certified_key(cert_path: &Path, key_path: &Path) -> io::Result<Arc<CertifiedKey>> {
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|error| invalid_data(cert_path, error))?;
+
+    if certs.is_empty() {
+        return Err(invalid_data(cert_path, "no certificates found"));
+    }
+
+    let key =
+        PrivateKeyDer::from_pem_file(key_path).map_err(|error| invalid_data(key_path, error))?;
+    let signing_key =
+        ring::sign::any_supported_type(&key).map_err(|error| invalid_data(key_path, error))?;
+
+    Ok(Arc::new(CertifiedKey::new(certs, signing_key)))
+}
+
+fn load_roots(ca_path: &Path) -> io::Result<RootCertStore> {
+    let mut roots = RootCertStore::empty();
+
+    for cert in
+        CertificateDer::pem_file_iter(ca_path).map_err(|error| invalid_data(ca_path, error))?
+    {
+        let cert = cert.map_err(|error| invalid_data(ca_path, error))?;
+        roots
+            .add(cert)
+            .map_err(|error| invalid_data(ca_path, error))?;
+    }
+
+    if roots.is_empty() {
+        return Err(invalid_data(ca_path, "no certificates found"));
+    }
+
+    Ok(roots)
+}
+
+fn server_config(
+    resolver: Arc<CertResolver>,
+    client_verifier: Option<Arc<dyn ClientCertVerifier>>,
+) -> io::Result<ServerConfig> {
+    let builder = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(io::Error::other)?;
+
+    let builder = match client_verifier {
+        Some(verifier) => builder.with_client_cert_verifier(verifier),
+        None => builder.with_no_client_auth(),
+    };
+
+    let mut config = builder.with_cert_resolver(resolver);
+    config.alpn_protocols = vec![
+        #[cfg(feature = "http2")]
+        crate::server::http2::ALPN_H2.to_vec(),
+        ALPN_HTTP_1_1.to_vec(),
+    ];
+    Ok(config)
+}
+
+fn modified(cert_path: &Path, key_path: &Path) -> Option<SystemTime> {
+    let cert = std::fs::metadata(cert_path)
+        .and_then(|m| m.modified())
+        .ok()?;
+    let key = std::fs::metadata(key_path)
+        .and_then(|m| m.modified())
+        .ok()?;
+    Some(cert.max(key))
+}
+
+/// A certificate loaded from disk, served for `server_name` or by default when `None`.
+#[derive(Debug)]
+struct CertEntry {
+    server_name: Option<String>,
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    modified: Option<SystemTime>,
+    key: Arc<CertifiedKey>,
+}
+
+impl CertEntry {
+    fn load(server_name: Option<String>, cert_path: &Path, key_path: &Path) -> io::Result<Self> {
+        Ok(CertEntry {
+            server_name,
+            cert_path: cert_path.to_path_buf(),
+            key_path: key_path.to_path_buf(),
+            modified: modified(cert_path, key_path),
+            key: load_certified_key(cert_path, key_path)?,
+        })
+    }
+
+    fn serves(&self, server_name: &str) -> bool {
+        match &self.server_name {
+            Some(name) => match name.strip_prefix("*.") {
+                Some(suffix) => server_name
+                    .split_once('.')
+                    .is_some_and(|(_, rest)| rest == suffix),
+                None => name == server_name,
+            },
+            None => false,
+        }
+    }
+}
+
+/// Picks a certificate from the SNI server name sent by the client.
+#[derive(Debug)]
+struct CertResolver {
+    entries: RwLock<Vec<CertEntry>>,
+}
+
+impl CertResolver {
+    /// Reloads every certificate, or only those whose files changed on disk. Entries that
+    /// fail to load keep their previous certificate.
+    ///
+    /// The files are read before taking the write lock, so that handshakes are not held up
+    /// while they load.
+    fn reload(&self, only_changed: bool) -> io::Result<()> {
+        let current = self
+            .entries
+            .read()
+            .unwrap()
+            .iter()
+            .map(|entry| {
+                (
+                    entry.server_name.clone(),
+                    entry.cert_path.clone(),
+                    entry.key_path.clone(),
+                    entry.modified,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let mut result = Ok(());
+        let mut loaded = Vec::new();
+
+        // Entries are only ever added, so their indices stay valid
+        for (index, (server_name, cert_path, key_path, last_modified)) in
+            current.into_iter().enumerate()
+        {
+            if only_changed && modified(&cert_path, &key_path) == last_modified {
+                continue;
+            }
+
+            match CertEntry::load(server_name, &cert_path, &key_path) {
+                Ok(entry) => loaded.push((index, entry)),
+                Err(error) => result = result.and(Err(error)),
+            }
+        }
+
+        let mut entries = self.entries.write().unwrap();
+        for (index, entry) in loaded {
+            entries[index] = entry;
+        }
+
+        result
+    }
+}
+
+impl ResolvesServerCert for CertResolver {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        let entries = self.entries.read().unwrap();
+        let server_name = client_hello.server_name().map(str::to_ascii_lowercase);
+
+        server_name
+            .and_then(|name| entries.iter().find(|entry| entry.serves(&name)))
+            .or_else(|| entries.iter().find(|entry| entry.server_name.is_none()))
+            .map(|entry| entry.key.clone())
+    }
+}
+
+/// Whether clients must present a certificate, see [`TlsConfig::with_client_auth`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ClientAuth {
+    /// Clients may connect without a certificate, but one that is sent must be valid.
+    Optional,
+    /// Connections without a valid client certificate are refused.
+    Required,
+}
+
+/// TLS settings for serving HTTPS, passed to [`Server::with_tls`](crate::Server::with_tls).
+///
+/// Certificates loaded from PEM files can be selected by SNI server name and reloaded
+/// from disk while the server runs, either by calling [`TlsConfig::reload`] or
+/// periodically with [`TlsConfig::with_reload_interval`]. Clones share the same
+/// certificates, so a clone can be kept around to trigger reloads.
+///
+/// The server advertises `http/1.1` through ALPN, preceded by `h2` with the `http2`
+/// feature, and the negotiated parameters of each connection are available to handlers
+/// as [`TlsInfo`] in the request extensions.
+#[derive(Clone)]
+pub struct TlsConfig {
+    config: Arc<ServerConfig>,
+    resolver: Option<Arc<CertResolver>>,
+    reload_interval: Option<Duration>,
+}
+
+impl TlsConfig {
+    /// Creates a config serving the certificate chain and private key in the given PEM
+    /// files. The certificate is used when no other one matches the SNI server name.
+    pub fn from_pem_files<P: AsRef<Path>>(cert_path: P, key_path: P) -> io::Result<Self> {
+        let entry = CertEntry::load(None, cert_path.as_ref(), key_path.as_ref())?;
+        let resolver = Arc::new(CertResolver {
+            entries: RwLock::new(vec![entry]),
+        });
+
+        Ok(TlsConfig {
+            config: Arc::new(server_config(resolver.clone(), None)?),
+            resolver: Some(resolver),
+            reload_interval: None,
+        })
+    }
+
+    /// Creates a config from a rustls `ServerConfig`, used as is. Its certificates cannot
+    /// be changed through this type.
+    pub fn from_server_config(config: ServerConfig) -> Self {
+        TlsConfig {
+            config: Arc::new(config),
+            resolver: None,
+            reload_interval: None,
+        }
+    }
+
+    /// Serves the certificate in the given PEM files to clients asking for `server_name`
+    /// through SNI. Names such as `*.example.com` match any single subdomain.
+    pub fn with_server_name<P: AsRef<Path>>(
+        self,
+        server_name: &str,
+        cert_path: P,
+        key_path: P,
+    ) -> io::Result<Self> {
+        let resolver = self.resolver()?;
+
+        let entry = CertEntry::load(
+            Some(server_name.to_ascii_lowercase()),
+            cert_path.as_ref(),
+            key_path.as_ref(),
+        )?;
+        resolver.entries.write().unwrap().push(entry);
+
+        Ok(self)
+    }
+
+    /// Verifies client certificates against the CA certificates in the given PEM file.
+    ///
+    /// The identity of a verified client is available to handlers through
+    /// [`Request::peer_identity`](crate::Request::peer_identity).
+    pub fn with_client_auth<P: AsRef<Path>>(
+        mut self,
+        ca_path: P,
+        mode: ClientAuth,
+    ) -> io::Result<Self> {
+        let resolver = self.resolver()?.clone();
+        let roots = Arc::new(load_roots(ca_path.as_ref())?);
+
+        let builder =
+            WebPkiClientVerifier::builder_with_provider(roots, Arc::new(ring::default_provider()));
+        let verifier = match mode {
+            ClientAuth::Optional => builder.allow_unauthenticated().build(),
+            ClientAuth::Required => builder.build(),
+        }
+        .map_err(io::Error::other)?;
+
+        self.config = Arc::new(server_config(resolver, Some(verifier))?);
+        Ok(self)
+    }
+
+    /// Checks the certificate files for changes every `interval` and reloads those that
+    /// were modified. Failures are logged and the previous certificate stays in use.
+    pub fn with_reload_interval(mut self, interval: Duration) -> Self {
+        self.reload_interval = Some(interval);
+        self
+    }
+
+    /// Reloads every certificate from disk. New connections use the reloaded certificates
+    /// while established ones are unaffected.
+    ///
+    /// If a certificate fails to load, the previous one stays in use and the error is
+    /// returned after the others have been reloaded.
+    pub fn reload(&self) -> io::Result<()> {
+        match &self.resolver {
+            Some(resolver) => resolver.reload(false),
+            None => Ok(()),
+        }
+    }
+
+    fn resolver(&self) -> io::Result<&Arc<CertResolver>> {
+        self.resolver.as_ref().ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "a custom ServerConfig cannot be changed",
+            )
+        })
+    }
+
+    pub(crate) fn acceptor(&self) -> TlsAcceptor {
+        TlsAcceptor::from(self.config.clone())
+    }
+
+    /// Starts the task reloading changed certificates, if an interval was set, until the
+    /// server shuts down.
+    pub(crate) fn watch(&self, shutdown: &Shutdown) {
+        let (Some(resolver), Some(interval)) = (&self.resolver, self.reload_interval) else {
+            return;
+        };
+
+        let resolver = resolver.clone();
+        let shutdown = shutdown.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            // The first tick completes immediately
+            ticker.tick().await;
+
+            let triggered = shutdown.triggered();
+            tokio::pin!(triggered);
+
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {}
+                    _ = &mut triggered => return,
+                }
+
+                let resolver = resolver.clone();
+                let reloaded = tokio::task::spawn_blocking(move || resolver.reload(true)).await;
+                if let Ok(Err(err)) = reloaded {
+                    eprintln!("Failed to reload TLS certificate: {}", err);
+                }
+            }
+        });
+    }
+}
+
+/// Details of the TLS session a request arrived on, stored in the request
+/// [`extensions`](crate::Request::extensions).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsInfo {
+    server_name: Option<String>,
+    alpn_protocol: Option<Vec<u8>>,
+    peer_identity: Option<PeerIdentity>,
+}
+
+impl TlsInfo {
+    fn new(connection: &ServerConnection) -> Self {
+        TlsInfo {
+            server_name: connection.server_name().map(str::to_string),
+            alpn_protocol: connection.alpn_protocol().map(<[u8]>::to_vec),
+            peer_identity: connection
+                .peer_certificates()
+                .and_then(|certs| certs.first())
+                .and_then(PeerIdentity::parse),
+        }
+    }
+
+    /// Returns the server name the client asked for through SNI.
+    pub fn server_name(&self) -> Option<&str> {
+        self.server_name.as_deref()
+    }
+
+    /// Returns the protocol negotiated through ALPN, such as `http/1.1`.
+    pub fn alpn_protocol(&self) -> Option<&[u8]> {
+        self.alpn_protocol.as_deref()
+    }
+
+    /// Returns the identity from the verified client certificate, if one was sent.
+    pub fn peer_identity(&self) -> Option<&PeerIdentity> {
+        self.peer_identity.as_ref()
+    }
+}
+
+/// The identity of a client that authenticated with a certificate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerIdentity {
+    subject: String,
+    subject_alt_names: Vec<String>,
+    fingerprint: String,
+}
+
+impl PeerIdentity {
+    fn parse(cert: &CertificateDer<'_>) -> Option<Self> {
+        let (_, parsed) = X509Certificate::from_der(cert).ok()?;
+
+        let subject_alt_names = match parsed.subject_alternative_name() {
+            Ok(Some(extension)) => extension
+                .value
+                .general_names
+                .iter()
+                .filter_map(|name| match name {
+                    GeneralName::DNSName(value)
+                    | GeneralName::RFC822Name(value)
+                    | GeneralName::URI(value) => Some(value.to_string()),
+                    GeneralName::IPAddress(bytes) => ip_address(bytes),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        let fingerprint = ::ring::digest::digest(&::ring::digest::SHA256, cert)
+            .as_ref()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+
+        Some(PeerIdentity {
+            subject: parsed.subject().to_string(),
+            subject_alt_names,
+            fingerprint,
+        })
+    }
+
+    /// Returns the distinguished name of the subject, such as `CN=billing,O=Example`.
+    pub fn subject(&self) -> &str {
+        &self.subject
+    }
+
+    /// Returns the DNS names, email addresses, URIs and IP addresses of the certificate.
+    pub fn subject_alt_names(&self) -> &[String] {
+        &self.subject_alt_names
+    }
+
+    /// Returns the SHA-256 fingerprint of the certificate, in lowercase hex.
+    pub fn fingerprint(&self) -> &str {
+        &self.fingerprint
+    }
+}
+
+fn ip_address(bytes: &[u8]) -> Option<String> {
+    let address = match bytes.len() {
+        4 => std::net::IpAddr::from(<[u8; 4]>::try_from(bytes).ok()?),
+        16 => std::net::IpAddr::from(<[u8; 16]>::try_from(bytes).ok()?),
+        _ => return None,
+    };
+
+    Some(address.to_string())
+}
+
+impl Request {
+    /// Returns the details of the TLS session the request arrived on.
+    pub fn tls_info(&self) -> Option<&TlsInfo> {
+        self.extensions().get::<TlsInfo>()
+    }
+
+    /// Returns the identity of the client, if it authenticated with a certificate.
+    pub fn peer_identity(&self) -> Option<&PeerIdentity> {
+        self.tls_info().and_then(TlsInfo::peer_identity)
+    }
+}
+
+impl<S: Connection> Connection for TlsStream<S> {
+    fn describe(&self) -> Describe {
+        let info = TlsInfo::new(self.get_ref().1);
+        Arc::new(move |request: &mut Request| {
+            request.extensions_mut().insert(info.clone());
+        })
+    }
+
+    fn peer_addr(&self) -> Option<SocketAddr> {
+        self.get_ref().0.peer_addr()
+    }
+
+    #[cfg(feature = "http2")]
+    fn alpn_protocol(&self) -> Option<&[u8]> {
+        self.get_ref().1.alpn_protocol()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rcgen::{BasicConstraints, CertificateParams, CertifiedIssuer, IsCa, Issuer, KeyPair};
+    use tokio_rustls::{
+        TlsConnector,
+        rustls::{ClientConfig, RootCertStore, pki_types::ServerName},
+    };
+
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("http-server-tls-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn certificate_authority() -> CertifiedIssuer<'static, KeyPair> {
+        let mut params = CertificateParams::new(Vec::new()).unwrap();
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        CertifiedIssuer::self_signed(params, KeyPair::generate().unwrap()).unwrap()
+    }
+
+    /// Writes a certificate for `name` signed by `ca`, returning the certificate in DER.
+    fn write_certificate(
+        ca: &Issuer<'_, KeyPair>,
+        dir: &Path,
+        file: &str,
+        name: &str,
+    ) -> CertificateDer<'static> {
+        let key = KeyPair::generate().unwrap();
+        let cert = CertificateParams::new(vec![name.to_string()])
+            .unwrap()
+            .signed_by(&key, ca)
+            .unwrap();
+
+        std::fs::write(dir.join(format!("{}.crt", file)), cert.pem()).unwrap();
+        std::fs::write(dir.join(format!("{}.key", file)), key.serialize_pem()).unwrap();
+        cert.der().clone()
+    }
+
+    /// Performs a handshake for `server_name`, optionally sending the client certificate
+    /// in the given files, and returns the certificate the server sent along with the
+    /// negotiated parameters.
+    async fn connect(
+        tls: &TlsConfig,
+        ca: &CertificateDer<'static>,
+        server_name: &str,
+        client_cert: Option<(PathBuf, PathBuf)>,
+    ) -> io::Result<(CertificateDer<'static>, TlsInfo)> {
+        let mut roots = RootCertStore::empty();
+        roots.add(ca.clone()).unwrap();
+
+        let builder = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_root_certificates(roots);
+        let mut config = match client_cert {
+            Some((cert_path, key_path)) => {
+                let certs = CertificateDer::pem_file_iter(cert_path)
+                    .unwrap()
+                    .collect::<Result<Vec<_>, _>>()
+                    .unwrap();
+                let key = PrivateKeyDer::from_pem_file(key_path).unwrap();
+                builder.with_client_auth_cert(certs, key).unwrap()
+            }
+            None => builder.with_no_client_auth(),
+        };
+        config.alpn_protocols = vec![ALPN_HTTP_1_1.to_vec()];
+
+        let (client, server) = tokio::io::duplex(16 * 1024);
+        let acceptor = tls.acceptor();
+        let server = tokio::spawn(async move {
+            let stream = acceptor.accept(server).await?;
+            Ok::<_, io::Error>(TlsInfo::new(stream.get_ref().1))
+        });
+
+        let name = ServerName::try_from(server_name.to_string()).unwrap();
+        let stream = TlsConnector::from(Arc::new(config))
+            .connect(name, client)
+            .await;
+        let info = server.await.unwrap()?;
+        let certificate = stream?.get_ref().1.peer_certificates().unwrap()[0].clone();
+
+        Ok((certificate, info))
+    }
+
+    async fn handshake(
+        tls: &TlsConfig,
+        ca: &CertificateDer<'static>,
+        server_name: &str,
+    ) -> (CertificateDer<'static>, TlsInfo) {
+        connect(tls, ca, server_name, None).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_server_name_selects_certificate() {
+        let dir = temp_dir("sni");
+        let ca = certificate_authority();
+        let default_cert = write_certificate(&ca, &dir, "default", "localhost");
+        let api_cert = write_certificate(&ca, &dir, "api", "api.example.test");
+
+        let tls = TlsConfig::from_pem_files(dir.join("default.crt"), dir.join("default.key"))
+            .unwrap()
+            .with_server_name("*.example.test", dir.join("api.crt"), dir.join("api.key"))
+            .unwrap();
+
+        let (certificate, info) = handshake(&tls, ca.der(), "api.example.test").await;
+        assert_eq!(certificate, api_cert);
+        assert_eq!(info.server_name(), Some("api.example.test"));
+        assert_eq!(info.alpn_protocol(), Some(ALPN_HTTP_1_1));
+
+        let (certificate, _) = handshake(&tls, ca.der(), "localhost").await;
+        assert_eq!(certificate, default_cert);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reload_picks_up_new_certificate() {
+        let dir = temp_dir("reload");
+        let ca = certificate_authority();
+        write_certificate(&ca, &dir, "server", "localhost");
+
+        let tls =
+            TlsConfig::from_pem_files(dir.join("server.crt"), dir.join("server.key")).unwrap();
+        let renewed = write_certificate(&ca, &dir, "server", "localhost");
+        tls.clone().reload().unwrap();
+
+        let (certificate, _) = handshake(&tls, ca.der(), "localhost").await;
+        assert_eq!(certificate, renewed);
+
+        // A broken file keeps the previous certificate in use
+        std::fs::write(dir.join("server.key"), "not a key").unwrap();
+        assert!(tls.reload().is_err());
+
+        let (certificate, _) = handshake(&tls, ca.der(), "localhost").await;
+        assert_eq!(certificate, renewed);
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_watch_stops_on_shutdown() {
+        let dir = temp_dir("watch");
+        let ca = certificate_authority();
+        write_certificate(&ca, &dir, "server", "localhost");
+
+        let tls = TlsConfig::from_pem_files(dir.join("server.crt"), dir.join("server.key"))
+            .unwrap()
+            .with_reload_interval(Duration::from_millis(10));
+        let resolver = tls.resolver().unwrap().clone();
+
+        let held = Arc::strong_count(&resolver);
+
+        let shutdown = Shutdown::default();
+        tls.watch(&shutdown);
+        assert_eq!(Arc::strong_count(&resolver), held + 1);
+
+        // The task drops its reference once it stops
+        shutdown.trigger();
+        while Arc::strong_count(&resolver) > held {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_client_auth() {
+        let dir = temp_dir("mtls");
+        let ca = certificate_authority();
+        write_certificate(&ca, &dir, "server", "localhost");
+        let client_cert = write_certificate(&ca, &dir, "client", "billing.internal");
+        std::fs::write(dir.join("ca.crt"), ca.pem()).unwrap();
+
+        let client_files = Some((dir.join("client.crt"), dir.join("client.key")));
+        let tls =
+            TlsConfig::from_pem_files(dir.join("server.crt"), dir.join("server.key")).unwrap();
+
+        let required = tls
+            .clone()
+            .with_client_auth(dir.join("ca.crt"), ClientAuth::Required)
+            .unwrap();
+        let (_, info) = connect(&required, ca.der(), "localhost", client_files.clone())
+            .await
+            .unwrap();
+        let identity = info.peer_identity().unwrap();
+        assert_eq!(identity.subject_alt_names(), ["billing.internal"]);
+        assert_eq!(identity.fingerprint().len(), 64);
+        assert_eq!(
+            identity.fingerprint(),
+            PeerIdentity::parse(&client_cert).unwrap().fingerprint()
+        );
+
+        assert!(
+            connect(&required, ca.der(), "localhost", None)
+                .await
+                .is_err()
+        );
+
+        let optional = tls
+            .with_client_auth(dir.join("ca.crt"), ClientAuth::Optional)
+            .unwrap();
+        let (_, info) = connect(&optional, ca.der(), "localhost", None)
+            .await
+            .unwrap();
+        assert!(info.peer_identity().is_none());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
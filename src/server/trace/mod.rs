@@ -0,0 +1,246 @@
+//! Distributed tracing with W3C Trace Context.
+//!
+//! Every request is given a [`TraceContext`], which continues the trace of an incoming
+//! `traceparent` header or starts a new one. Handlers read it with
+//! [`Request::trace_context`] or as an extractor, and forward
+//! [`TraceContext::traceparent`] to the services they call.
+//!
+//! With the `tracing` feature, connections and requests are wrapped in `tracing` spans
+//! carrying the OpenTelemetry HTTP attributes. With the `otlp` feature, request spans can
+//! also be exported to an OpenTelemetry collector, see [`OtlpExporter`].
+
+#[cfg(feature = "otlp")]
+mod otlp;
+
+use std::fmt::Write;
+
+#[cfg(feature = "otlp")]
+pub use otlp::OtlpExporter;
+#[cfg(feature = "otlp")]
+pub(crate) use otlp::{SpanRecord, SpanSender};
+
+use crate::{
+    Request,
+    extract::FromRequest,
+    responses::{HttpResponse, InternalServerError},
+    server::random,
+};
+
+const TRACEPARENT_HEADER: &str = "traceparent";
+const TRACESTATE_HEADER: &str = "tracestate";
+
+const FLAG_SAMPLED: u8 = 0x01;
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{:02x}", byte);
+        hex
+    })
+}
+
+/// Decodes lowercase hexadecimal into `N` bytes, rejecting identifiers that are all zeros.
+fn from_hex<const N: usize>(hex: &str) -> Option<[u8; N]> {
+    if hex.len() != N * 2 || !hex.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f')) {
+        return None;
+    }
+
+    let mut bytes = [0; N];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+    }
+
+    bytes.iter().any(|&byte| byte != 0).then_some(bytes)
+}
+
+/// A `traceparent` header received from the caller.
+struct Parent {
+    trace_id: [u8; 16],
+    span_id: [u8; 8],
+    flags: u8,
+}
+
+// https://www.w3.org/TR/trace-context/#traceparent-header-field-values
+fn parse_traceparent(value: &str) -> Option<Parent> {
+    let mut parts = value.trim().split('-');
+    let version = parts.next()?;
+    let trace_id = from_hex::<16>(parts.next()?)?;
+    let span_id = from_hex::<8>(parts.next()?)?;
+    let flags = parts.next()?;
+
+    let version_valid =
+        version.len() == 2 && version != "ff" && u8::from_str_radix(version, 16).is_ok();
+    // Version 00 has exactly four fields, later versions may append more
+    let fields_valid = version != "00" || parts.next().is_none();
+
+    if !version_valid || !fields_valid || flags.len() != 2 {
+        return None;
+    }
+
+    Some(Parent {
+        trace_id,
+        span_id,
+        flags: u8::from_str_radix(flags, 16).ok()?,
+    })
+}
+
+/// The position of a request in a distributed trace.
+///
+/// The span id identifies the request on this server, and the parent span id the caller
+/// that sent the `traceparent` header, if any.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceContext {
+    trace_id: [u8; 16],
+    span_id: [u8; 8],
+    parent_span_id: Option<[u8; 8]>,
+    flags: u8,
+    trace_state: Option<String>,
+}
+
+impl TraceContext {
+    /// Continues the trace described by the `traceparent` and `tracestate` headers of
+    /// `req`, or starts a new sampled trace if they are missing or invalid.
+    pub(crate) fn from_headers(req: &Request) -> Self {
+        let mut span_id = [0; 8];
+        random::fill(&mut span_id);
+
+        let parent = req
+            .headers()
+            .get::<String>(TRACEPARENT_HEADER)
+            .and_then(|value| parse_traceparent(&value));
+
+        match parent {
+            Some(parent) => TraceContext {
+                trace_id: parent.trace_id,
+                span_id,
+                parent_span_id: Some(parent.span_id),
+                flags: parent.flags & FLAG_SAMPLED,
+                trace_state: req
+                    .headers()
+                    .get::<String>(TRACESTATE_HEADER)
+                    .filter(|state| !state.is_empty()),
+            },
+            None => {
+                let mut trace_id = [0; 16];
+                random::fill(&mut trace_id);
+
+                TraceContext {
+                    trace_id,
+                    span_id,
+                    parent_span_id: None,
+                    flags: FLAG_SAMPLED,
+                    trace_state: None,
+                }
+            }
+        }
+    }
+
+    /// Returns the trace id as 32 hexadecimal digits.
+    pub fn trace_id(&self) -> String {
+        to_hex(&self.trace_id)
+    }
+
+    /// Returns the id of the span of this request as 16 hexadecimal digits.
+    pub fn span_id(&self) -> String {
+        to_hex(&self.span_id)
+    }
+
+    /// Returns the id of the span that sent the request, if it was part of a trace.
+    pub fn parent_span_id(&self) -> Option<String> {
+        self.parent_span_id.map(|id| to_hex(&id))
+    }
+
+    /// Returns whether the caller asked for the trace to be recorded.
+    pub fn is_sampled(&self) -> bool {
+        self.flags & FLAG_SAMPLED != 0
+    }
+
+    /// Returns the vendor-specific `tracestate` received with the request.
+    pub fn trace_state(&self) -> Option<&str> {
+        self.trace_state.as_deref()
+    }
+
+    /// Returns the `traceparent` header to send to downstream services, making this
+    /// request the parent of their spans.
+    pub fn traceparent(&self) -> String {
+        format!(
+            "00-{}-{}-{:02x}",
+            self.trace_id(),
+            self.span_id(),
+            self.flags
+        )
+    }
+}
+
+impl Request {
+    /// Returns the trace context of the request. It is set for every request received by
+    /// a [`Server`](crate::Server).
+    pub fn trace_context(&self) -> Option<&TraceContext> {
+        self.extensions().get::<TraceContext>()
+    }
+}
+
+impl FromRequest for TraceContext {
+    fn from_request(req: &Request) -> Result<Self, Box<dyn HttpResponse>> {
+        req.trace_context().cloned().ok_or_else(|| {
+            InternalServerError::with_message("The request has no trace context").into()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn request(headers: &str) -> Request {
+        let raw = format!("GET / HTTP/1.1\r\n{}\r\n", headers);
+        Request::from_reader(raw.as_bytes()).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_continue_incoming_trace() {
+        let req = request(
+            "traceparent: 00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01\r\n\
+             tracestate: congo=t61rcWkgMzE\r\n",
+        )
+        .await;
+        let context = TraceContext::from_headers(&req);
+
+        assert_eq!(context.trace_id(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(context.parent_span_id().unwrap(), "00f067aa0ba902b7");
+        assert_ne!(context.span_id(), "00f067aa0ba902b7");
+        assert!(context.is_sampled());
+        assert_eq!(context.trace_state(), Some("congo=t61rcWkgMzE"));
+        assert_eq!(
+            context.traceparent(),
+            format!(
+                "00-4bf92f3577b34da6a3ce929d0e0e4736-{}-01",
+                context.span_id()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_start_trace_on_invalid_header() {
+        let invalid = [
+            "traceparent: 00-00000000000000000000000000000000-00f067aa0ba902b7-01\r\n",
+            "traceparent: ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01\r\n",
+            "traceparent: 00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01\r\n",
+            "traceparent: 00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-extra\r\n",
+        ];
+
+        for header in invalid {
+            let context = TraceContext::from_headers(&request(header).await);
+            assert_ne!(context.trace_id(), "4bf92f3577b34da6a3ce929d0e0e4736");
+            assert!(context.parent_span_id().is_none());
+        }
+
+        // Later versions may carry more fields
+        let req = request(
+            "traceparent: 01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00-extra\r\n",
+        )
+        .await;
+        let context = TraceContext::from_headers(&req);
+        assert_eq!(context.trace_id(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert!(!context.is_sampled());
+    }
+}
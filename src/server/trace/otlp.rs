@@ -0,0 +1,402 @@
+use std::{
+    io,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::{Value, json};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    sync::mpsc,
+};
+
+use crate::{Request, headers::keys, server::trace::TraceContext};
+
+const DEFAULT_PATH: &str = "/v1/traces";
+const DEFAULT_SERVICE_NAME: &str = "http-server";
+const DEFAULT_BATCH_SIZE: usize = 512;
+const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+const QUEUE_SIZE: usize = 2048;
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// https://opentelemetry.io/docs/specs/otel/trace/api/#spankind
+const SPAN_KIND_SERVER: u8 = 2;
+// https://opentelemetry.io/docs/specs/otel/trace/api/#set-status
+const STATUS_CODE_ERROR: u8 = 2;
+
+pub(crate) type SpanSender = mpsc::Sender<SpanRecord>;
+
+fn string_attribute(key: &str, value: &str) -> Value {
+    json!({ "key": key, "value": { "stringValue": value } })
+}
+
+fn int_attribute(key: &str, value: i64) -> Value {
+    // 64-bit integers are encoded as strings by the OTLP JSON mapping
+    json!({ "key": key, "value": { "intValue": value.to_string() } })
+}
+
+fn unix_nanos(time: SystemTime) -> String {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_nanos()
+        .to_string()
+}
+
+/// A finished server span waiting to be exported.
+pub(crate) struct SpanRecord {
+    trace_id: String,
+    span_id: String,
+    parent_span_id: Option<String>,
+    trace_state: Option<String>,
+    name: String,
+    start: SystemTime,
+    end: SystemTime,
+    attributes: Vec<Value>,
+    status: Option<u16>,
+}
+
+impl SpanRecord {
+    pub(crate) fn new(
+        req: &Request,
+        context: &TraceContext,
+        scheme: &str,
+        start: SystemTime,
+    ) -> Self {
+        let mut attributes = vec![
+            string_attribute("http.request.method", req.method()),
+            string_attribute("url.path", req.path()),
+            string_attribute("url.scheme", scheme),
+            string_attribute(
+                "network.protocol.version",
+                req.http_version().trim_start_matches("HTTP/"),
+            ),
+        ];
+
+        if let Some((_, query)) = req.target().split_once('?') {
+            attributes.push(string_attribute("url.query", query));
+        }
+
+        if let Some(user_agent) = req.headers().get::<String>(keys::USER_AGENT_HEADER) {
+            attributes.push(string_attribute("user_agent.original", &user_agent));
+        }
+
+        if let Some(addr) = req.peer_addr() {
+            attributes.push(string_attribute("client.address", &addr.ip().to_string()));
+            attributes.push(int_attribute("client.port", addr.port().into()));
+        }
+
+        SpanRecord {
+            trace_id: context.trace_id(),
+            span_id: context.span_id(),
+            parent_span_id: context.parent_span_id(),
+            trace_state: context.trace_state().map(str::to_string),
+            name: req.method().to_string(),
+            start,
+            end: start,
+            attributes,
+            status: None,
+        }
+    }
+
+    pub(crate) fn set_status(&mut self, status: u16) {
+        self.status = Some(status);
+    }
+
+    pub(crate) fn finish(&mut self, end: SystemTime) {
+        self.end = end;
+    }
+
+    fn to_json(&self) -> Value {
+        let mut attributes = self.attributes.clone();
+        if let Some(status) = self.status {
+            attributes.push(int_attribute("http.response.status_code", status.into()));
+        }
+
+        let mut span = json!({
+            "traceId": self.trace_id,
+            "spanId": self.span_id,
+            "name": self.name,
+            "kind": SPAN_KIND_SERVER,
+            "startTimeUnixNano": unix_nanos(self.start),
+            "endTimeUnixNano": unix_nanos(self.end),
+            "attributes": attributes,
+            "status": {},
+        });
+
+        if let Some(parent_span_id) = &self.parent_span_id {
+            span["parentSpanId"] = json!(parent_span_id);
+        }
+
+        if let Some(trace_state) = &self.trace_state {
+            span["traceState"] = json!(trace_state);
+        }
+
+        // Server spans only report an error for 5xx responses, 4xx are the client's fault
+        if self.status.is_some_and(|status| status >= 500) {
+            span["status"] = json!({ "code": STATUS_CODE_ERROR });
+        }
+
+        span
+    }
+}
+
+/// Exports request spans to an OpenTelemetry collector using OTLP over HTTP with JSON
+/// encoding, available with the `otlp` feature.
+///
+/// Spans of sampled requests are queued and sent in batches by a background task. When
+/// the collector cannot keep up, new spans are dropped instead of slowing requests down.
+///
+/// # Example
+///
+/// ```no_run
+/// use http_server::{Router, Server, trace::OtlpExporter};
+///
+/// # async fn run() -> std::io::Result<()> {
+/// let exporter = OtlpExporter::new("http://localhost:4318")?.with_service_name("checkout");
+///
+/// Server::new("127.0.0.1:8080", Router::new())
+///     .with_otlp(exporter)
+///     .serve()
+///     .await
+/// # }
+/// ```
+pub struct OtlpExporter {
+    authority: String,
+    path: String,
+    service_name: String,
+    batch_size: usize,
+    flush_interval: Duration,
+}
+
+impl OtlpExporter {
+    /// Creates a new OtlpExporter sending spans to the collector at `endpoint`, such as
+    /// `http://localhost:4318`. The path defaults to `/v1/traces`.
+    ///
+    /// Only `http://` endpoints are supported.
+    pub fn new(endpoint: &str) -> io::Result<Self> {
+        let invalid = || {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid OTLP endpoint: {}", endpoint),
+            )
+        };
+
+        let rest = endpoint.strip_prefix("http://").ok_or_else(invalid)?;
+        let (authority, path) = match rest.find('/') {
+            Some(index) if index + 1 < rest.len() => (&rest[..index], &rest[index..]),
+            Some(index) => (&rest[..index], DEFAULT_PATH),
+            None => (rest, DEFAULT_PATH),
+        };
+
+        if authority.is_empty() {
+            return Err(invalid());
+        }
+
+        let has_port = authority
+            .rsplit_once(':')
+            .is_some_and(|(_, port)| !port.ends_with(']'));
+
+        Ok(OtlpExporter {
+            authority: if has_port {
+                authority.to_string()
+            } else {
+                format!("{}:80", authority)
+            },
+            path: path.to_string(),
+            service_name: DEFAULT_SERVICE_NAME.to_string(),
+            batch_size: DEFAULT_BATCH_SIZE,
+            flush_interval: DEFAULT_FLUSH_INTERVAL,
+        })
+    }
+
+    /// Sets the `service.name` reported with the spans. Defaults to `http-server`.
+    pub fn with_service_name<S: Into<String>>(mut self, service_name: S) -> Self {
+        self.service_name = service_name.into();
+        self
+    }
+
+    /// Sets how many spans are sent in a single request. Defaults to 512.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Sets how often queued spans are sent when a batch is not full. Defaults to 5
+    /// seconds.
+    pub fn with_flush_interval(mut self, interval: Duration) -> Self {
+        self.flush_interval = interval;
+        self
+    }
+
+    /// Starts the background task sending spans, which runs until every sender is dropped.
+    pub(crate) fn spawn(self) -> SpanSender {
+        let (sender, receiver) = mpsc::channel(QUEUE_SIZE);
+        tokio::spawn(self.run(receiver));
+        sender
+    }
+
+    async fn run(self, mut receiver: mpsc::Receiver<SpanRecord>) {
+        let mut batch = Vec::new();
+        let mut interval = tokio::time::interval(self.flush_interval);
+
+        loop {
+            tokio::select! {
+                record = receiver.recv() => match record {
+                    Some(record) => {
+                        batch.push(record);
+                        if batch.len() >= self.batch_size {
+                            self.flush(&mut batch).await;
+                        }
+                    }
+                    None => {
+                        self.flush(&mut batch).await;
+                        return;
+                    }
+                },
+                _ = interval.tick() => self.flush(&mut batch).await,
+            }
+        }
+    }
+
+    async fn flush(&self, batch: &mut Vec<SpanRecord>) {
+        if batch.is_empty() {
+            return;
+        }
+
+        let body = self.encode(batch);
+        batch.clear();
+
+        match tokio::time::timeout(EXPORT_TIMEOUT, self.post(&body)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => eprintln!("Failed to export spans: {}", err),
+            Err(_) => eprintln!("Failed to export spans: the collector timed out"),
+        }
+    }
+
+    fn encode(&self, batch: &[SpanRecord]) -> Vec<u8> {
+        let spans = batch.iter().map(SpanRecord::to_json).collect::<Vec<_>>();
+
+        json!({
+            "resourceSpans": [{
+                "resource": {
+                    "attributes": [string_attribute("service.name", &self.service_name)],
+                },
+                "scopeSpans": [{
+                    "scope": {
+                        "name": env!("CARGO_PKG_NAME"),
+                        "version": env!("CARGO_PKG_VERSION"),
+                    },
+                    "spans": spans,
+                }],
+            }],
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    async fn post(&self, body: &[u8]) -> io::Result<()> {
+        let mut stream = TcpStream::connect(&self.authority).await?;
+
+        let head = format!(
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            self.path,
+            self.authority,
+            body.len()
+        );
+        stream.write_all(head.as_bytes()).await?;
+        stream.write_all(body).await?;
+
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).await?;
+
+        let status_line = response
+            .split(|&byte| byte == b'\r')
+            .next()
+            .map(String::from_utf8_lossy)
+            .unwrap_or_default();
+
+        match status_line.split(' ').nth(1) {
+            Some(status) if status.starts_with('2') => Ok(()),
+            _ => Err(io::Error::other(format!(
+                "The collector responded with {:?}",
+                status_line
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_export_to_collector() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+
+        let sender = OtlpExporter::new(&endpoint)
+            .unwrap()
+            .with_service_name("checkout")
+            .with_batch_size(1)
+            .spawn();
+
+        let req = Request::from_reader(
+            &b"GET /orders?page=2 HTTP/1.1\r\n\
+               traceparent: 00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01\r\n\r\n"[..],
+        )
+        .await
+        .unwrap();
+        let context = TraceContext::from_headers(&req);
+
+        let mut record = SpanRecord::new(&req, &context, "http", SystemTime::now());
+        record.set_status(503);
+        record.finish(SystemTime::now());
+        sender.send(record).await.unwrap();
+
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let export = Request::from_reader(&mut stream).await.unwrap();
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+            .await
+            .unwrap();
+
+        assert_eq!(export.method(), "POST");
+        assert_eq!(export.path(), "/v1/traces");
+
+        let body: Value = serde_json::from_slice(export.body()).unwrap();
+        let resource = &body["resourceSpans"][0];
+        assert_eq!(
+            resource["resource"]["attributes"][0]["value"]["stringValue"],
+            "checkout"
+        );
+
+        let span = &resource["scopeSpans"][0]["spans"][0];
+        assert_eq!(span["traceId"], "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(span["parentSpanId"], "00f067aa0ba902b7");
+        assert_eq!(span["spanId"], context.span_id());
+        assert_eq!(span["name"], "GET");
+        assert_eq!(span["kind"], 2);
+        assert_eq!(span["status"]["code"], 2);
+        assert!(
+            span["attributes"]
+                .as_array()
+                .unwrap()
+                .contains(&int_attribute("http.response.status_code", 503))
+        );
+    }
+
+    #[test]
+    fn test_endpoint() {
+        let exporter = OtlpExporter::new("http://collector").unwrap();
+        assert_eq!(exporter.authority, "collector:80");
+        assert_eq!(exporter.path, "/v1/traces");
+
+        let exporter = OtlpExporter::new("http://[::1]:4318/custom/traces").unwrap();
+        assert_eq!(exporter.authority, "[::1]:4318");
+        assert_eq!(exporter.path, "/custom/traces");
+
+        assert!(OtlpExporter::new("https://collector:4318").is_err());
+    }
+}
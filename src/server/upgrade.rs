@@ -0,0 +1,166 @@
+//! Taking over the connection of a request to speak another protocol.
+//!
+//! A handler answers with a [`SwitchingProtocolsResponse`], or a [`TunnelResponse`] for
+//! `CONNECT` requests. Once the response is sent, its callback receives the connection as
+//! an [`Upgraded`] stream. Protocol upgrades are only available over HTTP/1.1.
+//!
+//! [`SwitchingProtocolsResponse`]: crate::responses::SwitchingProtocolsResponse
+//! [`TunnelResponse`]: crate::responses::TunnelResponse
+
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// A stream that replays bytes already read from it before reading further.
+pub(crate) struct Rewind<S> {
+    prefix: Vec<u8>,
+    position: usize,
+    inner: S,
+}
+
+impl<S> Rewind<S> {
+    pub(crate) fn new(prefix: Vec<u8>, inner: S) -> Self {
+        Rewind {
+            prefix,
+            position: 0,
+            inner,
+        }
+    }
+
+    pub(crate) fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Returns the inner stream and the bytes that were not replayed yet.
+    fn into_parts(mut self) -> (S, Vec<u8>) {
+        self.prefix.drain(..self.position);
+        (self.inner, self.prefix)
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for Rewind<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        if self.position < self.prefix.len() {
+            let remaining = &self.prefix[self.position..];
+            let len = remaining.len().min(buf.remaining());
+            buf.put_slice(&remaining[..len]);
+            self.position += len;
+            return Poll::Ready(Ok(()));
+        }
+
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for Rewind<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+/// A bidirectional byte stream.
+pub trait Io: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Io for T {}
+
+/// The connection of a request after its response was sent, handed over to another
+/// protocol. Bytes the client sent right after the request are read first.
+pub struct Upgraded {
+    stream: Rewind<Box<dyn Io>>,
+}
+
+impl Upgraded {
+    pub(crate) fn new(stream: Box<dyn Io>, leftover: Vec<u8>) -> Self {
+        Upgraded {
+            stream: Rewind::new(leftover, stream),
+        }
+    }
+
+    /// Returns the underlying stream and the bytes received after the request that have
+    /// not been read yet, for protocols that need the raw connection.
+    pub fn into_parts(self) -> (Box<dyn Io>, Vec<u8>) {
+        self.stream.into_parts()
+    }
+}
+
+impl AsyncRead for Upgraded {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for Upgraded {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}
+
+/// Takes over the connection once a response that switches protocols has been sent.
+pub(crate) type OnUpgrade =
+    Box<dyn FnOnce(Upgraded) -> Pin<Box<dyn Future<Output = ()> + Send>> + Send>;
+
+/// Boxes a callback receiving the upgraded connection.
+pub(crate) fn on_upgrade<F, Fut>(callback: F) -> OnUpgrade
+where
+    F: FnOnce(Upgraded) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    Box::new(move |upgraded| Box::pin(callback(upgraded)))
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::AsyncReadExt;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_into_parts_keeps_unread_bytes() {
+        let (_client, server) = tokio::io::duplex(64);
+        let mut upgraded = Upgraded::new(Box::new(server), b"abc".to_vec());
+
+        let mut buffer = [0; 1];
+        upgraded.read_exact(&mut buffer).await.unwrap();
+        assert_eq!(&buffer, b"a");
+
+        let (_, leftover) = upgraded.into_parts();
+        assert_eq!(leftover, b"bc");
+    }
+}
@@ -0,0 +1,225 @@
+//! The permessage-deflate extension of RFC 7692.
+
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+
+use super::{CloseCode, frame::FrameError};
+
+/// The empty stored block that ends a sync flush, which is left out of messages.
+const TRAILER: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+const EXTENSION: &str = "permessage-deflate";
+
+fn invalid() -> FrameError {
+    FrameError::Protocol(CloseCode::INVALID_DATA, "Invalid compressed message")
+}
+
+/// The parameters agreed on during the handshake.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub(super) struct DeflateParams {
+    server_no_context_takeover: bool,
+    client_no_context_takeover: bool,
+}
+
+/// Accepts the first permessage-deflate offer of a `Sec-WebSocket-Extensions` header that
+/// the server supports, returning its parameters and the value of the response header.
+pub(super) fn negotiate(header: &str) -> Option<(DeflateParams, String)> {
+    header.split(',').find_map(|offer| {
+        let mut parts = offer.split(';').map(str::trim);
+        if !parts.next()?.eq_ignore_ascii_case(EXTENSION) {
+            return None;
+        }
+
+        let mut params = DeflateParams::default();
+        let mut seen = Vec::new();
+
+        for param in parts {
+            let (name, value) = match param.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+                None => (param, None),
+            };
+
+            // Offers repeating a parameter are invalid
+            if seen.contains(&name) {
+                return None;
+            }
+            seen.push(name);
+
+            match (name, value) {
+                ("server_no_context_takeover", None) => params.server_no_context_takeover = true,
+                ("client_no_context_takeover", None) => params.client_no_context_takeover = true,
+                // Only the full window is supported when compressing
+                ("server_max_window_bits", Some("15")) => {}
+                ("client_max_window_bits", None) => {}
+                ("client_max_window_bits", Some(bits))
+                    if bits
+                        .parse::<u8>()
+                        .is_ok_and(|bits| (8..=15).contains(&bits)) => {}
+                _ => return None,
+            }
+        }
+
+        let mut response = EXTENSION.to_string();
+        if params.server_no_context_takeover {
+            response.push_str("; server_no_context_takeover");
+        }
+        if params.client_no_context_takeover {
+            response.push_str("; client_no_context_takeover");
+        }
+
+        Some((params, response))
+    })
+}
+
+/// Compresses sent messages and decompresses received ones.
+pub(super) struct Deflate {
+    params: DeflateParams,
+    compress: Compress,
+    decompress: Decompress,
+}
+
+impl Deflate {
+    pub(super) fn new(params: DeflateParams) -> Self {
+        Deflate {
+            params,
+            compress: Compress::new(Compression::default(), false),
+            decompress: Decompress::new(false),
+        }
+    }
+
+    pub(super) fn compress(&mut self, data: &[u8]) -> Vec<u8> {
+        let start = self.compress.total_in();
+        let mut output = Vec::with_capacity(data.len() / 2 + 64);
+
+        loop {
+            let consumed = (self.compress.total_in() - start) as usize;
+            // Compressing into memory cannot fail
+            let _ = self
+                .compress
+                .compress_vec(&data[consumed..], &mut output, FlushCompress::Sync);
+
+            let consumed = (self.compress.total_in() - start) as usize;
+            if consumed == data.len() && output.len() < output.capacity() {
+                break;
+            }
+
+            output.reserve(output.capacity().max(64));
+        }
+
+        if output.ends_with(&TRAILER) {
+            output.truncate(output.len() - TRAILER.len());
+        }
+
+        if self.params.server_no_context_takeover {
+            self.compress.reset();
+        }
+
+        output
+    }
+
+    /// Decompresses a message, failing if it is invalid or inflates to more than `max_size`
+    /// bytes.
+    pub(super) fn decompress(
+        &mut self,
+        data: &[u8],
+        max_size: usize,
+    ) -> Result<Vec<u8>, FrameError> {
+        let mut input = Vec::with_capacity(data.len() + TRAILER.len());
+        input.extend_from_slice(data);
+        input.extend_from_slice(&TRAILER);
+
+        let start = self.decompress.total_in();
+        let mut output = Vec::with_capacity((data.len() * 2).clamp(64, max_size.max(64)));
+
+        loop {
+            let consumed = (self.decompress.total_in() - start) as usize;
+            let written = output.len();
+
+            let status = self
+                .decompress
+                .decompress_vec(&input[consumed..], &mut output, FlushDecompress::Sync)
+                .map_err(|_| invalid())?;
+
+            if output.len() > max_size {
+                return Err(FrameError::Protocol(
+                    CloseCode::MESSAGE_TOO_BIG,
+                    "Message exceeds the maximum size",
+                ));
+            }
+
+            let stalled = (self.decompress.total_in() - start) as usize == consumed
+                && output.len() == written;
+            let consumed = (self.decompress.total_in() - start) as usize;
+            let full = output.len() == output.capacity();
+
+            if status == Status::StreamEnd || (consumed == input.len() && !full) {
+                break;
+            }
+
+            // Free space left unused means the input ended in the middle of a block
+            if stalled && !full {
+                return Err(invalid());
+            }
+
+            output.reserve(output.capacity().min(max_size + 1 - output.len()));
+        }
+
+        if self.params.client_no_context_takeover {
+            self.decompress.reset(false);
+        }
+
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiate() {
+        let (params, response) =
+            negotiate("permessage-deflate; client_max_window_bits; server_no_context_takeover")
+                .unwrap();
+        assert!(params.server_no_context_takeover);
+        assert_eq!(response, "permessage-deflate; server_no_context_takeover");
+
+        // A reduced server window is declined, so the next offer is selected
+        let (params, response) = negotiate(
+            "permessage-deflate; server_max_window_bits=10, permessage-deflate; client_no_context_takeover",
+        )
+        .unwrap();
+        assert!(params.client_no_context_takeover);
+        assert_eq!(response, "permessage-deflate; client_no_context_takeover");
+
+        assert!(negotiate("x-webkit-deflate-frame").is_none());
+        assert!(negotiate("permessage-deflate; unknown").is_none());
+    }
+
+    #[test]
+    fn test_round_trip_with_context_takeover() {
+        let mut server = Deflate::new(DeflateParams::default());
+        let mut client = Deflate::new(DeflateParams::default());
+
+        for _ in 0..3 {
+            let message = b"Hello, hello, hello, hello!".repeat(20);
+            let compressed = server.compress(&message);
+            assert!(compressed.len() < message.len());
+            assert_eq!(
+                client.decompress(&compressed, 1 << 20).ok().unwrap(),
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn test_decompress_limit() {
+        let mut server = Deflate::new(DeflateParams::default());
+        let compressed = server.compress(&[0; 10_000]);
+
+        let mut client = Deflate::new(DeflateParams::default());
+        assert!(matches!(
+            client.decompress(&compressed, 1000),
+            Err(FrameError::Protocol(CloseCode::MESSAGE_TOO_BIG, _))
+        ));
+    }
+}
@@ -0,0 +1,245 @@
+use std::io;
+
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use super::CloseCode;
+
+const FIN: u8 = 0x80;
+const RSV1: u8 = 0x40;
+const RESERVED: u8 = 0x30;
+const OPCODE: u8 = 0x0f;
+const MASK: u8 = 0x80;
+const LENGTH: u8 = 0x7f;
+
+/// The largest payload of a control frame.
+pub(super) const MAX_CONTROL_PAYLOAD: usize = 125;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(super) enum OpCode {
+    Continuation,
+    Text,
+    Binary,
+    Close,
+    Ping,
+    Pong,
+}
+
+impl OpCode {
+    fn from_bits(bits: u8) -> Option<Self> {
+        match bits {
+            0x0 => Some(OpCode::Continuation),
+            0x1 => Some(OpCode::Text),
+            0x2 => Some(OpCode::Binary),
+            0x8 => Some(OpCode::Close),
+            0x9 => Some(OpCode::Ping),
+            0xa => Some(OpCode::Pong),
+            _ => None,
+        }
+    }
+
+    fn bits(&self) -> u8 {
+        match self {
+            OpCode::Continuation => 0x0,
+            OpCode::Text => 0x1,
+            OpCode::Binary => 0x2,
+            OpCode::Close => 0x8,
+            OpCode::Ping => 0x9,
+            OpCode::Pong => 0xa,
+        }
+    }
+
+    pub(super) fn is_control(&self) -> bool {
+        matches!(self, OpCode::Close | OpCode::Ping | OpCode::Pong)
+    }
+}
+
+/// A single WebSocket frame, with its payload already unmasked.
+pub(super) struct Frame {
+    pub(super) fin: bool,
+    pub(super) compressed: bool,
+    pub(super) opcode: OpCode,
+    pub(super) payload: Vec<u8>,
+}
+
+/// An error raised while reading a frame.
+pub(super) enum FrameError {
+    Io(io::Error),
+    /// The peer broke the protocol, and the connection must be closed with this code.
+    Protocol(CloseCode, &'static str),
+}
+
+impl From<io::Error> for FrameError {
+    fn from(error: io::Error) -> Self {
+        FrameError::Io(error)
+    }
+}
+
+/// Reads a frame sent by a client, rejecting payloads larger than `max_payload` before
+/// reading them.
+pub(super) async fn read<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    max_payload: usize,
+) -> Result<Frame, FrameError> {
+    let mut head = [0; 2];
+    reader.read_exact(&mut head).await?;
+
+    if head[0] & RESERVED != 0 {
+        return Err(FrameError::Protocol(
+            CloseCode::PROTOCOL_ERROR,
+            "Reserved bits are set",
+        ));
+    }
+
+    let opcode = OpCode::from_bits(head[0] & OPCODE).ok_or(FrameError::Protocol(
+        CloseCode::PROTOCOL_ERROR,
+        "Unknown opcode",
+    ))?;
+    let fin = head[0] & FIN != 0;
+
+    if head[1] & MASK == 0 {
+        return Err(FrameError::Protocol(
+            CloseCode::PROTOCOL_ERROR,
+            "Client frames must be masked",
+        ));
+    }
+
+    let length = match head[1] & LENGTH {
+        126 => reader.read_u16().await? as u64,
+        127 => reader.read_u64().await?,
+        length => length as u64,
+    };
+
+    if opcode.is_control() && (!fin || length > MAX_CONTROL_PAYLOAD as u64) {
+        return Err(FrameError::Protocol(
+            CloseCode::PROTOCOL_ERROR,
+            "Control frames must not be fragmented or exceed 125 bytes",
+        ));
+    }
+
+    if length > max_payload as u64 {
+        return Err(FrameError::Protocol(
+            CloseCode::MESSAGE_TOO_BIG,
+            "Message exceeds the maximum size",
+        ));
+    }
+
+    let mut mask = [0; 4];
+    reader.read_exact(&mut mask).await?;
+
+    let mut payload = vec![0; length as usize];
+    reader.read_exact(&mut payload).await?;
+
+    for (i, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[i % 4];
+    }
+
+    Ok(Frame {
+        fin,
+        compressed: head[0] & RSV1 != 0,
+        opcode,
+        payload,
+    })
+}
+
+/// Encodes an unfragmented, unmasked frame as sent by a server.
+pub(super) fn encode(opcode: OpCode, compressed: bool, payload: &[u8]) -> Vec<u8> {
+    let mut frame = Vec::with_capacity(payload.len() + 10);
+
+    let mut first = FIN | opcode.bits();
+    if compressed {
+        first |= RSV1;
+    }
+    frame.push(first);
+
+    match payload.len() {
+        length if length < 126 => frame.push(length as u8),
+        length if length <= u16::MAX as usize => {
+            frame.push(126);
+            frame.extend_from_slice(&(length as u16).to_be_bytes());
+        }
+        length => {
+            frame.push(127);
+            frame.extend_from_slice(&(length as u64).to_be_bytes());
+        }
+    }
+
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// Encodes a masked frame as sent by a client.
+#[cfg(test)]
+pub(super) fn client_frame(first: u8, payload: &[u8]) -> Vec<u8> {
+    let mask = [0x37, 0xfa, 0x21, 0x3d];
+    let mut frame = vec![first];
+
+    match payload.len() {
+        length if length < 126 => frame.push(MASK | length as u8),
+        length => {
+            frame.push(MASK | 126);
+            frame.extend_from_slice(&(length as u16).to_be_bytes());
+        }
+    }
+
+    frame.extend_from_slice(&mask);
+    frame.extend(
+        payload
+            .iter()
+            .enumerate()
+            .map(|(i, byte)| byte ^ mask[i % 4]),
+    );
+    frame
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_masked_frame() {
+        // The masked "Hello" example from RFC 6455, section 5.7
+        let data = [
+            0x81, 0x85, 0x37, 0xfa, 0x21, 0x3d, 0x7f, 0x9f, 0x4d, 0x51, 0x58,
+        ];
+        let Ok(frame) = read(&mut &data[..], 1024).await else {
+            panic!("expected a frame");
+        };
+
+        assert!(frame.fin);
+        assert_eq!(frame.opcode, OpCode::Text);
+        assert_eq!(frame.payload, b"Hello");
+    }
+
+    #[tokio::test]
+    async fn test_reject_invalid_frames() {
+        // Unmasked client frame
+        let data = [0x81, 0x05, b'H', b'e', b'l', b'l', b'o'];
+        assert!(matches!(
+            read(&mut &data[..], 1024).await,
+            Err(FrameError::Protocol(CloseCode::PROTOCOL_ERROR, _))
+        ));
+
+        // Payload over the limit
+        let data = client_frame(0x82, &[0; 200]);
+        assert!(matches!(
+            read(&mut &data[..], 100).await,
+            Err(FrameError::Protocol(CloseCode::MESSAGE_TOO_BIG, _))
+        ));
+
+        // Fragmented ping
+        let data = client_frame(0x09, b"ping");
+        assert!(matches!(
+            read(&mut &data[..], 1024).await,
+            Err(FrameError::Protocol(CloseCode::PROTOCOL_ERROR, _))
+        ));
+    }
+
+    #[test]
+    fn test_encode_lengths() {
+        assert_eq!(encode(OpCode::Text, false, b"Hi"), [0x81, 0x02, b'H', b'i']);
+
+        let frame = encode(OpCode::Binary, true, &[0; 300]);
+        assert_eq!(&frame[..4], [0xc2, 126, 0x01, 0x2c]);
+        assert_eq!(frame.len(), 304);
+    }
+}
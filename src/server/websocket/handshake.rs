@@ -0,0 +1,187 @@
+use std::future::Future;
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use sha1::{Digest, Sha1};
+
+use crate::{
+    Request,
+    extract::FromRequest,
+    headers::keys,
+    responses::{BadRequestError, HttpResponse, SwitchingProtocolsResponse, UpgradeRequiredError},
+    server::websocket::{WebSocket, WebSocketConfig},
+};
+
+const SEC_WEBSOCKET_KEY_HEADER: &str = "Sec-WebSocket-Key";
+const SEC_WEBSOCKET_ACCEPT_HEADER: &str = "Sec-WebSocket-Accept";
+const SEC_WEBSOCKET_VERSION_HEADER: &str = "Sec-WebSocket-Version";
+const SEC_WEBSOCKET_PROTOCOL_HEADER: &str = "Sec-WebSocket-Protocol";
+#[cfg(feature = "websocket-deflate")]
+const SEC_WEBSOCKET_EXTENSIONS_HEADER: &str = "Sec-WebSocket-Extensions";
+
+const WEBSOCKET_VERSION: &str = "13";
+
+// https://datatracker.ietf.org/doc/html/rfc6455#section-1.3
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Computes the `Sec-WebSocket-Accept` value answering a `Sec-WebSocket-Key`.
+fn accept_key(key: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(key.as_bytes());
+    hasher.update(ACCEPT_GUID.as_bytes());
+    STANDARD.encode(hasher.finalize())
+}
+
+/// Returns whether a comma-separated header contains `token`, ignoring case.
+fn has_token(req: &Request, key: &str, token: &str) -> bool {
+    req.headers().get::<String>(key).is_some_and(|value| {
+        value
+            .split(',')
+            .any(|item| item.trim().eq_ignore_ascii_case(token))
+    })
+}
+
+fn upgrade_required(message: &str) -> Box<dyn HttpResponse> {
+    UpgradeRequiredError::with_message(message)
+        .with_header(keys::UPGRADE_HEADER, "websocket")
+        .with_header(SEC_WEBSOCKET_VERSION_HEADER, WEBSOCKET_VERSION)
+        .into()
+}
+
+/// A request to open a WebSocket, which validates the opening handshake of RFC 6455.
+///
+/// Requests that are not WebSocket upgrades, or that ask for another protocol version,
+/// are rejected with 426 Upgrade Required. A malformed `Sec-WebSocket-Key` is rejected
+/// with 400 Bad Request.
+pub struct WebSocketUpgrade {
+    key: String,
+    protocols: Vec<String>,
+    #[cfg(feature = "websocket-deflate")]
+    extensions: Option<String>,
+    config: WebSocketConfig,
+}
+
+impl FromRequest for WebSocketUpgrade {
+    fn from_request(req: &Request) -> Result<Self, Box<dyn HttpResponse>> {
+        if req.method() != "GET"
+            || !has_token(req, keys::UPGRADE_HEADER, "websocket")
+            || !has_token(req, keys::CONNECTION_HEADER, "upgrade")
+        {
+            return Err(upgrade_required("Expected a WebSocket upgrade request"));
+        }
+
+        if req
+            .headers()
+            .get::<String>(SEC_WEBSOCKET_VERSION_HEADER)
+            .as_deref()
+            != Some(WEBSOCKET_VERSION)
+        {
+            return Err(upgrade_required("Unsupported WebSocket version"));
+        }
+
+        let key = req
+            .headers()
+            .get::<String>(SEC_WEBSOCKET_KEY_HEADER)
+            .filter(|key| STANDARD.decode(key).is_ok_and(|nonce| nonce.len() == 16))
+            .ok_or_else(|| {
+                Box::<dyn HttpResponse>::from(BadRequestError::with_message(format!(
+                    "Invalid header: {}",
+                    SEC_WEBSOCKET_KEY_HEADER
+                )))
+            })?;
+
+        let protocols = req
+            .headers()
+            .get::<String>(SEC_WEBSOCKET_PROTOCOL_HEADER)
+            .map(|value| {
+                value
+                    .split(',')
+                    .map(|protocol| protocol.trim().to_string())
+                    .filter(|protocol| !protocol.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(WebSocketUpgrade {
+            key,
+            protocols,
+            #[cfg(feature = "websocket-deflate")]
+            extensions: req.headers().get::<String>(SEC_WEBSOCKET_EXTENSIONS_HEADER),
+            config: WebSocketConfig::default(),
+        })
+    }
+}
+
+impl WebSocketUpgrade {
+    /// Sets the configuration of the connection, such as its message size limit.
+    pub fn with_config(mut self, config: WebSocketConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Accepts the handshake with 101 Switching Protocols. Once the response is sent, the
+    /// connection is passed to `callback` as a [`WebSocket`].
+    pub fn on_upgrade<F, Fut>(self, callback: F) -> Box<dyn HttpResponse>
+    where
+        F: FnOnce(WebSocket) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let protocol = self
+            .config
+            .protocols
+            .iter()
+            .find(|supported| self.protocols.contains(supported))
+            .cloned();
+
+        #[cfg(feature = "websocket-deflate")]
+        let deflate = self
+            .extensions
+            .as_deref()
+            .filter(|_| self.config.compression)
+            .and_then(super::deflate::negotiate);
+
+        let mut response = SwitchingProtocolsResponse::new({
+            let protocol = protocol.clone();
+            #[cfg(feature = "websocket-deflate")]
+            let deflate = deflate.as_ref().map(|(params, _)| *params);
+
+            move |upgraded| {
+                let socket = WebSocket::new(
+                    upgraded,
+                    self.config,
+                    protocol,
+                    #[cfg(feature = "websocket-deflate")]
+                    deflate,
+                );
+                callback(socket)
+            }
+        })
+        .with_header(keys::UPGRADE_HEADER, "websocket")
+        .with_header(keys::CONNECTION_HEADER, "Upgrade")
+        .with_header(SEC_WEBSOCKET_ACCEPT_HEADER, &accept_key(&self.key));
+
+        if let Some(protocol) = &protocol {
+            response = response.with_header(SEC_WEBSOCKET_PROTOCOL_HEADER, protocol);
+        }
+
+        #[cfg(feature = "websocket-deflate")]
+        if let Some((_, extension)) = &deflate {
+            response = response.with_header(SEC_WEBSOCKET_EXTENSIONS_HEADER, extension);
+        }
+
+        Box::new(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accept_key() {
+        // Example from RFC 6455, section 1.3
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+}
@@ -0,0 +1,143 @@
+//! WebSocket support, available with the `websocket` feature.
+//!
+//! [`Router::ws`](crate::Router::ws) registers an endpoint that performs the RFC 6455
+//! handshake and hands each connection to a handler as a [`WebSocket`]. Handlers that need
+//! the request, such as its path parameters or state, can instead take a
+//! [`WebSocketUpgrade`] next to other extractors and call
+//! [`WebSocketUpgrade::on_upgrade`].
+//!
+//! Messages compressed with permessage-deflate are supported with the `websocket-deflate`
+//! feature.
+
+#[cfg(feature = "websocket-deflate")]
+mod deflate;
+mod frame;
+mod handshake;
+mod socket;
+
+pub use handshake::WebSocketUpgrade;
+pub use socket::WebSocket;
+
+/// The default limit on the size of a received message, in bytes.
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 64 << 20;
+
+/// A message sent or received over a [`WebSocket`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    /// A ping, which is answered with a pong automatically when received.
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    /// The closing handshake, with an optional code and reason.
+    Close(Option<CloseFrame>),
+}
+
+impl From<String> for Message {
+    fn from(text: String) -> Self {
+        Message::Text(text)
+    }
+}
+
+impl From<&str> for Message {
+    fn from(text: &str) -> Self {
+        Message::Text(text.to_string())
+    }
+}
+
+impl From<Vec<u8>> for Message {
+    fn from(data: Vec<u8>) -> Self {
+        Message::Binary(data)
+    }
+}
+
+/// The status code and reason of a close message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CloseFrame {
+    pub code: CloseCode,
+    pub reason: String,
+}
+
+impl CloseFrame {
+    /// Creates a new CloseFrame with the given code and reason.
+    pub fn new<S: Into<String>>(code: CloseCode, reason: S) -> Self {
+        CloseFrame {
+            code,
+            reason: reason.into(),
+        }
+    }
+}
+
+/// A status code sent when closing a WebSocket, as defined by RFC 6455, section 7.4.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct CloseCode(pub u16);
+
+impl CloseCode {
+    pub const NORMAL: CloseCode = CloseCode(1000);
+    pub const GOING_AWAY: CloseCode = CloseCode(1001);
+    pub const PROTOCOL_ERROR: CloseCode = CloseCode(1002);
+    pub const UNSUPPORTED_DATA: CloseCode = CloseCode(1003);
+    pub const INVALID_DATA: CloseCode = CloseCode(1007);
+    pub const POLICY_VIOLATION: CloseCode = CloseCode(1008);
+    pub const MESSAGE_TOO_BIG: CloseCode = CloseCode(1009);
+    pub const INTERNAL_ERROR: CloseCode = CloseCode(1011);
+
+    /// Returns whether the code may be sent in a close frame. Codes such as 1005 and 1006
+    /// are reserved to report a missing or abnormal close locally.
+    pub fn is_allowed(&self) -> bool {
+        matches!(self.0, 1000..=1003 | 1007..=1011 | 3000..=4999)
+    }
+}
+
+/// Settings of the WebSocket connections accepted by an endpoint.
+#[derive(Debug, Clone)]
+pub struct WebSocketConfig {
+    max_message_size: usize,
+    protocols: Vec<String>,
+    #[cfg(feature = "websocket-deflate")]
+    compression: bool,
+}
+
+impl Default for WebSocketConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WebSocketConfig {
+    /// Creates a new WebSocketConfig with a 64 MiB message limit and no subprotocols.
+    pub fn new() -> Self {
+        WebSocketConfig {
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            protocols: Vec::new(),
+            #[cfg(feature = "websocket-deflate")]
+            compression: true,
+        }
+    }
+
+    /// Sets the largest message accepted from a client, after reassembling fragments and
+    /// decompressing. Larger messages close the connection with
+    /// [`CloseCode::MESSAGE_TOO_BIG`].
+    pub fn with_max_message_size(mut self, max_message_size: usize) -> Self {
+        self.max_message_size = max_message_size;
+        self
+    }
+
+    /// Sets the subprotocols supported by the server, in order of preference. The first
+    /// one also offered by the client is selected, see [`WebSocket::protocol`].
+    pub fn with_protocols<S: Into<String>, I: IntoIterator<Item = S>>(
+        mut self,
+        protocols: I,
+    ) -> Self {
+        self.protocols = protocols.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Enables or disables permessage-deflate compression, which is enabled by default
+    /// and used when the client offers it.
+    #[cfg(feature = "websocket-deflate")]
+    pub fn with_compression(mut self, compression: bool) -> Self {
+        self.compression = compression;
+        self
+    }
+}
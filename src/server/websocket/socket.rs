@@ -0,0 +1,449 @@
+use std::{io, time::Duration};
+
+use tokio::io::{AsyncWriteExt, BufReader};
+
+#[cfg(feature = "websocket-deflate")]
+use super::deflate::{Deflate, DeflateParams};
+use super::{
+    CloseCode, CloseFrame, Message, WebSocketConfig,
+    frame::{self, FrameError, MAX_CONTROL_PAYLOAD, OpCode},
+};
+use crate::server::upgrade::Upgraded;
+
+/// How long [`WebSocket::close`] waits for the client to answer the closing handshake.
+const CLOSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum State {
+    Open,
+    /// A close frame was sent and the answer of the client is awaited.
+    CloseSent,
+    Closed,
+}
+
+/// A message split across several frames, received so far.
+struct Fragments {
+    opcode: OpCode,
+    compressed: bool,
+    data: Vec<u8>,
+}
+
+/// A WebSocket connection, which sends and receives whole messages.
+///
+/// Fragmented messages are reassembled, pings are answered automatically, and a close
+/// message from the client is echoed before it is returned. Clients breaking the protocol
+/// are disconnected with the matching [`CloseCode`].
+pub struct WebSocket {
+    stream: BufReader<Upgraded>,
+    config: WebSocketConfig,
+    protocol: Option<String>,
+    #[cfg(feature = "websocket-deflate")]
+    deflate: Option<Deflate>,
+    fragments: Option<Fragments>,
+    state: State,
+}
+
+impl WebSocket {
+    pub(super) fn new(
+        upgraded: Upgraded,
+        config: WebSocketConfig,
+        protocol: Option<String>,
+        #[cfg(feature = "websocket-deflate")] deflate: Option<DeflateParams>,
+    ) -> Self {
+        WebSocket {
+            stream: BufReader::new(upgraded),
+            config,
+            protocol,
+            #[cfg(feature = "websocket-deflate")]
+            deflate: deflate.map(Deflate::new),
+            fragments: None,
+            state: State::Open,
+        }
+    }
+
+    /// Returns the subprotocol selected during the handshake, if any.
+    pub fn protocol(&self) -> Option<&str> {
+        self.protocol.as_deref()
+    }
+
+    /// Receives the next message, or `None` once the connection is closed.
+    pub async fn recv(&mut self) -> Option<io::Result<Message>> {
+        if self.state == State::Closed {
+            return None;
+        }
+
+        Some(self.read_message().await)
+    }
+
+    /// Sends a message. Sending [`Message::Close`] starts the closing handshake, after
+    /// which only the answer of the client can be received.
+    pub async fn send<M: Into<Message>>(&mut self, message: M) -> io::Result<()> {
+        if self.state != State::Open {
+            return Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "WebSocket is closed",
+            ));
+        }
+
+        match message.into() {
+            Message::Text(text) => self.write_data(OpCode::Text, text.into_bytes()).await,
+            Message::Binary(data) => self.write_data(OpCode::Binary, data).await,
+            Message::Ping(data) => self.write_control(OpCode::Ping, &data).await,
+            Message::Pong(data) => self.write_control(OpCode::Pong, &data).await,
+            Message::Close(frame) => {
+                let payload = close_payload(frame)?;
+                self.write_control(OpCode::Close, &payload).await?;
+                self.state = State::CloseSent;
+                Ok(())
+            }
+        }
+    }
+
+    /// Closes the connection, waiting a few seconds for the client to answer the closing
+    /// handshake. Messages received meanwhile are discarded.
+    pub async fn close(&mut self, frame: Option<CloseFrame>) -> io::Result<()> {
+        if self.state == State::Open {
+            self.send(Message::Close(frame)).await?;
+        }
+
+        let drain = async {
+            while let Some(message) = self.recv().await {
+                message?;
+            }
+            Ok(())
+        };
+
+        match tokio::time::timeout(CLOSE_TIMEOUT, drain).await {
+            Ok(result) => result,
+            Err(_) => {
+                self.shutdown().await;
+                Ok(())
+            }
+        }
+    }
+
+    async fn read_message(&mut self) -> io::Result<Message> {
+        loop {
+            let buffered = self.fragments.as_ref().map_or(0, |f| f.data.len());
+            let max_payload = self.config.max_message_size.saturating_sub(buffered);
+
+            let frame = match frame::read(&mut self.stream, max_payload).await {
+                Ok(frame) => frame,
+                Err(error) => return Err(self.fail(error).await),
+            };
+
+            match frame.opcode {
+                OpCode::Ping => {
+                    if self.state == State::Open {
+                        self.write(OpCode::Pong, false, &frame.payload).await?;
+                    }
+                    return Ok(Message::Ping(frame.payload));
+                }
+                OpCode::Pong => return Ok(Message::Pong(frame.payload)),
+                OpCode::Close => return self.receive_close(&frame.payload).await,
+                OpCode::Continuation => {
+                    let Some(fragments) = self.fragments.as_mut() else {
+                        return Err(self.fail(protocol_error("Unexpected continuation")).await);
+                    };
+
+                    if frame.compressed {
+                        return Err(self
+                            .fail(protocol_error("Continuation frames must not set RSV1"))
+                            .await);
+                    }
+
+                    fragments.data.extend_from_slice(&frame.payload);
+
+                    if frame.fin
+                        && let Some(fragments) = self.fragments.take()
+                    {
+                        return self
+                            .complete(fragments.opcode, fragments.compressed, fragments.data)
+                            .await;
+                    }
+                }
+                OpCode::Text | OpCode::Binary => {
+                    if self.fragments.is_some() {
+                        return Err(self.fail(protocol_error("Expected a continuation")).await);
+                    }
+
+                    if frame.fin {
+                        return self
+                            .complete(frame.opcode, frame.compressed, frame.payload)
+                            .await;
+                    }
+
+                    self.fragments = Some(Fragments {
+                        opcode: frame.opcode,
+                        compressed: frame.compressed,
+                        data: frame.payload,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Builds a data message once all of its frames were received.
+    async fn complete(
+        &mut self,
+        opcode: OpCode,
+        compressed: bool,
+        data: Vec<u8>,
+    ) -> io::Result<Message> {
+        let data = if compressed {
+            match self.inflate(&data) {
+                Ok(data) => data,
+                Err(error) => return Err(self.fail(error).await),
+            }
+        } else {
+            data
+        };
+
+        if opcode == OpCode::Binary {
+            return Ok(Message::Binary(data));
+        }
+
+        match String::from_utf8(data) {
+            Ok(text) => Ok(Message::Text(text)),
+            Err(_) => Err(self
+                .fail(FrameError::Protocol(
+                    CloseCode::INVALID_DATA,
+                    "Text message is not valid UTF-8",
+                ))
+                .await),
+        }
+    }
+
+    async fn receive_close(&mut self, payload: &[u8]) -> io::Result<Message> {
+        let frame = match payload {
+            [] => None,
+            [_] => return Err(self.fail(protocol_error("Invalid close frame")).await),
+            [high, low, reason @ ..] => {
+                let code = CloseCode(u16::from_be_bytes([*high, *low]));
+                if !code.is_allowed() {
+                    return Err(self.fail(protocol_error("Invalid close code")).await);
+                }
+
+                let Ok(reason) = std::str::from_utf8(reason) else {
+                    return Err(self
+                        .fail(FrameError::Protocol(
+                            CloseCode::INVALID_DATA,
+                            "Close reason is not valid UTF-8",
+                        ))
+                        .await);
+                };
+
+                Some(CloseFrame::new(code, reason))
+            }
+        };
+
+        // Answer with the same code, unless this is already the answer to our close
+        if self.state == State::Open {
+            let payload = frame
+                .as_ref()
+                .map_or(Vec::new(), |frame| frame.code.0.to_be_bytes().to_vec());
+            let _ = self.write(OpCode::Close, false, &payload).await;
+        }
+
+        self.shutdown().await;
+        Ok(Message::Close(frame))
+    }
+
+    /// Closes the connection after a failure, telling the client why when the protocol
+    /// was broken.
+    async fn fail(&mut self, error: FrameError) -> io::Error {
+        match error {
+            FrameError::Io(error) => {
+                self.state = State::Closed;
+                error
+            }
+            FrameError::Protocol(code, reason) => {
+                if self.state == State::Open
+                    && let Ok(payload) = close_payload(Some(CloseFrame::new(code, reason)))
+                {
+                    let _ = self.write(OpCode::Close, false, &payload).await;
+                }
+
+                self.shutdown().await;
+                io::Error::new(io::ErrorKind::InvalidData, reason)
+            }
+        }
+    }
+
+    async fn shutdown(&mut self) {
+        self.state = State::Closed;
+        let _ = self.stream.shutdown().await;
+    }
+
+    #[cfg(feature = "websocket-deflate")]
+    fn inflate(&mut self, data: &[u8]) -> Result<Vec<u8>, FrameError> {
+        match self.deflate.as_mut() {
+            Some(deflate) => deflate.decompress(data, self.config.max_message_size),
+            None => Err(protocol_error("Compression was not negotiated")),
+        }
+    }
+
+    #[cfg(not(feature = "websocket-deflate"))]
+    fn inflate(&mut self, _data: &[u8]) -> Result<Vec<u8>, FrameError> {
+        Err(protocol_error("Compression was not negotiated"))
+    }
+
+    async fn write_data(&mut self, opcode: OpCode, data: Vec<u8>) -> io::Result<()> {
+        #[cfg(feature = "websocket-deflate")]
+        if let Some(deflate) = self.deflate.as_mut() {
+            let compressed = deflate.compress(&data);
+            return self.write(opcode, true, &compressed).await;
+        }
+
+        self.write(opcode, false, &data).await
+    }
+
+    async fn write_control(&mut self, opcode: OpCode, payload: &[u8]) -> io::Result<()> {
+        if payload.len() > MAX_CONTROL_PAYLOAD {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Control messages must not exceed 125 bytes",
+            ));
+        }
+
+        self.write(opcode, false, payload).await
+    }
+
+    async fn write(&mut self, opcode: OpCode, compressed: bool, payload: &[u8]) -> io::Result<()> {
+        self.stream
+            .write_all(&frame::encode(opcode, compressed, payload))
+            .await?;
+        self.stream.flush().await
+    }
+}
+
+fn protocol_error(reason: &'static str) -> FrameError {
+    FrameError::Protocol(CloseCode::PROTOCOL_ERROR, reason)
+}
+
+fn close_payload(frame: Option<CloseFrame>) -> io::Result<Vec<u8>> {
+    let Some(frame) = frame else {
+        return Ok(Vec::new());
+    };
+
+    if !frame.code.is_allowed() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Close code is reserved",
+        ));
+    }
+
+    let mut payload = frame.code.0.to_be_bytes().to_vec();
+    payload.extend_from_slice(frame.reason.as_bytes());
+
+    if payload.len() > MAX_CONTROL_PAYLOAD {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Close reason must not exceed 123 bytes",
+        ));
+    }
+
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+
+    use super::*;
+    use crate::server::websocket::frame::client_frame;
+
+    fn socket(config: WebSocketConfig) -> (WebSocket, DuplexStream) {
+        let (client, server) = tokio::io::duplex(4096);
+        let upgraded = Upgraded::new(Box::new(server), Vec::new());
+        let socket = WebSocket::new(
+            upgraded,
+            config,
+            None,
+            #[cfg(feature = "websocket-deflate")]
+            None,
+        );
+        (socket, client)
+    }
+
+    async fn read_frame(client: &mut DuplexStream) -> Vec<u8> {
+        let mut head = [0; 2];
+        client.read_exact(&mut head).await.unwrap();
+        let mut payload = vec![0; (head[1] & 0x7f) as usize];
+        client.read_exact(&mut payload).await.unwrap();
+        [head.to_vec(), payload].concat()
+    }
+
+    #[tokio::test]
+    async fn test_fragmented_message_with_ping() {
+        let (mut socket, mut client) = socket(WebSocketConfig::new());
+
+        client.write_all(&client_frame(0x01, b"Hel")).await.unwrap();
+        client.write_all(&client_frame(0x89, b"p")).await.unwrap();
+        client.write_all(&client_frame(0x80, b"lo")).await.unwrap();
+
+        let message = socket.recv().await.unwrap().unwrap();
+        assert_eq!(message, Message::Ping(b"p".to_vec()));
+        assert_eq!(read_frame(&mut client).await, [0x8a, 0x01, b'p']);
+
+        let message = socket.recv().await.unwrap().unwrap();
+        assert_eq!(message, Message::Text("Hello".to_string()));
+
+        socket.send("Hi").await.unwrap();
+        assert_eq!(read_frame(&mut client).await, [0x81, 0x02, b'H', b'i']);
+    }
+
+    #[tokio::test]
+    async fn test_close_handshake() {
+        let (mut socket, mut client) = socket(WebSocketConfig::new());
+
+        client
+            .write_all(&client_frame(0x88, b"\x03\xe8bye"))
+            .await
+            .unwrap();
+
+        let message = socket.recv().await.unwrap().unwrap();
+        assert_eq!(
+            message,
+            Message::Close(Some(CloseFrame::new(CloseCode::NORMAL, "bye")))
+        );
+        assert_eq!(read_frame(&mut client).await, [0x88, 0x02, 0x03, 0xe8]);
+        assert!(socket.recv().await.is_none());
+        assert!(socket.send("late").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_message_too_big() {
+        let config = WebSocketConfig::new().with_max_message_size(10);
+        let (mut socket, mut client) = socket(config);
+
+        // Each fragment fits, but the whole message does not
+        client
+            .write_all(&client_frame(0x02, &[0; 8]))
+            .await
+            .unwrap();
+        client
+            .write_all(&client_frame(0x80, &[0; 8]))
+            .await
+            .unwrap();
+
+        assert!(socket.recv().await.unwrap().is_err());
+        let frame = read_frame(&mut client).await;
+        assert_eq!((frame[0], &frame[2..4]), (0x88, &[0x03, 0xf1][..]));
+        assert!(socket.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_utf8() {
+        let (mut socket, mut client) = socket(WebSocketConfig::new());
+
+        client
+            .write_all(&client_frame(0x81, &[0xff]))
+            .await
+            .unwrap();
+
+        assert!(socket.recv().await.unwrap().is_err());
+        let frame = read_frame(&mut client).await;
+        assert_eq!((frame[0], &frame[2..4]), (0x88, &[0x03, 0xef][..]));
+    }
+}